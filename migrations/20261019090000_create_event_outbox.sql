@@ -0,0 +1,18 @@
+-- Add migration script here
+DROP TABLE IF EXISTS apf_ru_event_outbox;
+
+CREATE TABLE apf_ru_event_outbox (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    seq BIGSERIAL NOT NULL,
+    proc_inst_id VARCHAR(255) NOT NULL,
+    event_type VARCHAR(255) NOT NULL,
+    payload TEXT NOT NULL,
+    create_time BIGINT NOT NULL,
+    status INT NOT NULL DEFAULT 0,
+    retries INT NOT NULL DEFAULT 0,
+    next_retry_time BIGINT NOT NULL,
+    delivered_time BIGINT NULL,
+    last_error VARCHAR(4000) NULL
+);
+
+CREATE INDEX apf_idx_event_outbox_status ON apf_ru_event_outbox (status, seq);
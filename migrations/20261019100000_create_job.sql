@@ -0,0 +1,21 @@
+-- Add migration script here
+DROP TABLE IF EXISTS apf_ru_job;
+
+CREATE TABLE apf_ru_job (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    rev INT DEFAULT NULL,
+    job_type VARCHAR(255) NOT NULL,
+    handler_cfg VARCHAR(4000) NULL,
+    proc_def_id VARCHAR(255) NOT NULL REFERENCES apf_re_procdef(id),
+    proc_inst_id VARCHAR(255) NOT NULL REFERENCES apf_ru_execution(id),
+    execution_id VARCHAR(255) NOT NULL REFERENCES apf_ru_execution(id),
+    element_id VARCHAR(255) NOT NULL,
+    retries INT NOT NULL DEFAULT 3,
+    due_time BIGINT NOT NULL,
+    lock_owner VARCHAR(255) NULL,
+    lock_expiration_time BIGINT NULL,
+    exception_msg VARCHAR(4000) NULL,
+    create_time BIGINT NOT NULL
+);
+CREATE INDEX apf_idx_job_due ON apf_ru_job (due_time);
+CREATE INDEX apf_idx_job_procinst ON apf_ru_job (proc_inst_id);
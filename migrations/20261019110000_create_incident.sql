@@ -0,0 +1,19 @@
+-- Add migration script here
+DROP TABLE IF EXISTS apf_ru_incident;
+
+CREATE TABLE apf_ru_incident (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    rev INT DEFAULT NULL,
+    incident_type VARCHAR(255) NOT NULL,
+    proc_def_id VARCHAR(255) NOT NULL REFERENCES apf_re_procdef(id),
+    proc_inst_id VARCHAR(255) NOT NULL REFERENCES apf_ru_execution(id),
+    execution_id VARCHAR(255) NOT NULL REFERENCES apf_ru_execution(id),
+    element_id VARCHAR(255) NOT NULL,
+    job_id VARCHAR(255) NULL,
+    error_msg VARCHAR(4000) NULL,
+    error_location VARCHAR(255) NULL,
+    error_stack TEXT NULL,
+    create_time BIGINT NOT NULL
+);
+CREATE INDEX apf_idx_incident_procinst ON apf_ru_incident (proc_inst_id);
+CREATE INDEX apf_idx_incident_job ON apf_ru_incident (job_id);
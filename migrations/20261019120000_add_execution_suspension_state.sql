@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE apf_ru_execution DROP COLUMN IF EXISTS suspension_state;
+
+ALTER TABLE apf_ru_execution ADD COLUMN suspension_state INT NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+-- Add migration script here
+ALTER TABLE apf_hi_procinst DROP COLUMN IF EXISTS end_user;
+ALTER TABLE apf_hi_procinst DROP COLUMN IF EXISTS delete_reason;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS delete_reason;
+
+ALTER TABLE apf_hi_procinst ADD COLUMN end_user VARCHAR(255) NULL;
+ALTER TABLE apf_hi_procinst ADD COLUMN delete_reason VARCHAR(4000) NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN delete_reason VARCHAR(4000) NULL;
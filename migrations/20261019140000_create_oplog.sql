@@ -0,0 +1,15 @@
+-- Add migration script here
+DROP TABLE IF EXISTS apf_hi_oplog;
+
+CREATE TABLE apf_hi_oplog (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    op_type VARCHAR(255) NOT NULL,
+    proc_def_id VARCHAR(255) NULL,
+    proc_inst_id VARCHAR(255) NULL,
+    task_id VARCHAR(255) NULL,
+    element_id VARCHAR(255) NULL,
+    user_id VARCHAR(255) NULL,
+    detail TEXT NULL,
+    create_time BIGINT NOT NULL
+);
+CREATE INDEX apf_idx_oplog_procinst ON apf_hi_oplog (proc_inst_id);
@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS assignee;
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS owner;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS assignee;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS owner;
+
+ALTER TABLE apf_ru_task ADD COLUMN assignee VARCHAR(255) NULL;
+ALTER TABLE apf_ru_task ADD COLUMN owner VARCHAR(255) NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN assignee VARCHAR(255) NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN owner VARCHAR(255) NULL;
+
+CREATE INDEX apf_idx_task_assignee ON apf_ru_task (assignee);
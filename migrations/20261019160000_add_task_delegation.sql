@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS delegation;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS delegation;
+
+ALTER TABLE apf_ru_task ADD COLUMN delegation VARCHAR(64) NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN delegation VARCHAR(64) NULL;
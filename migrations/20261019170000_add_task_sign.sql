@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS sign_parent_id;
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS sign_mode;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS sign_parent_id;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS sign_mode;
+
+ALTER TABLE apf_ru_task ADD COLUMN sign_parent_id VARCHAR(255) NULL;
+ALTER TABLE apf_ru_task ADD COLUMN sign_mode VARCHAR(64) NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN sign_parent_id VARCHAR(255) NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN sign_mode VARCHAR(64) NULL;
+
+CREATE INDEX apf_idx_task_sign_parent ON apf_ru_task (sign_parent_id);
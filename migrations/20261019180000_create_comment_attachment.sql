@@ -0,0 +1,32 @@
+-- Add migration script here
+DROP TABLE IF EXISTS apf_hi_comment;
+DROP TABLE IF EXISTS apf_hi_attachment;
+
+ALTER TABLE apf_ge_bytearray ALTER COLUMN deployment_id DROP NOT NULL;
+
+CREATE TABLE apf_hi_comment (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    comment_type VARCHAR(255) NOT NULL,
+    user_id VARCHAR(255) NULL,
+    task_id VARCHAR(255) NULL,
+    proc_inst_id VARCHAR(255) NULL,
+    message VARCHAR(4000) NULL,
+    create_time BIGINT NOT NULL
+);
+CREATE INDEX apf_idx_comment_task ON apf_hi_comment (task_id);
+CREATE INDEX apf_idx_comment_procinst ON apf_hi_comment (proc_inst_id);
+
+CREATE TABLE apf_hi_attachment (
+    id VARCHAR(255) NOT NULL PRIMARY KEY,
+    name VARCHAR(255) NULL,
+    description VARCHAR(4000) NULL,
+    attachment_type VARCHAR(255) NULL,
+    user_id VARCHAR(255) NULL,
+    task_id VARCHAR(255) NULL,
+    proc_inst_id VARCHAR(255) NULL,
+    url VARCHAR(4000) NULL,
+    content_id VARCHAR(255) NULL REFERENCES apf_ge_bytearray(id),
+    create_time BIGINT NOT NULL
+);
+CREATE INDEX apf_idx_attachment_task ON apf_hi_attachment (task_id);
+CREATE INDEX apf_idx_attachment_procinst ON apf_hi_attachment (proc_inst_id);
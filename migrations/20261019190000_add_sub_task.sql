@@ -0,0 +1,12 @@
+-- Add migration script here
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS parent_task_id;
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS block_parent;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS parent_task_id;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS block_parent;
+
+ALTER TABLE apf_ru_task ADD COLUMN parent_task_id VARCHAR(255) NULL;
+ALTER TABLE apf_ru_task ADD COLUMN block_parent BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE apf_hi_taskinst ADD COLUMN parent_task_id VARCHAR(255) NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN block_parent BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX apf_idx_task_parent ON apf_ru_task (parent_task_id);
@@ -0,0 +1,16 @@
+-- Add migration script here
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS priority;
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS due_date;
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS follow_up_date;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS priority;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS due_date;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS follow_up_date;
+
+ALTER TABLE apf_ru_task ADD COLUMN priority INT NOT NULL DEFAULT 50;
+ALTER TABLE apf_ru_task ADD COLUMN due_date BIGINT NULL;
+ALTER TABLE apf_ru_task ADD COLUMN follow_up_date BIGINT NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN priority INT NOT NULL DEFAULT 50;
+ALTER TABLE apf_hi_taskinst ADD COLUMN due_date BIGINT NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN follow_up_date BIGINT NULL;
+
+CREATE INDEX apf_idx_task_due_date ON apf_ru_task (due_date);
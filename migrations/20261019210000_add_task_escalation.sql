@@ -0,0 +1,8 @@
+-- Add migration script here
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS remind_count;
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS last_remind_time;
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS escalate_time;
+
+ALTER TABLE apf_ru_task ADD COLUMN remind_count INT NOT NULL DEFAULT 0;
+ALTER TABLE apf_ru_task ADD COLUMN last_remind_time BIGINT NULL;
+ALTER TABLE apf_ru_task ADD COLUMN escalate_time BIGINT NULL;
@@ -0,0 +1,6 @@
+-- Add migration script here
+DROP INDEX IF EXISTS apf_idx_ru_exec_start_user;
+DROP INDEX IF EXISTS apf_idx_hi_pro_inst_start_user;
+
+CREATE INDEX apf_idx_ru_exec_start_user ON apf_ru_execution (start_user);
+CREATE INDEX apf_idx_hi_pro_inst_start_user ON apf_hi_procinst (start_user);
@@ -0,0 +1,7 @@
+-- Add migration script here
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS prev_task_id;
+ALTER TABLE apf_hi_taskinst DROP COLUMN IF EXISTS prev_task_id;
+
+ALTER TABLE apf_ru_task ADD COLUMN prev_task_id VARCHAR(255) NULL;
+ALTER TABLE apf_hi_taskinst ADD COLUMN prev_task_id VARCHAR(255) NULL;
+CREATE INDEX apf_idx_hi_task_prev_task ON apf_hi_taskinst (prev_task_id);
@@ -0,0 +1,4 @@
+-- Add migration script here
+DROP INDEX IF EXISTS apf_idx_event_outbox_procinst;
+
+CREATE INDEX apf_idx_event_outbox_procinst ON apf_ru_event_outbox (proc_inst_id, seq);
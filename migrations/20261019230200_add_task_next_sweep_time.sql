@@ -0,0 +1,9 @@
+-- Add migration script here
+DROP INDEX IF EXISTS apf_idx_task_next_sweep_time;
+ALTER TABLE apf_ru_task DROP COLUMN IF EXISTS next_sweep_time;
+
+ALTER TABLE apf_ru_task ADD COLUMN next_sweep_time BIGINT NULL;
+CREATE INDEX apf_idx_task_next_sweep_time ON apf_ru_task (next_sweep_time);
+
+-- the sweeper looks at the overdue tasks once more, and leaves the ones without escalation
+UPDATE apf_ru_task SET next_sweep_time = due_date WHERE due_date IS NOT NULL;
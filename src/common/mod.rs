@@ -2,6 +2,7 @@ pub mod db;
 pub mod string_builder;
 pub mod utils;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 use once_cell::sync::OnceCell;
 use serde::Deserialize;
@@ -16,6 +17,7 @@ pub struct Configure {
     pub server: Server,
     pub database: Database,
     pub profile: String,
+    pub engine: Option<Engine>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,6 +38,16 @@ pub struct Database {
     pub dbname: Option<String>,
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct Engine {
+    pub task_notify: Option<bool>,
+    pub task_notify_channel: Option<String>,
+    pub job_retries: Option<i32>,
+    pub job_retry_backoff: Option<i64>,
+    /// name -> yaml file of the holidays and the make-up working days
+    pub business_calendars: Option<HashMap<String, String>>,
+}
+
 #[allow(dead_code)]
 pub fn global_cfg() -> &'static Arc<Configure> {
     CONFIG.get_or_init(|| {
@@ -110,7 +110,7 @@ mod tests {
         let obj1 = create_test_bytearray(&tran).await.unwrap();
 
         let dao = ApfGeBytearrayDao::new(&tran);
-        dao.get_by_deployment_id(&obj1.deployment_id).await.unwrap();
+        dao.get_by_deployment_id(obj1.deployment_id.as_ref().unwrap()).await.unwrap();
 
         tran.rollback().await.unwrap();
     }
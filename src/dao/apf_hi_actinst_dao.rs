@@ -114,6 +114,62 @@ impl<'a> ApfHiActinstDao<'a> {
         Ok(r)
     }
 
+    /// Closes the unfinished activities of the process instance.
+    pub async fn close_by_proc_inst(&self, proc_inst_id: &str, end_time: i64, end_user_id: Option<String>) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_actinst
+            set rev = rev + 1,
+                end_time = $1,
+                duration = $1 - start_time,
+                end_user_id = $2
+            where proc_inst_id = $3
+                and end_time is null
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&end_time, &end_user_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    /// Closes the unfinished activities of the execution.
+    pub async fn close_by_execution(&self, execution_id: &str, end_time: i64, end_user_id: Option<String>) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_actinst
+            set rev = rev + 1,
+                end_time = $1,
+                duration = $1 - start_time,
+                end_user_id = $2
+            where execution_id = $3
+                and end_time is null
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&end_time, &end_user_id, &execution_id]).await?;
+
+        Ok(r)
+    }
+
+    /// Finds the finished activities of the element in the process instance.
+    pub async fn find_finished_by_element(&self, proc_inst_id: &str, element_id: &str) -> Result<Vec<ApfHiActinst>> {
+        let sql = r#"
+            select id, rev, proc_def_id, proc_inst_id, execution_id,
+                task_id, element_id, element_name, element_type,
+                start_user_id, end_user_id, start_time, end_time, duration
+            from apf_hi_actinst
+            where proc_inst_id = $1
+                and element_id = $2
+                and end_time is not null
+            order by start_time desc
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id, &element_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfHiActinst::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfHiActinst>>();
+
+        Ok(rst)
+    }
+
     #[allow(unused)]
     pub async fn get_by_id(&self, id: &str) -> Result<ApfHiActinst> {
         let sql = r#"
@@ -145,6 +201,36 @@ impl<'a> ApfHiActinstDao<'a> {
 
         Ok(rst)
     }
+
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_actinst
+            set proc_def_id = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    /// Updates the unfinished activities of the execution.
+    pub async fn update_open_element(&self, execution_id: &str, element_id: &str, element_name: Option<String>) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_actinst
+            set element_id = $1,
+                element_name = $2,
+                rev = rev + 1
+            where execution_id = $3
+                and end_time is null
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&element_id, &element_name, &execution_id]).await?;
+
+        Ok(r)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,144 @@
+use color_eyre::Result;
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_postgres::Transaction;
+use crate::{model::{ApfHiAttachment, NewApfHiAttachment}, gen_id};
+use super::{BaseDao, Dao};
+
+pub struct ApfHiAttachmentDao<'a> {
+    base_dao: BaseDao<'a>
+}
+
+impl<'a> Dao for ApfHiAttachmentDao<'a> {
+
+    fn tran(&self) -> &Transaction {
+        self.base_dao.tran()
+    }
+}
+
+impl<'a> ApfHiAttachmentDao<'a> {
+
+    pub fn new(tran: &'a Transaction<'a>) -> Self {
+        Self {
+            base_dao: BaseDao::new(tran)
+        }
+    }
+
+    pub async fn create(&self, obj: &NewApfHiAttachment) -> Result<ApfHiAttachment> {
+        let sql = r#"
+            insert into apf_hi_attachment (
+                name, description, attachment_type, user_id, task_id, 
+                proc_inst_id, url, content_id, create_time, id
+            ) values (
+                $1, $2, $3, $4, $5, 
+                $6, $7, $8, $9, $10
+            )
+            returning *
+        "#;
+        let new_id = gen_id();
+        let stmt = self.tran().prepare(sql).await?;
+        let row = self
+            .tran()
+            .query_one(
+                &stmt, 
+                &[
+                    &obj.name,
+                    &obj.description,
+                    &obj.attachment_type,
+                    &obj.user_id,
+                    &obj.task_id,
+                    &obj.proc_inst_id,
+                    &obj.url,
+                    &obj.content_id,
+                    &obj.create_time,
+                    &new_id,
+                ]
+            )
+            .await?;
+        let rst = ApfHiAttachment::from_row(row)?;
+
+        Ok(rst)
+    }
+
+    pub async fn get_by_id(&self, id: &str) -> Result<ApfHiAttachment> {
+        let sql = r#"
+            select id, name, description, attachment_type, user_id, 
+                task_id, proc_inst_id, url, content_id, create_time
+            from apf_hi_attachment
+            where id = $1
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let row = self.tran().query_one(&stmt, &[&id]).await?;
+        let rst = ApfHiAttachment::from_row(row)?;
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_task(&self, task_id: &str) -> Result<Vec<ApfHiAttachment>> {
+        let sql = r#"
+            select id, name, description, attachment_type, user_id, 
+                task_id, proc_inst_id, url, content_id, create_time
+            from apf_hi_attachment
+            where task_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&task_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfHiAttachment::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfHiAttachment>>();
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_proc_inst(&self, proc_inst_id: &str) -> Result<Vec<ApfHiAttachment>> {
+        let sql = r#"
+            select id, name, description, attachment_type, user_id, 
+                task_id, proc_inst_id, url, content_id, create_time
+            from apf_hi_attachment
+            where proc_inst_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfHiAttachment::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfHiAttachment>>();
+
+        Ok(rst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::db;
+    use crate::get_now;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_find() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let obj = NewApfHiAttachment {
+            name: Some("invoice.pdf".to_owned()),
+            attachment_type: Some("application/pdf".to_owned()),
+            user_id: Some("user_1".to_owned()),
+            task_id: Some("test_task_1".to_owned()),
+            proc_inst_id: Some("test_proc_inst_1".to_owned()),
+            url: Some("https://example.com/invoice.pdf".to_owned()),
+            create_time: get_now(),
+            ..Default::default()
+        };
+        let attachment_dao = ApfHiAttachmentDao::new(&tran);
+        let attachment = attachment_dao.create(&obj).await.unwrap();
+
+        let rst = attachment_dao.get_by_id(&attachment.id).await.unwrap();
+        assert_eq!(rst.url, obj.url);
+        let rst = attachment_dao.find_by_task("test_task_1").await.unwrap();
+        assert_eq!(rst.len(), 1);
+
+        tran.rollback().await.unwrap();
+    }
+}
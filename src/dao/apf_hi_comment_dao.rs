@@ -0,0 +1,126 @@
+use color_eyre::Result;
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_postgres::Transaction;
+use crate::{model::{ApfHiComment, NewApfHiComment}, gen_id};
+use super::{BaseDao, Dao};
+
+pub struct ApfHiCommentDao<'a> {
+    base_dao: BaseDao<'a>
+}
+
+impl<'a> Dao for ApfHiCommentDao<'a> {
+
+    fn tran(&self) -> &Transaction {
+        self.base_dao.tran()
+    }
+}
+
+impl<'a> ApfHiCommentDao<'a> {
+
+    pub fn new(tran: &'a Transaction<'a>) -> Self {
+        Self {
+            base_dao: BaseDao::new(tran)
+        }
+    }
+
+    pub async fn create(&self, obj: &NewApfHiComment) -> Result<ApfHiComment> {
+        let sql = r#"
+            insert into apf_hi_comment (
+                comment_type, user_id, task_id, proc_inst_id, message, 
+                create_time, id
+            ) values (
+                $1, $2, $3, $4, $5, 
+                $6, $7
+            )
+            returning *
+        "#;
+        let new_id = gen_id();
+        let stmt = self.tran().prepare(sql).await?;
+        let row = self
+            .tran()
+            .query_one(
+                &stmt, 
+                &[
+                    &obj.comment_type,
+                    &obj.user_id,
+                    &obj.task_id,
+                    &obj.proc_inst_id,
+                    &obj.message,
+                    &obj.create_time,
+                    &new_id,
+                ]
+            )
+            .await?;
+        let rst = ApfHiComment::from_row(row)?;
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_task(&self, task_id: &str) -> Result<Vec<ApfHiComment>> {
+        let sql = r#"
+            select id, comment_type, user_id, task_id, proc_inst_id, 
+                message, create_time
+            from apf_hi_comment
+            where task_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&task_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfHiComment::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfHiComment>>();
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_proc_inst(&self, proc_inst_id: &str) -> Result<Vec<ApfHiComment>> {
+        let sql = r#"
+            select id, comment_type, user_id, task_id, proc_inst_id, 
+                message, create_time
+            from apf_hi_comment
+            where proc_inst_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfHiComment::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfHiComment>>();
+
+        Ok(rst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::db;
+    use crate::get_now;
+    use crate::model::CommentType;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_find() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let obj = NewApfHiComment {
+            comment_type: CommentType::COMMENT.to_owned(),
+            user_id: Some("user_1".to_owned()),
+            task_id: Some("test_task_1".to_owned()),
+            proc_inst_id: Some("test_proc_inst_1".to_owned()),
+            message: Some("同意，请注意预算".to_owned()),
+            create_time: get_now(),
+        };
+        let comment_dao = ApfHiCommentDao::new(&tran);
+        comment_dao.create(&obj).await.unwrap();
+
+        let rst = comment_dao.find_by_task("test_task_1").await.unwrap();
+        assert_eq!(rst.len(), 1);
+        let rst = comment_dao.find_by_proc_inst("test_proc_inst_1").await.unwrap();
+        assert_eq!(rst[0].message, Some("同意，请注意预算".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+}
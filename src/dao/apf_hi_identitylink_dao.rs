@@ -69,6 +69,19 @@ impl<'a> ApfHiIdentitylinkDao<'a> {
         let rst = self.create(&new_hi_ident).await?;
         Ok(rst)
     }
+
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_identitylink
+            set proc_def_id = $1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,109 @@
+use color_eyre::Result;
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_postgres::Transaction;
+use crate::{model::{ApfHiOplog, NewApfHiOplog}, gen_id};
+use super::{BaseDao, Dao};
+
+pub struct ApfHiOplogDao<'a> {
+    base_dao: BaseDao<'a>
+}
+
+impl<'a> Dao for ApfHiOplogDao<'a> {
+
+    fn tran(&self) -> &Transaction {
+        self.base_dao.tran()
+    }
+}
+
+impl<'a> ApfHiOplogDao<'a> {
+
+    pub fn new(tran: &'a Transaction<'a>) -> Self {
+        Self {
+            base_dao: BaseDao::new(tran)
+        }
+    }
+
+    pub async fn create(&self, obj: &NewApfHiOplog) -> Result<ApfHiOplog> {
+        let sql = r#"
+            insert into apf_hi_oplog (
+                op_type, proc_def_id, proc_inst_id, task_id, element_id, 
+                user_id, detail, create_time, id
+            ) values (
+                $1, $2, $3, $4, $5, 
+                $6, $7, $8, $9
+            )
+            returning *
+        "#;
+        let new_id = gen_id();
+        let stmt = self.tran().prepare(sql).await?;
+        let row = self
+            .tran()
+            .query_one(
+                &stmt, 
+                &[
+                    &obj.op_type,
+                    &obj.proc_def_id,
+                    &obj.proc_inst_id,
+                    &obj.task_id,
+                    &obj.element_id,
+                    &obj.user_id,
+                    &obj.detail,
+                    &obj.create_time,
+                    &new_id,
+                ]
+            )
+            .await?;
+        let rst = ApfHiOplog::from_row(row)?;
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_proc_inst(&self, proc_inst_id: &str) -> Result<Vec<ApfHiOplog>> {
+        let sql = r#"
+            select id, op_type, proc_def_id, proc_inst_id, task_id, 
+                element_id, user_id, detail, create_time
+            from apf_hi_oplog
+            where proc_inst_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfHiOplog::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfHiOplog>>();
+
+        Ok(rst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::db;
+    use crate::get_now;
+    use crate::model::OpType;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_find() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let obj = NewApfHiOplog {
+            op_type: OpType::MODIFY_PROCESS_INSTANCE.to_owned(),
+            proc_inst_id: Some("test_proc_inst_1".to_owned()),
+            element_id: Some("approval_1".to_owned()),
+            user_id: Some("admin".to_owned()),
+            create_time: get_now(),
+            ..Default::default()
+        };
+        let oplog_dao = ApfHiOplogDao::new(&tran);
+        oplog_dao.create(&obj).await.unwrap();
+
+        let rst = oplog_dao.find_by_proc_inst("test_proc_inst_1").await.unwrap();
+        assert_eq!(rst.len(), 1);
+        assert_eq!(rst[0].user_id, Some("admin".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+}
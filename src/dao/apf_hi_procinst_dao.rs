@@ -99,11 +99,43 @@ impl<'a> ApfHiProcinstDao<'a> {
         Ok(r)
     }
 
+    /// Marks the end of the process instance which is deleted before reaching an end event.
+    pub async fn mark_deleted(&self, id: &str, end_time: i64, end_user: Option<String>, delete_reason: Option<String>) -> Result<u64> {
+        let hi_procinst = self.get_by_id(id).await?;
+        let duration = end_time - hi_procinst.start_time;
+
+        let sql = r#"
+            update apf_hi_procinst
+            set rev = rev + 1,
+                end_time = $1,
+                duration = $2,
+                end_user = $3,
+                delete_reason = $4
+            where id = $5
+                and rev = $6
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&end_time, &duration, &end_user, &delete_reason, &id, &hi_procinst.rev]).await?;
+
+        if r != 1 {
+            Err(
+                AppError::new(
+                    ErrorCode::InternalError, 
+                    Some(&format!("apf_hi_procinst({}) is not updated correctly, affects ({}) != 1", hi_procinst.id, r)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        Ok(r)
+    }
+
     pub async fn get_by_id(&self, id: &str) -> Result<ApfHiProcinst> {
         let sql = r#"
             select id, rev, proc_inst_id, business_key,
                 proc_def_id, start_time, start_user, start_element_id,
-                end_time, duration, end_element_id
+                end_time, duration, end_element_id, end_user, delete_reason
             from apf_hi_procinst
             where id = $1
         "#;
@@ -113,6 +145,20 @@ impl<'a> ApfHiProcinstDao<'a> {
 
         Ok(rst)
     }
+
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_procinst
+            set proc_def_id = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
 }
 
 #[cfg(test)]
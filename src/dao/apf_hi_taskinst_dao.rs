@@ -44,6 +44,17 @@ impl<'a> ApfHiTaskinstDao<'a> {
             description: task.description.clone(),
             start_user_id: task.start_user_id.clone(),
             form_key: task.form_key.clone(),
+            assignee: task.assignee.clone(),
+            owner: task.owner.clone(),
+            delegation: task.delegation.clone(),
+            sign_parent_id: task.sign_parent_id.clone(),
+            sign_mode: task.sign_mode.clone(),
+            parent_task_id: task.parent_task_id.clone(),
+            block_parent: task.block_parent,
+            priority: task.priority,
+            due_date: task.due_date,
+            follow_up_date: task.follow_up_date,
+            prev_task_id: task.prev_task_id.clone(),
         };
 
         let rst = self.create(&new_hi_task).await?;
@@ -55,12 +66,18 @@ impl<'a> ApfHiTaskinstDao<'a> {
                 rev, execution_id, proc_inst_id, proc_def_id,
                 element_id, element_name, element_type, business_key,
                 description, start_user_id, start_time,
-                suspension_state, form_key, end_time, duration, id
+                suspension_state, form_key, end_time, duration,
+                assignee, owner, delegation, sign_parent_id,
+                sign_mode, parent_task_id, block_parent, priority,
+                due_date, follow_up_date, prev_task_id, id
             ) values (
                 $1, $2, $3, $4,
                 $5, $6, $7, $8,
                 $9, $10, $11, $12,
-                $13, $14, $15, $16
+                $13, $14, $15, $16,
+                $17, $18, $19, $20,
+                $21, $22, $23, $24,
+                $25, $26, $27
             )
             returning *
         "#;
@@ -85,6 +102,17 @@ impl<'a> ApfHiTaskinstDao<'a> {
                     &obj.form_key,
                     &obj.end_time,
                     &obj.duration,
+                    &obj.assignee,
+                    &obj.owner,
+                    &obj.delegation,
+                    &obj.sign_parent_id,
+                    &obj.sign_mode,
+                    &obj.parent_task_id,
+                    &obj.block_parent,
+                    &obj.priority,
+                    &obj.due_date,
+                    &obj.follow_up_date,
+                    &obj.prev_task_id,
                     &obj.id,
                 ]
             )
@@ -131,12 +159,146 @@ impl<'a> ApfHiTaskinstDao<'a> {
         Ok(r)
     }
 
+    /// Closes the task without completing it, e.g. the task is rejected or withdrawn.
+    pub async fn close(
+        &self, 
+        task_id: &str, 
+        end_time: i64, 
+        end_user_id: Option<String>, 
+        delete_reason: Option<String>
+    ) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_taskinst
+            set end_time = $1,
+                duration = $1 - start_time,
+                end_user_id = $2,
+                delete_reason = $3,
+                rev = rev + 1
+            where id = $4
+                and end_time is null
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&end_time, &end_user_id, &delete_reason, &task_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_assignee(&self, id: &str, assignee: Option<String>) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_taskinst
+            set assignee = $1,
+                rev = rev + 1
+            where id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&assignee, &id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_delegation(
+        &self,
+        id: &str,
+        owner: Option<String>,
+        assignee: Option<String>,
+        delegation: Option<String>
+    ) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_taskinst
+            set owner = $1,
+                assignee = $2,
+                delegation = $3,
+                rev = rev + 1
+            where id = $4
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&owner, &assignee, &delegation, &id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_priority(&self, id: &str, priority: i32) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_taskinst
+            set priority = $1,
+                rev = rev + 1
+            where id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&priority, &id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_dates(&self, id: &str, due_date: Option<i64>, follow_up_date: Option<i64>) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_taskinst
+            set due_date = $1,
+                follow_up_date = $2,
+                rev = rev + 1
+            where id = $3
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&due_date, &follow_up_date, &id]).await?;
+
+        Ok(r)
+    }
+
+    /// The tasks created when the given task was completed.
+    pub async fn find_by_prev_task(&self, prev_task_id: &str) -> Result<Vec<ApfHiTaskinst>> {
+        let sql = r#"
+            select id, rev, execution_id, proc_inst_id, proc_def_id,
+                element_id, element_name, element_type, business_key,
+                description, start_user_id, end_user_id, start_time,
+                suspension_state, form_key, end_time, duration, delete_reason,
+                assignee, owner, delegation, sign_parent_id, sign_mode,
+                parent_task_id, block_parent, priority, due_date, follow_up_date,
+                prev_task_id
+                from apf_hi_taskinst
+            where prev_task_id = $1
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&prev_task_id]).await?;
+        let rst = rows.iter()
+            .map(|row| ApfHiTaskinst::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfHiTaskinst>>();
+
+        Ok(rst)
+    }
+
+    /// Closes the unfinished tasks of the process instance.
+    pub async fn close_by_proc_inst(
+        &self, 
+        proc_inst_id: &str, 
+        end_time: i64, 
+        end_user_id: Option<String>, 
+        delete_reason: Option<String>
+    ) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_taskinst
+            set end_time = $1,
+                duration = $1 - start_time,
+                end_user_id = $2,
+                delete_reason = $3,
+                rev = rev + 1
+            where proc_inst_id = $4
+                and end_time is null
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&end_time, &end_user_id, &delete_reason, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
     pub async fn get_by_id(&self, id: &str) -> Result<ApfHiTaskinst> {
         let sql = r#"
             select id, rev, execution_id, proc_inst_id, proc_def_id,
                 element_id, element_name, element_type, business_key,
                 description, start_user_id, end_user_id, start_time,
-                suspension_state, form_key, end_time, duration
+                suspension_state, form_key, end_time, duration, delete_reason,
+                assignee, owner, delegation, sign_parent_id, sign_mode,
+                parent_task_id, block_parent, priority, due_date, follow_up_date,
+                prev_task_id
                 from apf_hi_taskinst
             where id = $1
         "#;
@@ -146,6 +308,34 @@ impl<'a> ApfHiTaskinstDao<'a> {
 
         Ok(rst)
     }
+
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_taskinst
+            set proc_def_id = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_element(&self, id: &str, element_id: &str, element_name: Option<String>) -> Result<u64> {
+        let sql = r#"
+            update apf_hi_taskinst
+            set element_id = $1,
+                element_name = $2,
+                rev = rev + 1
+            where id = $3
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&element_id, &element_name, &id]).await?;
+
+        Ok(r)
+    }
 }
 
 #[cfg(test)]
@@ -58,6 +58,7 @@ impl<'a> ApfReProcdefDao<'a> {
     pub async fn get_lastest_by_key(&self, key: &str, company_id: &str) -> Result<ApfReProcdef> {
         let where_sql = "where t1.key = $1
             and t1.company_id = $2
+            and t1.is_deleted = 0
             order by t1.version desc
             limit 1";
 
@@ -202,6 +203,38 @@ impl<'a> ApfReProcdefDao<'a> {
         Ok(rst)
     }
 
+    pub async fn update_suspension_state(&self, procdef_id: &str, suspension_state: i32, update_user_id: &str) -> Result<()> {
+        let tran = self.tran();
+        let procdef_dto = ProcdefDto {
+            id: Some(procdef_id.to_owned()),
+            suspension_state: Some(suspension_state),
+            update_user_id: Some(update_user_id.to_owned()),
+            update_time: Some(get_now()),
+            ..Default::default()
+        };
+
+        let rst = execute!(|&procdef_dto, &tran| {
+            "UPDATE apf_re_procdef 
+            SET suspension_state = :suspension_state,
+                update_user_id = :update_user_id,
+                update_time = :update_time
+            WHERE ID = :id"
+        })?;
+
+        if rst != 1 {
+            Err(
+                AppError::new(
+                    ErrorCode::NotFound, 
+                    Some(&format!("apf_re_procdef({}) is not exist", procdef_id)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        Ok(())
+    }
+
     pub async fn delete_by_id(&self, procdef_id: &str, update_user_id: &str) -> Result<()> {
         let tran = self.tran();
         let procdef_dto = ProcdefDto {
@@ -0,0 +1,244 @@
+use color_eyre::Result;
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_postgres::Transaction;
+use crate::{model::{ApfRuEventOutbox, NewApfRuEventOutbox, OutboxStatus}, gen_id};
+use crate::error::{AppError, ErrorCode};
+use super::{BaseDao, Dao};
+
+// key of the advisory lock, which makes only one dispatcher deliver the events at the same time
+const DISPATCH_LOCK_KEY: i64 = 20221019_0001;
+
+pub struct ApfRuEventOutboxDao<'a> {
+    base_dao: BaseDao<'a>
+}
+
+impl<'a> Dao for ApfRuEventOutboxDao<'a> {
+
+    fn tran(&self) -> &Transaction {
+        self.base_dao.tran()
+    }
+}
+
+impl<'a> ApfRuEventOutboxDao<'a> {
+
+    pub fn new(tran: &'a Transaction<'a>) -> Self {
+        Self {
+            base_dao: BaseDao::new(tran)
+        }
+    }
+
+    pub async fn create(&self, obj: &NewApfRuEventOutbox) -> Result<ApfRuEventOutbox> {
+        let sql = r#"
+            insert into apf_ru_event_outbox (
+                proc_inst_id, event_type, payload, create_time, status, 
+                retries, next_retry_time, id
+            ) values (
+                $1, $2, $3, $4, $5, 
+                $6, $7, $8
+            )
+            returning *
+        "#;
+
+        let new_id = gen_id();
+        let retries: i32 = 0;
+        let stmt = self.tran().prepare(sql).await?;
+        let row = self
+            .tran()
+            .query_one(
+                &stmt, 
+                &[
+                    &obj.proc_inst_id,
+                    &obj.event_type,
+                    &obj.payload,
+                    &obj.create_time,
+                    &OutboxStatus::PENDING,
+                    &retries,
+                    &obj.create_time,
+                    &new_id,
+                ]
+            )
+            .await?;
+        let rst = ApfRuEventOutbox::from_row(row)?;
+
+        Ok(rst)
+    }
+
+    pub async fn try_lock_dispatch(&self) -> Result<bool> {
+        let sql = r#"select pg_try_advisory_xact_lock($1)"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let row = self.tran().query_one(&stmt, &[&DISPATCH_LOCK_KEY]).await?;
+        let rst: bool = row.get(0);
+
+        Ok(rst)
+    }
+
+    /// Finds the events which can be delivered now, in the order they were written. The event
+    /// is held back while an earlier one of its process instance waits for a retry or has failed.
+    pub async fn find_pending(&self, now: i64, limit: i64) -> Result<Vec<ApfRuEventOutbox>> {
+        let sql = r#"
+            select t1.id, t1.seq, t1.proc_inst_id, t1.event_type, t1.payload, 
+                t1.create_time, t1.status, t1.retries, t1.next_retry_time, t1.delivered_time, 
+                t1.last_error
+            from apf_ru_event_outbox t1
+            where t1.status = $1
+                and t1.next_retry_time <= $2
+                and not exists (
+                    select 1 from apf_ru_event_outbox t2
+                    where t2.proc_inst_id = t1.proc_inst_id
+                        and t2.seq < t1.seq
+                        and (t2.status = $3 or (t2.status = $1 and t2.next_retry_time > $2))
+                )
+            order by t1.seq
+            limit $4
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&OutboxStatus::PENDING, &now, &OutboxStatus::FAILED, &limit]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuEventOutbox::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuEventOutbox>>();
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_proc_inst(&self, proc_inst_id: &str) -> Result<Vec<ApfRuEventOutbox>> {
+        let sql = r#"
+            select id, seq, proc_inst_id, event_type, payload, 
+                create_time, status, retries, next_retry_time, delivered_time, 
+                last_error
+            from apf_ru_event_outbox
+            where proc_inst_id = $1
+            order by seq
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuEventOutbox::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuEventOutbox>>();
+
+        Ok(rst)
+    }
+
+    pub async fn mark_delivered(&self, id: &str, delivered_time: i64) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_event_outbox
+            set status = $1, 
+                delivered_time = $2
+            where id = $3
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&OutboxStatus::DELIVERED, &delivered_time, &id]).await?;
+
+        Self::check_affected(id, r)
+    }
+
+    /// Records the delivery error, the event becomes `FAILED` when `status` is given.
+    pub async fn mark_error(&self, id: &str, status: i32, next_retry_time: i64, last_error: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_event_outbox
+            set status = $1, 
+                retries = retries + 1,
+                next_retry_time = $2,
+                last_error = $3
+            where id = $4
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&status, &next_retry_time, &last_error, &id]).await?;
+
+        Self::check_affected(id, r)
+    }
+
+    /// Puts the failed events of the process instance back to the queue.
+    pub async fn retry_failed(&self, proc_inst_id: &str, now: i64) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_event_outbox
+            set status = $1,
+                retries = 0,
+                next_retry_time = $2
+            where proc_inst_id = $3 and status = $4
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&OutboxStatus::PENDING, &now, &proc_inst_id, &OutboxStatus::FAILED]).await?;
+
+        Ok(r)
+    }
+
+    /// Gives up the failed event, the later events of its process instance are delivered.
+    pub async fn skip_failed(&self, id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_event_outbox
+            set status = $1
+            where id = $2 and status = $3
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&OutboxStatus::SKIPPED, &id, &OutboxStatus::FAILED]).await?;
+
+        Self::check_affected(id, r)
+    }
+
+    fn check_affected(id: &str, r: u64) -> Result<u64> {
+        if r != 1 {
+            Err(
+                AppError::new(
+                    ErrorCode::InternalError, 
+                    Some(&format!("apf_ru_event_outbox({}) is not updated correctly, affects ({}) != 1", id, r)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        Ok(r)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::common::db;
+    use crate::get_now;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_mark() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let event = create_test_event_outbox("test_outbox_proc_inst", &tran).await;
+        assert_eq!(event.status, OutboxStatus::PENDING);
+
+        let outbox_dao = ApfRuEventOutboxDao::new(&tran);
+        assert!(outbox_dao.try_lock_dispatch().await.unwrap());
+
+        let pending = outbox_dao.find_pending(get_now(), 1000).await.unwrap();
+        assert!(pending.iter().any(|e| e.id == event.id));
+
+        outbox_dao.mark_error(&event.id, OutboxStatus::PENDING, get_now() + 1000, "sink error").await.unwrap();
+        let pending = outbox_dao.find_pending(get_now(), 1000).await.unwrap();
+        assert!(!pending.iter().any(|e| e.id == event.id));
+
+        outbox_dao.mark_delivered(&event.id, get_now()).await.unwrap();
+
+        let events = outbox_dao.find_by_proc_inst("test_outbox_proc_inst").await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, OutboxStatus::DELIVERED);
+        assert_eq!(events[0].retries, 1);
+        assert_eq!(events[0].last_error, Some("sink error".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+
+    pub async fn create_test_event_outbox(proc_inst_id: &str, tran: &Transaction<'_>) -> ApfRuEventOutbox {
+        let obj = NewApfRuEventOutbox {
+            proc_inst_id: proc_inst_id.to_owned(),
+            event_type: "TaskCreated".to_owned(),
+            payload: "{}".to_owned(),
+            create_time: get_now(),
+        };
+
+        let outbox_dao = ApfRuEventOutboxDao::new(tran);
+        let rst = outbox_dao.create(&obj).await.unwrap();
+
+        rst
+    }
+}
@@ -156,11 +156,39 @@ impl<'a> ApfRuExecutionDao<'a> {
         Ok(r)
     }
 
+    /// Updates the process instance and all of its executions.
+    pub async fn update_suspension_state_by_proc_inst(&self, proc_inst_id: &str, suspension_state: i32) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_execution
+            set suspension_state = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&suspension_state, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    /// Updates all executions of the process definition.
+    pub async fn update_suspension_state_by_procdef(&self, proc_def_id: &str, suspension_state: i32) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_execution
+            set suspension_state = $1,
+                rev = rev + 1
+            where proc_def_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&suspension_state, &proc_def_id]).await?;
+
+        Ok(r)
+    }
+
     pub async fn get_by_id(&self, id: &str) -> Result<ApfRuExecution> {
         let sql = r#"
             select id, rev, proc_inst_id, business_key, parent_id, 
                 proc_def_id, root_proc_inst_id, element_id, is_active, start_time, 
-                start_user
+                start_user, suspension_state
             from apf_ru_execution 
             where id = $1
         "#;
@@ -171,6 +199,27 @@ impl<'a> ApfRuExecutionDao<'a> {
         Ok(rst)
     }
 
+    /// Finds the child executions of the process instance, the process instance itself is excluded.
+    pub async fn find_children_by_proc_inst(&self, proc_inst_id: &str) -> Result<Vec<ApfRuExecution>> {
+        let sql = r#"
+            select id, rev, proc_inst_id, business_key, parent_id, 
+                proc_def_id, root_proc_inst_id, element_id, is_active, start_time, 
+                start_user, suspension_state
+            from apf_ru_execution 
+            where proc_inst_id = $1
+                and id <> $1
+            order by start_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuExecution::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuExecution>>();
+
+        Ok(rst)
+    }
+
     pub async fn count_inactive_by_element(&self, proc_inst_id: &str, element_id: &str) -> Result<i64> {
         let sql = r#"
             select count(id) 
@@ -199,6 +248,15 @@ impl<'a> ApfRuExecutionDao<'a> {
         Ok(r)
     }
 
+    /// Deletes the process instance and all of its executions.
+    pub async fn delete_by_proc_inst_id(&self, proc_inst_id: &str) -> Result<u64> {
+        let sql = "delete from apf_ru_execution where proc_inst_id = $1 ";
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
     pub async fn delete(&self, id: &str) -> Result<u64> {
         let sql = "delete from apf_ru_execution where id = $1 ";
         let stmt = self.tran().prepare(sql).await?;
@@ -206,6 +264,33 @@ impl<'a> ApfRuExecutionDao<'a> {
 
         Ok(r)
     }
+
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_execution
+            set proc_def_id = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_element(&self, id: &str, element_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_execution
+            set element_id = $1,
+                rev = rev + 1
+            where id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&element_id, &id]).await?;
+
+        Ok(r)
+    }
 }
 
 #[cfg(test)]
@@ -60,6 +60,31 @@ impl<'a> ApfRuIdentitylinkDao<'a> {
         Ok(rst)
     }
 
+    pub async fn find_by_task_id(&self, task_id: &str) -> Result<Vec<ApfRuIdentitylink>> {
+        let sql = r#"
+            select id, rev, ident_type, group_id, user_id, 
+                task_id, proc_inst_id, proc_def_id
+            from apf_ru_identitylink
+            where task_id = $1
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&task_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuIdentitylink::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuIdentitylink>>();
+
+        Ok(rst)
+    }
+
+    pub async fn delete_by_proc_inst_id(&self, proc_inst_id: &str) -> Result<u64> {
+        let sql = r#"delete from apf_ru_identitylink where proc_inst_id = $1"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
     pub async fn delete_by_task_id(&self, task_id: &str)
             -> Result<u64> {
         let sql = r#" delete from apf_ru_identitylink where task_id = $1"#;
@@ -69,6 +94,19 @@ impl<'a> ApfRuIdentitylinkDao<'a> {
         Ok(r)
     }
 
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_identitylink
+            set proc_def_id = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
 }
 
 #[cfg(test)]
@@ -94,6 +132,9 @@ pub mod tests {
         assert_eq!(ru_ident.ident_type, IdentType::group);
 
         let ru_ident_dao = ApfRuIdentitylinkDao::new(&tran);
+        let rst = ru_ident_dao.find_by_task_id(&task.id).await.unwrap();
+        assert_eq!(rst, vec![ru_ident]);
+
         let rst = ru_ident_dao.delete_by_task_id(&task.id).await.unwrap();
         assert_eq!(rst, 1);
 
@@ -0,0 +1,229 @@
+use color_eyre::Result;
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_postgres::Transaction;
+
+use crate::error::{AppError, ErrorCode};
+use crate::{model::{ApfRuIncident, NewApfRuIncident}, gen_id};
+use super::{BaseDao, Dao};
+
+pub struct ApfRuIncidentDao<'a> {
+    base_dao: BaseDao<'a>
+}
+
+impl<'a> Dao for ApfRuIncidentDao<'a> {
+
+    fn tran(&self) -> &Transaction {
+        self.base_dao.tran()
+    }
+}
+
+impl<'a> ApfRuIncidentDao<'a> {
+
+    pub fn new(tran: &'a Transaction<'a>) -> Self {
+        Self {
+            base_dao: BaseDao::new(tran)
+        }
+    }
+
+    pub async fn create(&self, obj: &NewApfRuIncident) -> Result<ApfRuIncident> {
+        let sql = r#"
+            insert into apf_ru_incident (
+                rev, incident_type, proc_def_id, proc_inst_id, execution_id, 
+                element_id, job_id, error_msg, error_location, error_stack, 
+                create_time, id
+            ) values (
+                $1, $2, $3, $4, $5, 
+                $6, $7, $8, $9, $10, 
+                $11, $12
+            )
+            returning *
+        "#;
+        let new_id = gen_id();
+        let rev: i32 = 1;
+        let stmt = self.tran().prepare(sql).await?;
+        let row = self
+            .tran()
+            .query_one(
+                &stmt, 
+                &[
+                    &rev,
+                    &obj.incident_type,
+                    &obj.proc_def_id,
+                    &obj.proc_inst_id,
+                    &obj.execution_id,
+                    &obj.element_id,
+                    &obj.job_id,
+                    &obj.error_msg,
+                    &obj.error_location,
+                    &obj.error_stack,
+                    &obj.create_time,
+                    &new_id,
+                ]
+            )
+            .await?;
+        let rst = ApfRuIncident::from_row(row)?;
+
+        Ok(rst)
+    }
+
+    pub async fn get_by_id(&self, id: &str) -> Result<ApfRuIncident> {
+        let sql = r#"
+            select id, rev, incident_type, proc_def_id, proc_inst_id, 
+                execution_id, element_id, job_id, error_msg, error_location, 
+                error_stack, create_time
+            from apf_ru_incident
+            where id = $1
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&id]).await?;
+        if rows.len() == 0 {
+            Err(
+                AppError::new(
+                    ErrorCode::NotFound, 
+                    Some(&format!("apf_ru_incident({}) is not exist", id)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        let rst = ApfRuIncident::from_row_ref(&rows[0])?;
+
+        Ok(rst)
+    }
+
+    pub async fn find_all(&self) -> Result<Vec<ApfRuIncident>> {
+        let sql = r#"
+            select id, rev, incident_type, proc_def_id, proc_inst_id, 
+                execution_id, element_id, job_id, error_msg, error_location, 
+                error_stack, create_time
+            from apf_ru_incident
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuIncident::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuIncident>>();
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_proc_inst(&self, proc_inst_id: &str) -> Result<Vec<ApfRuIncident>> {
+        let sql = r#"
+            select id, rev, incident_type, proc_def_id, proc_inst_id, 
+                execution_id, element_id, job_id, error_msg, error_location, 
+                error_stack, create_time
+            from apf_ru_incident
+            where proc_inst_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuIncident::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuIncident>>();
+
+        Ok(rst)
+    }
+
+    pub async fn delete_by_execution_id(&self, execution_id: &str) -> Result<u64> {
+        let sql = r#"delete from apf_ru_incident where execution_id = $1"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&execution_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn delete_by_proc_inst_id(&self, proc_inst_id: &str) -> Result<u64> {
+        let sql = r#"delete from apf_ru_incident where proc_inst_id = $1"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn delete_by_job_id(&self, job_id: &str) -> Result<u64> {
+        let sql = r#"delete from apf_ru_incident where job_id = $1"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&job_id]).await?;
+
+        Ok(r)
+    }
+
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_incident
+            set proc_def_id = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_element_by_execution(&self, execution_id: &str, element_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_incident
+            set element_id = $1,
+                rev = rev + 1
+            where execution_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&element_id, &execution_id]).await?;
+
+        Ok(r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::db;
+    use crate::dao::apf_ru_execution_dao::tests::create_test_procinst;
+    use crate::dao::apf_ru_job_dao::tests::create_test_job;
+    use crate::get_now;
+    use crate::model::IncidentType;
+    use crate::service::engine::tests::create_test_deploy;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_and_delete() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process1.bpmn.xml", &tran).await;
+        let proc_inst = create_test_procinst(&procdef, &tran).await;
+        let job = create_test_job(&proc_inst, &tran).await;
+
+        let obj = NewApfRuIncident {
+            incident_type: IncidentType::FAILED_JOB.to_owned(),
+            proc_def_id: job.proc_def_id.clone(),
+            proc_inst_id: job.proc_inst_id.clone(),
+            execution_id: job.execution_id.clone(),
+            element_id: job.element_id.clone(),
+            job_id: Some(job.id.clone()),
+            error_msg: Some("error".to_owned()),
+            error_location: Some("test.rs:1".to_owned()),
+            error_stack: None,
+            create_time: get_now(),
+        };
+        let incident_dao = ApfRuIncidentDao::new(&tran);
+        let incident = incident_dao.create(&obj).await.unwrap();
+
+        let rst = incident_dao.get_by_id(&incident.id).await.unwrap();
+        assert_eq!(rst.error_location, Some("test.rs:1".to_owned()));
+
+        let rst = incident_dao.find_by_proc_inst(&proc_inst.id).await.unwrap();
+        assert_eq!(rst.len(), 1);
+
+        let rst = incident_dao.delete_by_job_id(&job.id).await.unwrap();
+        assert_eq!(rst, 1);
+
+        tran.rollback().await.unwrap();
+    }
+}
@@ -0,0 +1,424 @@
+use color_eyre::Result;
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_postgres::Transaction;
+
+use crate::error::{AppError, ErrorCode};
+use crate::{model::{ApfRuJob, NewApfRuJob, SuspensionState}, gen_id};
+use super::{BaseDao, Dao};
+
+pub struct ApfRuJobDao<'a> {
+    base_dao: BaseDao<'a>
+}
+
+impl<'a> Dao for ApfRuJobDao<'a> {
+
+    fn tran(&self) -> &Transaction {
+        self.base_dao.tran()
+    }
+}
+
+impl<'a> ApfRuJobDao<'a> {
+
+    pub fn new(tran: &'a Transaction<'a>) -> Self {
+        Self {
+            base_dao: BaseDao::new(tran)
+        }
+    }
+
+    pub async fn create(&self, obj: &NewApfRuJob) -> Result<ApfRuJob> {
+        let sql = r#"
+            insert into apf_ru_job (
+                rev, job_type, handler_cfg, proc_def_id, proc_inst_id, 
+                execution_id, element_id, retries, due_time, create_time, 
+                exception_msg, id
+            ) values (
+                $1, $2, $3, $4, $5, 
+                $6, $7, $8, $9, $10, 
+                $11, $12
+            )
+            returning *
+        "#;
+        let new_id = gen_id();
+        let rev: i32 = 1;
+        let stmt = self.tran().prepare(sql).await?;
+        let row = self
+            .tran()
+            .query_one(
+                &stmt, 
+                &[
+                    &rev,
+                    &obj.job_type,
+                    &obj.handler_cfg,
+                    &obj.proc_def_id,
+                    &obj.proc_inst_id,
+                    &obj.execution_id,
+                    &obj.element_id,
+                    &obj.retries,
+                    &obj.due_time,
+                    &obj.create_time,
+                    &obj.exception_msg,
+                    &new_id,
+                ]
+            )
+            .await?;
+        let rst = ApfRuJob::from_row(row)?;
+
+        Ok(rst)
+    }
+
+    pub async fn get_by_id(&self, id: &str) -> Result<ApfRuJob> {
+        let sql = r#"
+            select id, rev, job_type, handler_cfg, proc_def_id, 
+                proc_inst_id, execution_id, element_id, retries, due_time, 
+                lock_owner, lock_expiration_time, exception_msg, create_time
+            from apf_ru_job
+            where id = $1
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&id]).await?;
+        if rows.len() == 0 {
+            Err(
+                AppError::new(
+                    ErrorCode::NotFound, 
+                    Some(&format!("apf_ru_job({}) is not exist", id)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        let rst = ApfRuJob::from_row_ref(&rows[0])?;
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_proc_inst(&self, proc_inst_id: &str) -> Result<Vec<ApfRuJob>> {
+        let sql = r#"
+            select id, rev, job_type, handler_cfg, proc_def_id, 
+                proc_inst_id, execution_id, element_id, retries, due_time, 
+                lock_owner, lock_expiration_time, exception_msg, create_time
+            from apf_ru_job
+            where proc_inst_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuJob::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuJob>>();
+
+        Ok(rst)
+    }
+
+    /// Locks the due jobs for the owner. The jobs whose lock is expired (e.g. the owner died) can
+    /// be acquired again, and the rows locked by other nodes are skipped.
+    pub async fn acquire(&self, lock_owner: &str, lock_expiration_time: i64, now: i64, limit: i64) -> Result<Vec<ApfRuJob>> {
+        let sql = r#"
+            update apf_ru_job
+            set rev = rev + 1,
+                lock_owner = $1,
+                lock_expiration_time = $2
+            where id in (
+                select id 
+                from apf_ru_job
+                where retries > 0
+                    and due_time <= $3
+                    and (lock_owner is null or lock_expiration_time < $3)
+                    and not exists (
+                        select 1 from apf_ru_execution e 
+                        where e.id = apf_ru_job.proc_inst_id and e.suspension_state = $5
+                    )
+                order by due_time
+                limit $4
+                for update skip locked
+            )
+            returning *
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&lock_owner, &lock_expiration_time, &now, &limit, &SuspensionState::TRUE]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuJob::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuJob>>();
+
+        Ok(rst)
+    }
+
+    /// Locks the job for the owner if it is not locked by others, used to execute a job manually.
+    pub async fn lock(&self, id: &str, lock_owner: &str, lock_expiration_time: i64, now: i64) -> Result<Option<ApfRuJob>> {
+        let sql = r#"
+            update apf_ru_job
+            set rev = rev + 1,
+                lock_owner = $1,
+                lock_expiration_time = $2
+            where id = $3
+                and (lock_owner is null or lock_expiration_time < $4)
+            returning *
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&lock_owner, &lock_expiration_time, &id, &now]).await?;
+        let rst = rows
+            .first()
+            .map(|row| ApfRuJob::from_row_ref(row).expect("unexpected_error"));
+
+        Ok(rst)
+    }
+
+    /// Holds the row lock of the job until the transaction ends, returns `None` when the job has
+    /// been taken over by another owner or is being executed.
+    pub async fn lock_for_execution(&self, id: &str, lock_owner: &str) -> Result<Option<ApfRuJob>> {
+        let sql = r#"
+            select id, rev, job_type, handler_cfg, proc_def_id, 
+                proc_inst_id, execution_id, element_id, retries, due_time, 
+                lock_owner, lock_expiration_time, exception_msg, create_time
+            from apf_ru_job
+            where id = $1 and lock_owner = $2
+            for update skip locked
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&id, &lock_owner]).await?;
+        let rst = rows
+            .first()
+            .map(|row| ApfRuJob::from_row_ref(row).expect("unexpected_error"));
+
+        Ok(rst)
+    }
+
+    /// Extends the lock of the acquired jobs which are not being executed yet.
+    pub async fn renew_locks(&self, ids: &[String], lock_owner: &str, lock_expiration_time: i64) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_job
+            set lock_expiration_time = $1
+            where id in (
+                select id
+                from apf_ru_job
+                where id = any($2) and lock_owner = $3
+                for update skip locked
+            )
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&lock_expiration_time, &ids, &lock_owner]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn unlock(&self, id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_job
+            set lock_owner = null,
+                lock_expiration_time = null
+            where id = $1
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&id]).await?;
+
+        Ok(r)
+    }
+
+    /// Records the failure of the job and releases its lock, 0 when the job has been taken over
+    /// by another owner since.
+    pub async fn mark_failure(&self, id: &str, lock_owner: &str, retries: i32, due_time: i64, exception_msg: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_job
+            set rev = rev + 1,
+                retries = $1,
+                due_time = $2,
+                exception_msg = $3,
+                lock_owner = null,
+                lock_expiration_time = null
+            where id = $4 and lock_owner = $5
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&retries, &due_time, &exception_msg, &id, &lock_owner]).await?;
+
+        Ok(r)
+    }
+
+    /// Makes the job executable again, e.g. after the incident is resolved. The job locked by
+    /// an executor is not changed until its lock is expired.
+    pub async fn set_retries(&self, id: &str, retries: i32, due_time: i64, now: i64) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_job
+            set rev = rev + 1,
+                retries = $1,
+                due_time = $2,
+                lock_owner = null,
+                lock_expiration_time = null
+            where id = $3
+                and (lock_owner is null or lock_expiration_time < $4)
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&retries, &due_time, &id, &now]).await?;
+
+        if r != 1 {
+            Err(
+                AppError::new(
+                    ErrorCode::NotFound, 
+                    Some(&format!("apf_ru_job({}) is not exist or locked by a job executor", id)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        Ok(r)
+    }
+
+    /// Updates the retries of the job locked by the owner, the lock is kept.
+    pub async fn set_retries_by_owner(&self, id: &str, retries: i32, lock_owner: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_job
+            set rev = rev + 1,
+                retries = $1
+            where id = $2 and lock_owner = $3
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&retries, &id, &lock_owner]).await?;
+
+        if r != 1 {
+            Err(
+                AppError::new(
+                    ErrorCode::InternalError, 
+                    Some(&format!("apf_ru_job({}) is not locked by ({})", id, lock_owner)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        Ok(r)
+    }
+
+    pub async fn delete_by_execution_id(&self, execution_id: &str) -> Result<u64> {
+        let sql = r#"delete from apf_ru_job where execution_id = $1"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&execution_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn delete_by_proc_inst_id(&self, proc_inst_id: &str) -> Result<u64> {
+        let sql = r#"delete from apf_ru_job where proc_inst_id = $1"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<u64> {
+        let sql = r#"delete from apf_ru_job where id = $1"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&id]).await?;
+
+        Ok(r)
+    }
+
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_job
+            set proc_def_id = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_element_by_execution(&self, execution_id: &str, element_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_job
+            set element_id = $1,
+                rev = rev + 1
+            where execution_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&element_id, &execution_id]).await?;
+
+        Ok(r)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use crate::common::db;
+    use crate::dao::apf_ru_execution_dao::tests::create_test_procinst;
+    use crate::get_now;
+    use crate::model::{ApfRuExecution, JobType};
+    use crate::service::engine::tests::create_test_deploy;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_acquire_and_delete() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process1.bpmn.xml", &tran).await;
+        let proc_inst = create_test_procinst(&procdef, &tran).await;
+        let job = create_test_job(&proc_inst, &tran).await;
+
+        let job_dao = ApfRuJobDao::new(&tran);
+        let now = get_now();
+        let jobs = job_dao.acquire("test_owner", now + 60000, now, 10000).await.unwrap();
+        let acquired = jobs.iter().find(|j| j.id == job.id).unwrap();
+        assert_eq!(acquired.lock_owner, Some("test_owner".to_owned()));
+
+        // the locked job can not be acquired by others
+        let jobs = job_dao.acquire("other_owner", now + 60000, now, 10000).await.unwrap();
+        assert!(jobs.iter().all(|j| j.id != job.id));
+        let rst = job_dao.lock(&job.id, "other_owner", now + 60000, now).await.unwrap();
+        assert!(rst.is_none());
+        let rst = job_dao.set_retries(&job.id, 3, now, now).await;
+        assert!(rst.is_err());
+
+        let rst = job_dao.renew_locks(&vec![job.id.clone()], "test_owner", now + 120000).await.unwrap();
+        assert_eq!(rst, 1);
+        let rst = job_dao.lock_for_execution(&job.id, "other_owner").await.unwrap();
+        assert!(rst.is_none());
+        let rst = job_dao.lock_for_execution(&job.id, "test_owner").await.unwrap().unwrap();
+        assert_eq!(rst.lock_expiration_time, Some(now + 120000));
+
+        // the job can be recovered when the lock of its owner is expired
+        let jobs = job_dao.acquire("other_owner", now + 240000, now + 180000, 10000).await.unwrap();
+        assert!(jobs.iter().any(|j| j.id == job.id));
+
+        assert_eq!(job_dao.mark_failure(&job.id, "test_owner", 2, now, "error").await.unwrap(), 0);
+        job_dao.mark_failure(&job.id, "other_owner", 2, now, "error").await.unwrap();
+        let failed = job_dao.get_by_id(&job.id).await.unwrap();
+        assert_eq!(failed.retries, 2);
+        assert_eq!(failed.lock_owner, None);
+
+        job_dao.set_retries(&job.id, 3, now, now).await.unwrap();
+        let job = job_dao.get_by_id(&job.id).await.unwrap();
+        assert_eq!(job.retries, 3);
+
+        let rst = job_dao.delete(&job.id).await.unwrap();
+        assert_eq!(rst, 1);
+
+        tran.rollback().await.unwrap();
+    }
+
+    pub async fn create_test_job(proc_inst: &ApfRuExecution, tran: &Transaction<'_>) -> ApfRuJob {
+        let now = get_now();
+        let obj = NewApfRuJob {
+            job_type: JobType::ASYNC_CONTINUATION.to_owned(),
+            handler_cfg: Some(JobType::ASYNC_BEFORE.to_owned()),
+            proc_def_id: proc_inst.proc_def_id.clone(),
+            proc_inst_id: proc_inst.id.clone(),
+            execution_id: proc_inst.id.clone(),
+            element_id: "approval_1".to_owned(),
+            retries: JobType::DEFAULT_RETRIES,
+            due_time: now,
+            exception_msg: None,
+            create_time: now,
+        };
+
+        let job_dao = ApfRuJobDao::new(tran);
+        let rst = job_dao.create(&obj).await.unwrap();
+
+        rst
+    }
+}
@@ -1,7 +1,7 @@
 use color_eyre::Result;
 use tokio_pg_mapper::FromTokioPostgresRow;
 use tokio_postgres::Transaction;
-use crate::{model::{ApfRuTask, NewApfRuTask}, gen_id};
+use crate::{model::{ApfRuTask, NewApfRuTask, SuspensionState}, gen_id};
 use super::{BaseDao, Dao};
 
 pub struct ApfRuTaskDao<'a> {
@@ -28,11 +28,16 @@ impl<'a> ApfRuTaskDao<'a> {
             insert into apf_ru_task (
                 rev, execution_id, proc_inst_id, proc_def_id, element_id,
                 element_name, element_type, business_key, description, start_user_id, 
-                create_time, suspension_state, form_key, id
+                create_time, suspension_state, form_key, assignee, owner, delegation, 
+                sign_parent_id, sign_mode, parent_task_id, block_parent, priority, 
+                due_date, follow_up_date, prev_task_id, next_sweep_time, id
             ) values (
                 $1, $2, $3, $4, $5, 
                 $6, $7, $8, $9, $10, 
-                $11, $12, $13, $14
+                $11, $12, $13, $14, $15, 
+                $16, $17, $18, $19, $20, 
+                $21, $22, $23, $24, $25,
+                $26
             )
             returning *
         "#;
@@ -56,6 +61,18 @@ impl<'a> ApfRuTaskDao<'a> {
                     &obj.create_time,
                     &obj.suspension_state,
                     &obj.form_key,
+                    &obj.assignee,
+                    &obj.owner,
+                    &obj.delegation,
+                    &obj.sign_parent_id,
+                    &obj.sign_mode,
+                    &obj.parent_task_id,
+                    &obj.block_parent,
+                    &obj.priority,
+                    &obj.due_date,
+                    &obj.follow_up_date,
+                    &obj.prev_task_id,
+                    &obj.next_sweep_time,
                     &new_id,
                 ]
             )
@@ -72,12 +89,136 @@ impl<'a> ApfRuTaskDao<'a> {
         Ok(r)
     }
 
+    pub async fn find_by_proc_inst(&self, proc_inst_id: &str) -> Result<Vec<ApfRuTask>> {
+        let sql = r#"
+            select id, rev, execution_id, proc_inst_id, proc_def_id, 
+                element_id, element_name, element_type, business_key, description, 
+                start_user_id, create_time, suspension_state, form_key,
+                assignee, owner, delegation, sign_parent_id, sign_mode,
+                parent_task_id, block_parent, priority, due_date, follow_up_date,
+                remind_count, last_remind_time, escalate_time, prev_task_id, next_sweep_time
+            from apf_ru_task
+            where proc_inst_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&proc_inst_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuTask::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuTask>>();
+
+        Ok(rst)
+    }
+
+    pub async fn find_by_execution(&self, execution_id: &str) -> Result<Vec<ApfRuTask>> {
+        let sql = r#"
+            select id, rev, execution_id, proc_inst_id, proc_def_id, 
+                element_id, element_name, element_type, business_key, description, 
+                start_user_id, create_time, suspension_state, form_key,
+                assignee, owner, delegation, sign_parent_id, sign_mode,
+                parent_task_id, block_parent, priority, due_date, follow_up_date,
+                remind_count, last_remind_time, escalate_time, prev_task_id, next_sweep_time
+            from apf_ru_task
+            where execution_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&execution_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuTask::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuTask>>();
+
+        Ok(rst)
+    }
+
+    /// The open tasks of the signers which are added to the task.
+    pub async fn find_by_sign_parent(&self, sign_parent_id: &str) -> Result<Vec<ApfRuTask>> {
+        let sql = r#"
+            select id, rev, execution_id, proc_inst_id, proc_def_id, 
+                element_id, element_name, element_type, business_key, description, 
+                start_user_id, create_time, suspension_state, form_key,
+                assignee, owner, delegation, sign_parent_id, sign_mode,
+                parent_task_id, block_parent, priority, due_date, follow_up_date,
+                remind_count, last_remind_time, escalate_time, prev_task_id, next_sweep_time
+            from apf_ru_task
+            where sign_parent_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&sign_parent_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuTask::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuTask>>();
+
+        Ok(rst)
+    }
+
+    /// The open sub-tasks of the task.
+    pub async fn find_by_parent(&self, parent_task_id: &str) -> Result<Vec<ApfRuTask>> {
+        let sql = r#"
+            select id, rev, execution_id, proc_inst_id, proc_def_id, 
+                element_id, element_name, element_type, business_key, description, 
+                start_user_id, create_time, suspension_state, form_key,
+                assignee, owner, delegation, sign_parent_id, sign_mode,
+                parent_task_id, block_parent, priority, due_date, follow_up_date,
+                remind_count, last_remind_time, escalate_time, prev_task_id, next_sweep_time
+            from apf_ru_task
+            where parent_task_id = $1
+            order by create_time
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&parent_task_id]).await?;
+        let rst = rows
+            .iter()
+            .map(|row| ApfRuTask::from_row_ref(row).expect("unexpected_error"))
+            .collect::<Vec<ApfRuTask>>();
+
+        Ok(rst)
+    }
+
+    pub async fn delete_by_proc_inst_id(&self, proc_inst_id: &str) -> Result<u64> {
+        let sql = r#"delete from apf_ru_task where proc_inst_id = $1"#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_inst_id]).await?;
+        Ok(r)
+    }
+
+    pub async fn update_suspension_state_by_proc_inst(&self, proc_inst_id: &str, suspension_state: i32) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set suspension_state = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&suspension_state, &proc_inst_id]).await?;
+        Ok(r)
+    }
+
+    pub async fn update_suspension_state_by_procdef(&self, proc_def_id: &str, suspension_state: i32) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set suspension_state = $1,
+                rev = rev + 1
+            where proc_def_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&suspension_state, &proc_def_id]).await?;
+        Ok(r)
+    }
+
     pub async fn get_by_id(&self, id: &str)
                 -> Result<ApfRuTask> {
         let sql = r#"
             select id, rev, execution_id, proc_inst_id, proc_def_id, 
                 element_id, element_name, element_type, business_key, description, 
-                start_user_id, create_time, suspension_state, form_key
+                start_user_id, create_time, suspension_state, form_key,
+                assignee, owner, delegation, sign_parent_id, sign_mode,
+                parent_task_id, block_parent, priority, due_date, follow_up_date,
+                remind_count, last_remind_time, escalate_time, prev_task_id, next_sweep_time
             from apf_ru_task
             where id = $1
         "#;
@@ -87,6 +228,188 @@ impl<'a> ApfRuTaskDao<'a> {
 
         Ok(rst)
     }
+
+    /// The active tasks which the escalation sweeper has to look at by `now`, the earliest first.
+    pub async fn find_overdue_ids(&self, now: i64, limit: i64) -> Result<Vec<String>> {
+        let sql = r#"
+            select id
+            from apf_ru_task
+            where next_sweep_time <= $1 and suspension_state = $2
+            order by next_sweep_time
+            limit $3
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&now, &SuspensionState::FALSE, &limit]).await?;
+        let rst = rows.iter().map(|row| row.get::<_, String>(0)).collect();
+
+        Ok(rst)
+    }
+
+    /// Locks the task until the transaction ends, `None` if it's gone or locked by another one.
+    pub async fn lock_by_id(&self, id: &str) -> Result<Option<ApfRuTask>> {
+        let sql = r#"
+            select id, rev, execution_id, proc_inst_id, proc_def_id, 
+                element_id, element_name, element_type, business_key, description, 
+                start_user_id, create_time, suspension_state, form_key,
+                assignee, owner, delegation, sign_parent_id, sign_mode,
+                parent_task_id, block_parent, priority, due_date, follow_up_date,
+                remind_count, last_remind_time, escalate_time, prev_task_id, next_sweep_time
+            from apf_ru_task
+            where id = $1
+            for update skip locked
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let rows = self.tran().query(&stmt, &[&id]).await?;
+        let rst = rows
+            .first()
+            .map(|row| ApfRuTask::from_row_ref(row).expect("unexpected_error"));
+
+        Ok(rst)
+    }
+
+    pub async fn mark_reminded(&self, id: &str, remind_time: i64) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set remind_count = remind_count + 1,
+                last_remind_time = $1,
+                rev = rev + 1
+            where id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&remind_time, &id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_next_sweep_time(&self, id: &str, next_sweep_time: Option<i64>) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set next_sweep_time = $1
+            where id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&next_sweep_time, &id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn mark_escalated(&self, id: &str, escalate_time: i64) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set escalate_time = $1,
+                rev = rev + 1
+            where id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&escalate_time, &id]).await?;
+
+        Ok(r)
+    }
+
+    /// Moves the rows of the process instance to another process definition.
+    pub async fn update_procdef_by_proc_inst(&self, proc_inst_id: &str, proc_def_id: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set proc_def_id = $1,
+                rev = rev + 1
+            where proc_inst_id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&proc_def_id, &proc_inst_id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_assignee(&self, id: &str, assignee: Option<String>) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set assignee = $1,
+                rev = rev + 1
+            where id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&assignee, &id]).await?;
+
+        Ok(r)
+    }
+
+    /// Takes the task only if it's still unassigned, 0 when another user has claimed it first.
+    pub async fn claim(&self, id: &str, assignee: &str) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set assignee = $1,
+                rev = rev + 1
+            where id = $2 and assignee is null
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&assignee, &id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_delegation(
+        &self,
+        id: &str,
+        owner: Option<String>,
+        assignee: Option<String>,
+        delegation: Option<String>
+    ) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set owner = $1,
+                assignee = $2,
+                delegation = $3,
+                rev = rev + 1
+            where id = $4
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&owner, &assignee, &delegation, &id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_priority(&self, id: &str, priority: i32) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set priority = $1,
+                rev = rev + 1
+            where id = $2
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&priority, &id]).await?;
+
+        Ok(r)
+    }
+
+    /// The escalation sweeper looks at the task again at the new due date.
+    pub async fn update_dates(&self, id: &str, due_date: Option<i64>, follow_up_date: Option<i64>) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set due_date = $1,
+                follow_up_date = $2,
+                next_sweep_time = $1,
+                rev = rev + 1
+            where id = $3
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&due_date, &follow_up_date, &id]).await?;
+
+        Ok(r)
+    }
+
+    pub async fn update_element(&self, id: &str, element_id: &str, element_name: Option<String>) -> Result<u64> {
+        let sql = r#"
+            update apf_ru_task
+            set element_id = $1,
+                element_name = $2,
+                rev = rev + 1
+            where id = $3
+        "#;
+        let stmt = self.tran().prepare(sql).await?;
+        let r = self.tran().execute(&stmt, &[&element_id, &element_name, &id]).await?;
+
+        Ok(r)
+    }
 }
 
 #[cfg(test)]
@@ -113,6 +436,18 @@ pub mod tests {
         // test get by id
         let task = task_dao.get_by_id(&task.id).await.unwrap();
 
+        // assign
+        task_dao.update_assignee(&task.id, Some("user_1".to_owned())).await.unwrap();
+        let task = task_dao.get_by_id(&task.id).await.unwrap();
+        assert_eq!(task.assignee, Some("user_1".to_owned()));
+
+        // the claimed task can't be claimed again
+        let rst = task_dao.claim(&task.id, "user_2").await.unwrap();
+        assert_eq!(rst, 0);
+        task_dao.update_assignee(&task.id, None).await.unwrap();
+        let rst = task_dao.claim(&task.id, "user_2").await.unwrap();
+        assert_eq!(rst, 1);
+
         // delete
         let rst = task_dao.delete(&task.id).await.unwrap();
         assert_eq!(rst, 1);
@@ -11,6 +11,12 @@ pub mod apf_ru_identitylink_dao;
 pub mod apf_hi_identitylink_dao;
 pub mod apf_ru_variable_dao;
 pub mod apf_hi_varinst_dao;
+pub mod apf_ru_event_outbox_dao;
+pub mod apf_ru_job_dao;
+pub mod apf_ru_incident_dao;
+pub mod apf_hi_oplog_dao;
+pub mod apf_hi_comment_dao;
+pub mod apf_hi_attachment_dao;
 pub mod sql_fragment;
 
 pub use base_dao::*;
@@ -26,4 +32,10 @@ pub use apf_ru_identitylink_dao::*;
 pub use apf_hi_identitylink_dao::*;
 pub use apf_ru_variable_dao::*;
 pub use apf_hi_varinst_dao::*;
+pub use apf_ru_event_outbox_dao::*;
+pub use apf_ru_job_dao::*;
+pub use apf_ru_incident_dao::*;
+pub use apf_hi_oplog_dao::*;
+pub use apf_hi_comment_dao::*;
+pub use apf_hi_attachment_dao::*;
 pub use sql_fragment::*;
\ No newline at end of file
@@ -14,6 +14,7 @@ pub enum ErrorCode {
     InvalidCredentials = 401_01,
     UnAuthorized = 401_02,
     NotFound = 404_01,
+    Suspended = 409_01,
     InternalError = 500_01,
     UnexpectedError = 500_02,
 }
@@ -35,6 +36,7 @@ impl ErrorCode {
             ErrorCode::UnAuthorized => "Not authorized".to_string(),
             ErrorCode::SessionNotExist => "Session not exists".to_string(),
             ErrorCode::NotFound => "Not found".to_string(),
+            ErrorCode::Suspended => "Suspended".to_string(),
             ErrorCode::FileSizeError => "File size error".to_string(),
             ErrorCode::ParseError => "Parse error".to_string(),
             ErrorCode::UnexpectedError => "Unexpected error".to_string(),
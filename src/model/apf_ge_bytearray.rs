@@ -8,7 +8,7 @@ use tokio_pg_mapper_derive::PostgresMapper;
 pub struct ApfGeBytearray {
     pub id: String,
     pub name: Option<String>,
-    pub deployment_id: String,
+    pub deployment_id: Option<String>,
     pub bytes: Option<Vec<u8>>,
 }
 
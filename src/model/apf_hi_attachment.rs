@@ -0,0 +1,38 @@
+use serde::Serialize;
+use tokio_pg_mapper_derive::PostgresMapper;
+
+/// The file of a task, it's stored in `apf_ge_bytearray` (`content_id`) or somewhere else (`url`).
+#[derive(Debug, Serialize, PartialEq, Default, Clone)]
+#[derive(PostgresMapper)]
+#[pg_mapper(table="apf_hi_attachment")]
+pub struct ApfHiAttachment {
+    pub id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub attachment_type: Option<String>,
+    pub user_id: Option<String>,
+    pub task_id: Option<String>,
+    pub proc_inst_id: Option<String>,
+    pub url: Option<String>,
+    pub content_id: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct NewApfHiAttachment {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub attachment_type: Option<String>,
+    pub user_id: Option<String>,
+    pub task_id: Option<String>,
+    pub proc_inst_id: Option<String>,
+    pub url: Option<String>,
+    pub content_id: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttachmentContent {
+    Bytes(Vec<u8>),
+    Url(String),
+}
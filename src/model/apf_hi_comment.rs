@@ -0,0 +1,35 @@
+use serde::Serialize;
+use tokio_pg_mapper_derive::PostgresMapper;
+
+#[derive(Debug, Serialize, PartialEq, Default, Clone)]
+#[derive(PostgresMapper)]
+#[pg_mapper(table="apf_hi_comment")]
+pub struct ApfHiComment {
+    pub id: String,
+    pub comment_type: String,
+    pub user_id: Option<String>,
+    pub task_id: Option<String>,
+    pub proc_inst_id: Option<String>,
+    pub message: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct NewApfHiComment {
+    pub comment_type: String,
+    pub user_id: Option<String>,
+    pub task_id: Option<String>,
+    pub proc_inst_id: Option<String>,
+    pub message: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug)]
+pub enum CommentType {}
+
+#[allow(dead_code)]
+impl CommentType {
+    pub const COMMENT: &'static str = "comment";
+    /// the reason given by the one who rejects the task
+    pub const REJECT: &'static str = "reject";
+}
@@ -0,0 +1,44 @@
+use serde::Serialize;
+use tokio_pg_mapper_derive::PostgresMapper;
+
+#[derive(Debug, Serialize, PartialEq, Default, Clone)]
+#[derive(PostgresMapper)]
+#[pg_mapper(table="apf_hi_oplog")]
+pub struct ApfHiOplog {
+    pub id: String,
+    pub op_type: String,
+    pub proc_def_id: Option<String>,
+    pub proc_inst_id: Option<String>,
+    pub task_id: Option<String>,
+    pub element_id: Option<String>,
+    pub user_id: Option<String>,
+    pub detail: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct NewApfHiOplog {
+    pub op_type: String,
+    pub proc_def_id: Option<String>,
+    pub proc_inst_id: Option<String>,
+    pub task_id: Option<String>,
+    pub element_id: Option<String>,
+    pub user_id: Option<String>,
+    pub detail: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug)]
+pub enum OpType {}
+
+#[allow(dead_code)]
+impl OpType {
+    pub const MODIFY_PROCESS_INSTANCE: &'static str = "modifyProcessInstance";
+    pub const MIGRATE_PROCESS_INSTANCE: &'static str = "migrateProcessInstance";
+    pub const DELEGATE_TASK: &'static str = "delegateTask";
+    pub const RESOLVE_TASK: &'static str = "resolveTask";
+    pub const ADD_SIGN: &'static str = "addSign";
+    pub const REMIND_TASK: &'static str = "remindTask";
+    pub const ESCALATE_TASK: &'static str = "escalateTask";
+    pub const WITHDRAW_TASK: &'static str = "withdrawTask";
+}
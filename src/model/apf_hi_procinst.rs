@@ -16,6 +16,8 @@ pub struct ApfHiProcinst {
     pub start_user: Option<String>,
     pub start_element_id: Option<String>,
     pub end_element_id: Option<String>,
+    pub end_user: Option<String>,
+    pub delete_reason: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -22,6 +22,18 @@ pub struct ApfHiTaskinst {
     pub duration: Option<i64>,
     pub suspension_state: i32,
     pub form_key: Option<String>,
+    pub delete_reason: Option<String>,
+    pub assignee: Option<String>,
+    pub owner: Option<String>,
+    pub delegation: Option<String>,
+    pub sign_parent_id: Option<String>,
+    pub sign_mode: Option<String>,
+    pub parent_task_id: Option<String>,
+    pub block_parent: bool,
+    pub priority: i32,
+    pub due_date: Option<i64>,
+    pub follow_up_date: Option<i64>,
+    pub prev_task_id: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -42,4 +54,26 @@ pub struct NewApfHiTaskinst {
     pub duration: Option<i64>,
     pub suspension_state: i32,
     pub form_key: Option<String>,
+    pub assignee: Option<String>,
+    pub owner: Option<String>,
+    pub delegation: Option<String>,
+    pub sign_parent_id: Option<String>,
+    pub sign_mode: Option<String>,
+    pub parent_task_id: Option<String>,
+    pub block_parent: bool,
+    pub priority: i32,
+    pub due_date: Option<i64>,
+    pub follow_up_date: Option<i64>,
+    pub prev_task_id: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum DeleteReason {}
+
+#[allow(dead_code)]
+impl DeleteReason {
+    pub const REJECTED: &'static str = "rejected";
+    pub const WITHDRAWN: &'static str = "withdrawn";
+    pub const MODIFIED: &'static str = "modified";
+    pub const PARENT_COMPLETED: &'static str = "parentCompleted";
 }
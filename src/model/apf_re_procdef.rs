@@ -25,6 +25,12 @@ pub struct ApfReProcdef {
     pub is_deleted: i32,
 }
 
+impl ApfReProcdef {
+    pub fn is_suspended(&self) -> bool {
+        self.suspension_state == SuspensionState::TRUE
+    }
+}
+
 #[derive(Debug)]
 pub enum SuspensionState {}
 
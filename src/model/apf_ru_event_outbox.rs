@@ -0,0 +1,38 @@
+use serde::Serialize;
+use tokio_pg_mapper_derive::PostgresMapper;
+
+#[derive(Debug, Serialize, PartialEq, Default, Clone)]
+#[derive(PostgresMapper)]
+#[pg_mapper(table="apf_ru_event_outbox")]
+pub struct ApfRuEventOutbox {
+    pub id: String,
+    pub seq: i64,
+    pub proc_inst_id: String,
+    pub event_type: String,
+    pub payload: String,
+    pub create_time: i64,
+    pub status: i32,
+    pub retries: i32,
+    pub next_retry_time: i64,
+    pub delivered_time: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct NewApfRuEventOutbox {
+    pub proc_inst_id: String,
+    pub event_type: String,
+    pub payload: String,
+    pub create_time: i64,
+}
+
+#[derive(Debug)]
+pub enum OutboxStatus {}
+
+#[allow(dead_code)]
+impl OutboxStatus {
+    pub const PENDING: i32 = 0;
+    pub const DELIVERED: i32 = 1;
+    pub const FAILED: i32 = 2;
+    pub const SKIPPED: i32 = 3;
+}
@@ -1,6 +1,7 @@
 use serde::Serialize;
 use color_eyre::Result;
 use crate::error::{AppError, ErrorCode};
+use crate::model::SuspensionState;
 use tokio_pg_mapper_derive::PostgresMapper;
 
 #[derive(PostgresMapper)]
@@ -18,9 +19,14 @@ pub struct ApfRuExecution {
     pub is_active: i32,
     pub start_time: i64,
     pub start_user: Option<String>,
+    pub suspension_state: i32,
 }
 
 impl ApfRuExecution {
+    pub fn is_suspended(&self) -> bool {
+        self.suspension_state == SuspensionState::TRUE
+    }
+
     pub fn proc_inst_id(&self) -> Result<String> {
         let proc_inst_id = self.proc_inst_id
             .clone()
@@ -0,0 +1,42 @@
+use serde::Serialize;
+use tokio_pg_mapper_derive::PostgresMapper;
+
+#[derive(Debug, Serialize, PartialEq, Default, Clone)]
+#[derive(PostgresMapper)]
+#[pg_mapper(table="apf_ru_incident")]
+pub struct ApfRuIncident {
+    pub id: String,
+    pub rev: i32,
+    pub incident_type: String,
+    pub proc_def_id: String,
+    pub proc_inst_id: String,
+    pub execution_id: String,
+    pub element_id: String,
+    pub job_id: Option<String>,
+    pub error_msg: Option<String>,
+    pub error_location: Option<String>,
+    pub error_stack: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct NewApfRuIncident {
+    pub incident_type: String,
+    pub proc_def_id: String,
+    pub proc_inst_id: String,
+    pub execution_id: String,
+    pub element_id: String,
+    pub job_id: Option<String>,
+    pub error_msg: Option<String>,
+    pub error_location: Option<String>,
+    pub error_stack: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug)]
+pub enum IncidentType {}
+
+#[allow(dead_code)]
+impl IncidentType {
+    pub const FAILED_JOB: &'static str = "failedJob";
+}
@@ -0,0 +1,52 @@
+use serde::Serialize;
+use tokio_pg_mapper_derive::PostgresMapper;
+
+#[derive(Debug, Serialize, PartialEq, Default, Clone)]
+#[derive(PostgresMapper)]
+#[pg_mapper(table="apf_ru_job")]
+pub struct ApfRuJob {
+    pub id: String,
+    pub rev: i32,
+    pub job_type: String,
+    pub handler_cfg: Option<String>,
+    pub proc_def_id: String,
+    pub proc_inst_id: String,
+    pub execution_id: String,
+    pub element_id: String,
+    pub retries: i32,
+    pub due_time: i64,
+    pub lock_owner: Option<String>,
+    pub lock_expiration_time: Option<i64>,
+    pub exception_msg: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct NewApfRuJob {
+    pub job_type: String,
+    pub handler_cfg: Option<String>,
+    pub proc_def_id: String,
+    pub proc_inst_id: String,
+    pub execution_id: String,
+    pub element_id: String,
+    pub retries: i32,
+    pub due_time: i64,
+    pub exception_msg: Option<String>,
+    pub create_time: i64,
+}
+
+#[derive(Debug)]
+pub enum JobType {}
+
+#[allow(dead_code)]
+impl JobType {
+    pub const ASYNC_CONTINUATION: &'static str = "async-continuation";
+
+    // handler_cfg of async continuation
+    pub const ASYNC_BEFORE: &'static str = "before";
+    pub const ASYNC_AFTER: &'static str = "after";
+    // the failed synchronous service task is retried
+    pub const RETRY: &'static str = "retry";
+
+    pub const DEFAULT_RETRIES: i32 = 3;
+}
@@ -21,6 +21,22 @@ pub struct ApfRuTask {
     pub create_time: i64,
     pub suspension_state: i32,
     pub form_key: Option<String>,
+    pub assignee: Option<String>,
+    pub owner: Option<String>,
+    pub delegation: Option<String>,
+    pub sign_parent_id: Option<String>,
+    pub sign_mode: Option<String>,
+    pub parent_task_id: Option<String>,
+    pub block_parent: bool,
+    pub priority: i32,
+    pub due_date: Option<i64>,
+    pub follow_up_date: Option<i64>,
+    pub remind_count: i32,
+    pub last_remind_time: Option<i64>,
+    pub escalate_time: Option<i64>,
+    pub prev_task_id: Option<String>,
+    /// when the escalation sweeper looks at the task again, `None` if there is nothing to do
+    pub next_sweep_time: Option<i64>,
 }
 
 #[derive(Debug, Default)]
@@ -38,6 +54,49 @@ pub struct NewApfRuTask {
     pub create_time: Option<i64>,
     pub suspension_state: i32,
     pub form_key: Option<String>,
+    pub assignee: Option<String>,
+    pub owner: Option<String>,
+    pub delegation: Option<String>,
+    pub sign_parent_id: Option<String>,
+    pub sign_mode: Option<String>,
+    pub parent_task_id: Option<String>,
+    pub block_parent: bool,
+    pub priority: i32,
+    pub due_date: Option<i64>,
+    pub follow_up_date: Option<i64>,
+    pub prev_task_id: Option<String>,
+    pub next_sweep_time: Option<i64>,
+}
+
+/// The task is `PENDING` while the delegate works on it, and it's `RESOLVED` after it's
+/// returned to the owner.
+#[derive(Debug)]
+pub enum DelegationState {}
+
+#[allow(dead_code)]
+impl DelegationState {
+    pub const PENDING: &'static str = "pending";
+    pub const RESOLVED: &'static str = "resolved";
+}
+
+/// How the added signers (加签) work with the task they are added to. The `Before` signers
+/// handle the task first, the `After` signers handle it after the original one, and the
+/// `Parallel` signers handle it at the same time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AddSignMode {
+    Before,
+    After,
+    Parallel,
+}
+
+impl AddSignMode {
+    pub fn name(&self) -> String {
+        match self {
+            AddSignMode::Before => "before".to_owned(),
+            AddSignMode::After => "after".to_owned(),
+            AddSignMode::Parallel => "parallel".to_owned(),
+        }
+    }
 }
 
 impl ApfRuTask {
@@ -48,4 +107,8 @@ impl ApfRuTask {
 
         Ok(rst)
     }
+
+    pub fn is_delegation_pending(&self) -> bool {
+        self.delegation.as_deref() == Some(DelegationState::PENDING)
+    }
 }
\ No newline at end of file
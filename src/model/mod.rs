@@ -12,6 +12,12 @@ pub mod apf_hi_identitylink;
 pub mod apf_ru_variable;
 pub mod wrapped_value;
 pub mod apf_hi_varinst;
+pub mod apf_ru_event_outbox;
+pub mod apf_ru_job;
+pub mod apf_ru_incident;
+pub mod apf_hi_oplog;
+pub mod apf_hi_comment;
+pub mod apf_hi_attachment;
 
 pub use apf_re_deployment::*;
 pub use apf_ge_bytearray::*;
@@ -27,5 +33,11 @@ pub use apf_hi_identitylink::*;
 pub use apf_ru_variable::*;
 pub use wrapped_value::*;
 pub use apf_hi_varinst::*;
+pub use apf_ru_event_outbox::*;
+pub use apf_ru_job::*;
+pub use apf_ru_incident::*;
+pub use apf_hi_oplog::*;
+pub use apf_hi_comment::*;
+pub use apf_hi_attachment::*;
 
 
@@ -0,0 +1,110 @@
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+use color_eyre::Result;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::error::{AppError, ErrorCode};
+
+/// The background threads of the job executor and the escalation sweeper. The operators hold
+/// `Rc` values, so every thread drives its loop with `Handle::block_on` instead of spawning tasks.
+pub(crate) struct BackgroundWorker {
+    name: String,
+    running: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl BackgroundWorker {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            running: Arc::new(AtomicBool::new(false)),
+            threads: vec![],
+        }
+    }
+
+    /// Starts `thread_count` threads running `run` until the worker is shut down or dropped.
+    /// It must be called in a multi-thread tokio runtime, the database connections are driven
+    /// by its worker threads while the caller is blocked in `shutdown`.
+    pub fn start<F, Fut>(&mut self, thread_count: usize, run: F) -> Result<()>
+    where
+        F: FnOnce(Arc<AtomicBool>) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = ()>,
+    {
+        let handle = Handle::try_current()?;
+        if handle.runtime_flavor() != RuntimeFlavor::MultiThread {
+            Err(
+                AppError::new(
+                    ErrorCode::NotSupportError,
+                    Some(&format!("{} must be started in a multi-thread tokio runtime", self.name)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?
+        }
+
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        for i in 0..thread_count {
+            let handle = handle.clone();
+            let run = run.clone();
+            let running = self.running.clone();
+            let thread = std::thread::Builder::new()
+                .name(format!("{}-{}", self.name, i))
+                .spawn(move || {
+                    handle.block_on(run(running));
+                })?;
+            self.threads.push(thread);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Stops the loops and waits for the threads.
+    pub fn shutdown(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        for thread in self.threads.drain(..) {
+            thread.join().ok();
+        }
+    }
+}
+
+impl Drop for BackgroundWorker {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_current_thread_runtime() {
+        let mut worker = BackgroundWorker::new("apf-test-worker");
+        assert!(worker.start(1, |_| async {}).is_err());
+        assert!(!worker.is_running());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_start_and_shutdown() {
+        let mut worker = BackgroundWorker::new("apf-test-worker");
+        worker.start(2, |running| async move {
+            while running.load(Ordering::SeqCst) {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        }).unwrap();
+        assert!(worker.is_running());
+
+        worker.shutdown();
+        assert!(!worker.is_running());
+    }
+}
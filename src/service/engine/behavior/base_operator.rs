@@ -4,13 +4,23 @@ use std::rc::Rc;
 use std::sync::Arc;
 
 use tokio_postgres::Transaction;
-use color_eyre::Result;
+use color_eyre::{Report, Result};
+use log4rs_macros::error;
 
 use crate::{get_now, RcRefCell};
 use crate::error::{AppError, ErrorCode};
-use crate::service::engine::{ BpmnEdge, BpmnElement, NodeType, OperateRst, Operator,OperatorContext, TakeOutgoingFlowsOperator };
-use crate::model::{ApfRuExecution, ApfRuTask, ApfRuVariableDto, NewApfHiActinst, NewApfRuExecution, WrappedValue};
-use crate::dao::{ApfHiActinstDao, ApfHiVarinstDao, ApfRuExecutionDao, ApfRuVariableDao};
+use crate::service::engine::{ 
+    BpmnEdge, BpmnElement, BpmnNode, convert_map, EngineEvent, get_listener, invoke_listeners, ListenerContext, 
+    job_retries, job_retry_backoff, ListenerEvent, ListenerType, NodeType, notify_task_completed, OperateRst, Operator, 
+    OperatorContext, run_script, TakeOutgoingFlowsOperator, truncate_msg
+};
+use crate::model::{
+    ApfRuExecution, ApfRuJob, ApfRuTask, ApfRuVariableDto, JobType, NewApfHiActinst, NewApfRuExecution, NewApfRuJob, WrappedValue
+};
+use crate::dao::{
+    ApfHiActinstDao, ApfHiTaskinstDao, ApfHiVarinstDao, ApfRuExecutionDao, ApfRuIdentitylinkDao, ApfRuIncidentDao, ApfRuJobDao, 
+    ApfRuTaskDao, ApfRuVariableDao
+};
 use crate::service::engine::query::TaskQuery;
 
 #[derive(Debug)]
@@ -75,19 +85,19 @@ impl BaseOperator {
                 )?
             },
             BpmnElement::Node(node) => {
-                let bpmn_process = operator_ctx.bpmn_process_ex()?;
-                let out_flows = node.out_flows(bpmn_process.as_ref());
-
                 match node.get_node_type() {
                     NodeType::StartEvent => {
-                        self._continue_outflow(operator_ctx, &out_flows, tran).await?;
+                        let out_flow = self.select_outflow(node, operator_ctx)?;
+                        self.take_outflow(out_flow, operator_ctx, tran).await?;
                     },
                     NodeType::EndEvent => {},
                     NodeType::UserTask => {
-                        self._continue_outflow(operator_ctx, &out_flows, tran).await?;
+                        let out_flow = self.select_outflow(node, operator_ctx)?;
+                        self.take_outflow(out_flow, operator_ctx, tran).await?;
                     },
                     NodeType::ServiceTask => {
-                        self._continue_outflow(operator_ctx, &out_flows, tran).await?;
+                        let out_flow = self.select_outflow(node, operator_ctx)?;
+                        self.take_outflow(out_flow, operator_ctx, tran).await?;
                     },
                     NodeType::ExclusiveGateway => {
                         Err(
@@ -150,18 +160,62 @@ impl BaseOperator {
         Ok(())
     }
 
-    async fn _continue_outflow(
+    /// Chooses the outgoing flow of the node: the first flow whose condition is true,
+    /// otherwise the default flow. A flow without condition is only allowed when it is
+    /// the single outgoing flow (checked by `BpmnDefinitions::validate`).
+    pub fn select_outflow(&self, node: &Arc<dyn BpmnNode>, operator_ctx: &OperatorContext) -> Result<Arc<dyn BpmnEdge>> {
+        let bpmn_process = operator_ctx.bpmn_process_ex()?;
+        let out_flows = node.out_flows(bpmn_process.as_ref());
+        let default_flow_id = node.get_default_flow();
+        let mut default_flow = None;
+
+        for flow in out_flows {
+            if Some(flow.get_id()) == default_flow_id {
+                default_flow = Some(flow);
+                continue;
+            }
+
+            match flow.get_condition_expr() {
+                None => return Ok(flow),
+                Some(expr) => {
+                    let js_global_vars = convert_map(&operator_ctx.variables);
+                    match run_script(expr, &js_global_vars) {
+                        Ok(v) => {
+                            if let Some(true) = v.as_boolean() {
+                                return Ok(flow);
+                            } else if let None = v.as_boolean() {
+                                error!("process({:?}) flow({}) condition's result value is not boolean!", self.proc_inst.id, flow.get_id());
+                            }
+                        },
+                        Err(_) => {
+                            error!("process({:?}) flow({}) condition's script runs failed!", self.proc_inst.id, flow.get_id());
+                        }
+                    }
+                }
+            }
+        }
+
+        let out_flow = default_flow.ok_or(
+            AppError::new(
+                ErrorCode::NotFound,
+                Some(&format!("not found valid outflow for {:?} (proc_inst: {:?}, element: {})", node.get_node_type(), self.proc_inst.id, node.get_id())),
+                concat!(file!(), ":", line!()),
+                None
+            )
+        )?;
+
+        Ok(out_flow)
+    }
+
+    pub async fn take_outflow(
         &self, 
+        out_flow: Arc<dyn BpmnEdge>, 
         operator_ctx: &mut OperatorContext, 
-        out_flows: &Vec<Arc<dyn BpmnEdge>>, 
         tran: &Transaction<'_>
     ) -> Result<()> {
         let current_exec = self.current_excution_ex()?;
-        let out_flow = out_flows
-            .get(0)
-            .ok_or(AppError::new(ErrorCode::NotFound, Some("start event must have 1 outflow"), concat!(file!(), ":", line!()), None))?;
-        let element = BpmnElement::Edge(out_flow.clone());
         let element_id = out_flow.get_id();
+        let element = BpmnElement::Edge(out_flow);
 
         // set edge for current exection and update the start of it
         self.mark_begin_exection(&element_id, operator_ctx.user_id.clone(), get_now(), tran).await?;
@@ -248,6 +302,254 @@ impl BaseOperator {
     }
 
 
+    /// Commits the process at this point, the job executor continues the execution
+    /// from the element in a new transaction.
+    pub async fn create_async_job(&self, handler_cfg: &str, element_id: &str, tran: &Transaction<'_>) -> Result<ApfRuJob> {
+        self.create_job(handler_cfg, element_id, job_retries(), get_now(), None, tran).await
+    }
+
+    /// The job which retries the failed synchronous service task after the backoff, the failure
+    /// is recorded on it as the one of a job.
+    pub async fn create_retry_job(&self, element_id: &str, retries: i32, err: &Report, tran: &Transaction<'_>) -> Result<ApfRuJob> {
+        let due_time = get_now() + job_retry_backoff();
+        self.create_job(JobType::RETRY, element_id, retries, due_time, Some(truncate_msg(&err.to_string())), tran).await
+    }
+
+    async fn create_job(
+        &self,
+        handler_cfg: &str,
+        element_id: &str,
+        retries: i32,
+        due_time: i64,
+        exception_msg: Option<String>,
+        tran: &Transaction<'_>
+    ) -> Result<ApfRuJob> {
+        let (execution_id, proc_def_id) = {
+            let current_exec = self.current_excution_ex()?;
+            let current_exec = current_exec.borrow();
+            (current_exec.id.clone(), current_exec.proc_def_id.clone())
+        };
+        let new_job = NewApfRuJob {
+            job_type: JobType::ASYNC_CONTINUATION.to_owned(),
+            handler_cfg: Some(handler_cfg.to_owned()),
+            proc_def_id,
+            proc_inst_id: self.proc_inst.id.clone(),
+            execution_id,
+            element_id: element_id.to_owned(),
+            retries,
+            due_time,
+            exception_msg,
+            create_time: get_now(),
+        };
+
+        let job_dao = ApfRuJobDao::new(tran);
+        let job = job_dao.create(&new_job).await?;
+
+        Ok(job)
+    }
+
+    /// Closes the tasks and activities of the execution, and removes its jobs.
+    pub async fn clear_execution(
+        &self,
+        execution_id: &str,
+        delete_reason: &str,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let end_time = get_now();
+        let task_dao = ApfRuTaskDao::new(tran);
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        let ru_ident_dao = ApfRuIdentitylinkDao::new(tran);
+
+        let tasks = task_dao.find_by_proc_inst(&self.proc_inst.id).await?;
+        for task in tasks.iter().filter(|t| t.execution_id == execution_id) {
+            Self::fire_listeners_of_task(self.proc_inst.clone(), ListenerEvent::DELETE, task, operator_ctx, tran).await?;
+            hi_task_dao.close(&task.id, end_time, operator_ctx.user_id.clone(), Some(delete_reason.to_owned())).await?;
+            notify_task_completed(task, tran).await?;
+            ru_ident_dao.delete_by_task_id(&task.id).await?;
+            task_dao.delete(&task.id).await?;
+        }
+
+        let hi_act_dao = ApfHiActinstDao::new(tran);
+        hi_act_dao.close_by_execution(execution_id, end_time, operator_ctx.user_id.clone()).await?;
+
+        let incident_dao = ApfRuIncidentDao::new(tran);
+        incident_dao.delete_by_execution_id(execution_id).await?;
+        let job_dao = ApfRuJobDao::new(tran);
+        job_dao.delete_by_execution_id(execution_id).await?;
+
+        Ok(())
+    }
+
+    /// Fires the `start` execution listeners and emits the `ActivityStarted` event.
+    pub async fn on_activity_start(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        self.fire_execution_listeners(ListenerEvent::START, operator_ctx, tran).await?;
+
+        let event = EngineEvent::ActivityStarted {
+            proc_inst_id: self.proc_inst.id.clone(),
+            execution_id: self.current_excution_ex()?.borrow().id.clone(),
+            element_id: self.element.get_element_id(),
+            element_type: self.element.get_element_type(),
+        };
+        operator_ctx.emit(event)?;
+
+        Ok(())
+    }
+
+    /// Fires the `end` execution listeners and emits the `ActivityCompleted` event.
+    pub async fn on_activity_end(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        self.fire_execution_listeners(ListenerEvent::END, operator_ctx, tran).await?;
+
+        let event = EngineEvent::ActivityCompleted {
+            proc_inst_id: self.proc_inst.id.clone(),
+            execution_id: self.current_excution_ex()?.borrow().id.clone(),
+            element_id: self.element.get_element_id(),
+            element_type: self.element.get_element_type(),
+        };
+        operator_ctx.emit(event)?;
+
+        Ok(())
+    }
+
+    pub async fn fire_execution_listeners(&self, event: &str, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        self.fire_listeners(ListenerType::Execution, event, ListenerContext::default(), operator_ctx, tran).await?;
+
+        Ok(())
+    }
+
+    /// The returned context holds the candidates which may be changed by the listeners.
+    pub async fn fire_task_listeners(
+        &self, 
+        event: &str, 
+        task: &ApfRuTask, 
+        candidate_users: Vec<String>, 
+        candidate_groups: Vec<String>, 
+        operator_ctx: &mut OperatorContext, 
+        tran: &Transaction<'_>
+    ) -> Result<ListenerContext> {
+        let listener_ctx = ListenerContext {
+            task_id: Some(task.id.clone()),
+            candidate_users,
+            candidate_groups,
+            ..Default::default()
+        };
+
+        self.fire_listeners(ListenerType::Task, event, listener_ctx, operator_ctx, tran).await
+    }
+
+    /// Fires the task listeners declared on the element of the task, e.g. when the task is assigned
+    /// or deleted out of the normal flow. The sub-tasks have no listeners.
+    pub async fn fire_listeners_of_task(
+        proc_inst: Rc<ApfRuExecution>,
+        event: &str,
+        task: &ApfRuTask,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        if task.parent_task_id.is_some() {
+            return Ok(());
+        }
+
+        let bpmn_process = operator_ctx.bpmn_process_ex()?;
+        let element = match task.element_id.as_ref().and_then(|id| bpmn_process.element_map.get(id)) {
+            Some(element) => element.clone(),
+            None => return Ok(()),
+        };
+
+        let base = BaseOperator::new(proc_inst, None, element, None, None);
+        base.fire_task_listeners(event, task, vec![], vec![], operator_ctx, tran).await?;
+
+        Ok(())
+    }
+
+    /// Calls the registered rust callback which is referenced by the `delegate` attribute
+    /// of service task.
+    pub async fn invoke_delegate(
+        &self, 
+        delegate: &str, 
+        task: &ApfRuTask, 
+        operator_ctx: &mut OperatorContext, 
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let callback = get_listener(delegate)
+            .ok_or(AppError::new(
+                ErrorCode::NotFound,
+                Some(&format!("delegate ({}) of element ({}) is not registered", delegate, self.element.get_element_id())),
+                concat!(file!(), ":", line!()),
+                None
+            ))?;
+        let listener_ctx = ListenerContext {
+            task_id: Some(task.id.clone()),
+            ..Default::default()
+        };
+
+        self.call_with_context("execute", listener_ctx, operator_ctx, tran, |ctx| callback(ctx)).await?;
+
+        Ok(())
+    }
+
+    async fn fire_listeners(
+        &self, 
+        listener_type: ListenerType, 
+        event: &str, 
+        listener_ctx: ListenerContext, 
+        operator_ctx: &mut OperatorContext, 
+        tran: &Transaction<'_>
+    ) -> Result<ListenerContext> {
+        let element_id = self.element.get_element_id();
+        let listeners = operator_ctx.bpmn_process_ex()?.get_listeners(&element_id, listener_type, event);
+        if listeners.is_empty() {
+            return Ok(listener_ctx);
+        }
+
+        self.call_with_context(event, listener_ctx, operator_ctx, tran, |ctx| invoke_listeners(&listeners, ctx)).await
+    }
+
+    async fn call_with_context<F>(
+        &self, 
+        event: &str, 
+        mut listener_ctx: ListenerContext, 
+        operator_ctx: &mut OperatorContext, 
+        tran: &Transaction<'_>,
+        callback: F
+    ) -> Result<ListenerContext> 
+    where F: FnOnce(&mut ListenerContext) -> Result<()> {
+        let element_id = self.element.get_element_id();
+        listener_ctx.event = event.to_owned();
+        listener_ctx.proc_inst_id = self.proc_inst.id.clone();
+        listener_ctx.proc_def_id = self.proc_inst.proc_def_id.clone();
+        listener_ctx.business_key = self.proc_inst.business_key.clone();
+        listener_ctx.element_id = element_id;
+        listener_ctx.user_id = operator_ctx.user_id.clone();
+        listener_ctx.variables = operator_ctx.variables.clone();
+
+        callback(&mut listener_ctx)?;
+
+        // save the variables which are changed by listeners
+        let mut changed_variables = HashMap::new();
+        for (key, value) in listener_ctx.variables.iter() {
+            if operator_ctx.variables.get(key) != Some(value) {
+                changed_variables.insert(key.to_owned(), value.clone());
+            }
+        }
+
+        if !changed_variables.is_empty() {
+            self.create_or_update_variables(&mut changed_variables, tran).await?;
+
+            for (name, value) in changed_variables.iter() {
+                let event = EngineEvent::VariableUpdated {
+                    proc_inst_id: self.proc_inst.id.clone(),
+                    name: name.to_owned(),
+                    value: value.clone(),
+                };
+                operator_ctx.emit(event)?;
+            }
+            operator_ctx.variables.extend(changed_variables);
+        }
+
+        Ok(listener_ctx)
+    }
+
     pub async fn check_complete_task_priviledge<'a>(
         &self, 
         task: Rc<ApfRuTask>, 
@@ -255,6 +557,34 @@ impl BaseOperator {
         operator_ctx: &mut OperatorContext,
         tran: &Transaction<'_>
     ) -> Result<()> {
+        // the delegated task goes back to its owner by resolving
+        if task.is_delegation_pending() {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("Task ({}) is delegated, it must be resolved first", task.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        // the assignee is the only one who can complete a claimed task
+        if let (Some(assignee), Some(user_id)) = (&task.assignee, &operator_ctx.user_id) {
+            if assignee != user_id {
+                Err(
+                    AppError::new(
+                        ErrorCode::UnAuthorized,
+                        Some(&format!("Task ({}) has been assigned to ({})", task.id, assignee)),
+                        concat!(file!(), ":", line!()),
+                        None
+                    )
+                )?;
+            }
+
+            return Ok(());
+        }
+
         // check the current user has priviledge complete the task
         let mut task_query = TaskQuery::new(tran).id(&task.id);
         if let BpmnElement::Node(node) = element {
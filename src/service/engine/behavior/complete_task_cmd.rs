@@ -1,14 +1,16 @@
 use std::rc::Rc;
 
-use color_eyre::Result;
+use color_eyre::{Report, Result};
+use log4rs_macros::error;
 use tokio_postgres::Transaction;
 
-use crate::RcRefCell;
+use crate::{get_now, RcRefCell};
 use crate::service::engine::{
-    BaseOperator, BpmnElement, ContinueProcessOperator, NodeType, OperateRst, 
+    BaseOperator, BpmnElement, ContinueProcessOperator, EngineEvent, ListenerEvent, NodeType, notify_task_completed, OperateRst, 
     Operator, OperatorContext, ServiceTaskBehavior, UserTaskBehavior
 };
-use crate::model::{ApfRuExecution, ApfRuTask};
+use crate::error::{AppError, ErrorCode};
+use crate::model::{AddSignMode, ApfRuExecution, ApfRuTask, DeleteReason, JobType};
 use crate::dao::{ApfHiTaskinstDao, ApfRuIdentitylinkDao, ApfRuTaskDao};
 
 #[derive(Debug)]
@@ -27,21 +29,27 @@ impl CompleteTaskCmd {
         let task = self.base.current_task_ex()?;
 
         self.base.check_complete_task_priviledge(task.clone(), &self.base.element, operator_ctx, tran).await?;
+        self.check_sign_order(&task, tran).await?;
+        check_sub_tasks_done(&task, tran).await?;
+        self.base.fire_task_listeners(ListenerEvent::COMPLETE, &task, vec![], vec![], operator_ctx, tran).await?;
 
-        // execute behavior and mark end
-        self.execute_behavior(task.clone(), operator_ctx, tran).await?;
-
-        // update task history
-        let hi_task_dao = ApfHiTaskinstDao::new(tran);
-        hi_task_dao.mark_end(&task.id, operator_ctx.user_id.clone()).await?;
-
-        // delete user and group data from ru_identity_link
-        let ru_ident_dao = ApfRuIdentitylinkDao::new(tran);
-        ru_ident_dao.delete_by_task_id(&task.id).await?;
-
-        // delete runtime task
+        // the element is left by the last one of the task and its added signers
         let task_dao = ApfRuTaskDao::new(tran);
-        task_dao.delete(&task.id).await?;
+        let other_tasks = task_dao.find_by_execution(&task.execution_id)
+            .await?
+            .into_iter()
+            .filter(|t| t.id != task.id && t.parent_task_id.is_none())
+            .count();
+        if other_tasks > 0 {
+            self.close_task(&task, operator_ctx, tran).await?;
+            return Ok(OperateRst::default());
+        }
+
+        // execute behavior and mark end
+        if let Err(e) = self.execute_behavior(task.clone(), operator_ctx, tran).await {
+            return self.retry_by_job(&task, e, operator_ctx, tran).await;
+        }
+        self.close_task(&task, operator_ctx, tran).await?;
 
         // continue to next operator
         if operator_ctx.is_terminated()? {
@@ -57,13 +65,112 @@ impl CompleteTaskCmd {
                 self.base.current_exec(),
                 None);
             operator_ctx.queue.push(Operator::ContinueProcessOperator(continue_operator));
+        } else if self.is_async_after() {
+            // the job executor leaves the task after commit
+            let element_id = task.element_id_ex()?;
+            self.base.create_async_job(JobType::ASYNC_AFTER, &element_id, tran).await?;
         } else {
+            operator_ctx.completed_task_id = Some(task.id.clone());
             self.base.continue_outflow(operator_ctx, tran).await?;
         }
 
         Ok(OperateRst::default())
     }
 
+    async fn close_task(&self, task: &ApfRuTask, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        // update task history
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        hi_task_dao.mark_end(&task.id, operator_ctx.user_id.clone()).await?;
+
+        if let BpmnElement::Node(node) = &self.base.element {
+            if node.get_node_type() == NodeType::UserTask {
+                notify_task_completed(task, tran).await?;
+            }
+        }
+
+        // delete user and group data from ru_identity_link
+        let ru_ident_dao = ApfRuIdentitylinkDao::new(tran);
+        ru_ident_dao.delete_by_task_id(&task.id).await?;
+
+        // the sub-tasks which are not blocking are closed with the task
+        let task_dao = ApfRuTaskDao::new(tran);
+        for sub_task in task_dao.find_by_parent(&task.id).await?.iter() {
+            let reason = Some(DeleteReason::PARENT_COMPLETED.to_owned());
+            hi_task_dao.close(&sub_task.id, get_now(), operator_ctx.user_id.clone(), reason).await?;
+            ru_ident_dao.delete_by_task_id(&sub_task.id).await?;
+            task_dao.delete(&sub_task.id).await?;
+        }
+
+        // delete runtime task
+        task_dao.delete(&task.id).await?;
+
+        operator_ctx.emit(EngineEvent::TaskCompleted {
+            proc_inst_id: task.proc_inst_id.clone(),
+            task_id: task.id.clone(),
+            element_id: task.element_id_ex()?,
+            user_id: operator_ctx.user_id.clone(),
+        })?;
+
+        Ok(())
+    }
+
+    /// The task waits for its `before` signers, and the `after` signers wait for the task they are added to.
+    async fn check_sign_order(&self, task: &ApfRuTask, tran: &Transaction<'_>) -> Result<()> {
+        let task_dao = ApfRuTaskDao::new(tran);
+        let waiting = if task.sign_mode == Some(AddSignMode::After.name()) {
+            task_dao.find_by_execution(&task.execution_id)
+                .await?
+                .iter()
+                .any(|t| Some(&t.id) == task.sign_parent_id.as_ref())
+        } else {
+            task_dao.find_by_sign_parent(&task.id)
+                .await?
+                .iter()
+                .any(|t| t.sign_mode == Some(AddSignMode::Before.name()))
+        };
+
+        if waiting {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("task ({}) is waiting for the other signers", task.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The failed synchronous service task which declares `retries` is left to a job, which retries
+    /// it like an asynchronous one. The other failures are returned to the caller.
+    async fn retry_by_job(&self, task: &ApfRuTask, err: Report, operator_ctx: &OperatorContext, tran: &Transaction<'_>)
+            -> Result<OperateRst> {
+        let retries = match &self.base.element {
+            BpmnElement::Node(node) if node.get_node_type() == NodeType::ServiceTask => node.get_retries(),
+            _ => None,
+        };
+
+        match retries {
+            // the job being executed records the failure itself
+            Some(retries) if operator_ctx.job_id.is_none() => {
+                error!("service task ({}) failed, it is retried by a job: {:?}", task.id, err);
+                self.base.create_retry_job(&task.element_id_ex()?, retries, &err, tran).await?;
+
+                Ok(OperateRst::default())
+            },
+            _ => Err(err),
+        }
+    }
+
+    fn is_async_after(&self) -> bool {
+        match &self.base.element {
+            BpmnElement::Node(node) => node.is_async_after(),
+            BpmnElement::Edge(_) => false,
+        }
+    }
+
     async fn execute_behavior(&self, task: Rc<ApfRuTask>, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>)
             -> Result<()> {
         match &self.base.element {
@@ -93,4 +200,27 @@ impl CompleteTaskCmd {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// The task can not be completed while any of its blocking sub-tasks is open.
+pub async fn check_sub_tasks_done(task: &ApfRuTask, tran: &Transaction<'_>) -> Result<()> {
+    let task_dao = ApfRuTaskDao::new(tran);
+    let blocking = task_dao.find_by_parent(&task.id)
+        .await?
+        .iter()
+        .filter(|t| t.block_parent)
+        .count();
+
+    if blocking > 0 {
+        Err(
+            AppError::new(
+                ErrorCode::InvalidInput,
+                Some(&format!("task ({}) has {} sub-tasks to be done", task.id, blocking)),
+                concat!(file!(), ":", line!()),
+                None
+            )
+        )?;
+    }
+
+    Ok(())
+}
@@ -4,10 +4,10 @@ use std::sync::Arc;
 use color_eyre::Result;
 use tokio_postgres::Transaction;
 
-use crate::service::engine::{BaseOperator, ContinueProcessOperator, OperateRst, Operator, OperatorContext};
+use crate::service::engine::{BaseOperator, ContinueProcessOperator, EngineEvent, OperateRst, Operator, OperatorContext};
 use crate::dao::{ApfHiProcinstDao, ApfRuExecutionDao};
 use crate::get_now;
-use crate::model::{ApfReProcdef, NewApfHiProcinst, NewApfRuExecution};
+use crate::model::{ApfReProcdef, NewApfHiProcinst, NewApfRuExecution, WrappedValue};
 
 #[derive(Debug)]
 pub struct CreateAndStartProcessInstanceCmd {
@@ -32,6 +32,7 @@ impl CreateAndStartProcessInstanceCmd {
             business_key: self.business_key.clone(),
             is_active: 1,
             start_time: get_now(),
+            start_user: operator_ctx.user_id.clone(),
             element_id: Some(start_event.get_element_id()),
             ..Default::default()
         };
@@ -52,12 +53,31 @@ impl CreateAndStartProcessInstanceCmd {
         };
         hi_procinst_dao.create(&new_hi_procinst).await?;
 
+        operator_ctx.emit(EngineEvent::ProcessStarted {
+            proc_inst_id: proc_inst.id.clone(),
+            proc_def_id: proc_inst.proc_def_id.clone(),
+            business_key: proc_inst.business_key.clone(),
+            start_user: proc_inst.start_user.clone(),
+        })?;
 
+        // copy the initiator into the variable named by the start event
+        if let (Some(name), Some(user_id)) = (start_event.get_initiator(), &operator_ctx.user_id) {
+            operator_ctx.variables.insert(name, WrappedValue::Str(user_id.clone()));
+        }
 
         // create or update variables
         let base_operator = BaseOperator::new(proc_inst.clone(), None, start_event.clone(), None, None);
         base_operator.create_or_update_variables(&mut operator_ctx.variables, tran).await?;
 
+        let variables = operator_ctx.variables.clone();
+        for (name, value) in variables {
+            operator_ctx.emit(EngineEvent::VariableUpdated {
+                proc_inst_id: proc_inst.id.clone(),
+                name,
+                value,
+            })?;
+        }
+
         // continue to handle start event operator
         let continue_operator = ContinueProcessOperator::new(
             start_event.clone(),
@@ -6,7 +6,11 @@ use tokio_postgres::Transaction;
 use crate::{RcRefCell, get_now};
 use crate::dao::{ApfHiIdentitylinkDao, ApfHiTaskinstDao, ApfRuIdentitylinkDao, ApfRuTaskDao};
 use crate::model::{ApfRuExecution, IdentType, NewApfRuIdentitylink, NewApfRuTask};
-use crate::service::engine::{BaseOperator, BpmnElement, CompleteTaskCmd, NodeType, OperateRst, Operator, OperatorContext};
+use crate::service::engine::{
+    BaseOperator, BpmnElement, CompleteTaskCmd, DEFAULT_PRIORITY, EngineEvent, eval_date, eval_priority,
+    find_business_calendar, ListenerEvent, NodeType, notify_task, OperateRst, Operator, OperatorContext, resolve_candidates,
+    TaskNotifyEvent
+};
 
 #[derive(Debug)]
 pub struct CreateTaskCmd {
@@ -28,6 +32,25 @@ impl CreateTaskCmd {
 
         // create task
         let now = Some(get_now());
+        let priority = match element.get_priority() {
+            Some(expr) => eval_priority(&expr, &operator_ctx.variables)?,
+            None => DEFAULT_PRIORITY,
+        };
+        let bpmn_process = operator_ctx.bpmn_process_ex()?;
+        let calendar = find_business_calendar(&bpmn_process, &element.get_element_id())?;
+        let due_date = match element.get_due_date() {
+            Some(expr) => eval_date(&expr, &operator_ctx.variables, get_now(), calendar.as_ref())?,
+            None => None,
+        };
+        let follow_up_date = match element.get_follow_up_date() {
+            Some(expr) => eval_date(&expr, &operator_ctx.variables, get_now(), calendar.as_ref())?,
+            None => None,
+        };
+        // the escalation sweeper looks at the task once it's overdue
+        let next_sweep_time = match bpmn_process.get_escalation(&element.get_element_id()) {
+            Some(_) => due_date,
+            None => None,
+        };
         let new_ru_task = NewApfRuTask {
             rev: 1,
             suspension_state: 0,
@@ -42,6 +65,18 @@ impl CreateTaskCmd {
             description: element.get_description(),
             start_user_id: operator_ctx.user_id.clone(),
             form_key: element.get_from_key(),
+            assignee: element.get_assignee(),
+            owner: None,
+            delegation: None,
+            sign_parent_id: None,
+            sign_mode: None,
+            parent_task_id: None,
+            block_parent: false,
+            priority,
+            due_date,
+            follow_up_date,
+            prev_task_id: operator_ctx.completed_task_id.clone(),
+            next_sweep_time,
         };
 
         let task_dao = ApfRuTaskDao::new(tran);
@@ -53,6 +88,7 @@ impl CreateTaskCmd {
 
         // create execution history
         self.base.create_hi_actinst(Some(task.id.clone()), tran).await?;
+        self.base.on_activity_start(operator_ctx, tran).await?;
 
         // handle candidate users and groups
         if let BpmnElement::Node(node) = &self.base.element {
@@ -61,7 +97,19 @@ impl CreateTaskCmd {
 
             match node.get_node_type() {
                 NodeType::UserTask | NodeType::ServiceTask => {
-                    for group in node.candidate_groups().iter() {
+                    // the candidates can be changed by the listeners of create event
+                    let candidate_users = resolve_candidates(&node.candidate_users(), &operator_ctx.variables)?;
+                    let candidate_groups = resolve_candidates(&node.candidate_groups(), &operator_ctx.variables)?;
+                    let listener_ctx = self.base.fire_task_listeners(
+                        ListenerEvent::CREATE, 
+                        &task, 
+                        candidate_users, 
+                        candidate_groups, 
+                        operator_ctx, 
+                        tran
+                    ).await?;
+
+                    for group in listener_ctx.candidate_groups.iter() {
                         let new_ru_ident = NewApfRuIdentitylink {
                             ident_type: IdentType::group,
                             group_id: Some(group.to_owned()),
@@ -74,7 +122,7 @@ impl CreateTaskCmd {
                         hi_ident_dao.create_from_ident_link(&ru_ident).await?;
                     }
 
-                    for user in node.candidate_users().iter() {
+                    for user in listener_ctx.candidate_users.iter() {
                         let new_ru_ident = NewApfRuIdentitylink {
                             ident_type: IdentType::group,
                             group_id: None,
@@ -86,11 +134,39 @@ impl CreateTaskCmd {
                         let ru_ident = ru_ident_dao.create(&new_ru_ident).await?;
                         hi_ident_dao.create_from_ident_link(&ru_ident).await?;
                     }
+
+                    if task.assignee.is_some() {
+                        self.base.fire_task_listeners(ListenerEvent::ASSIGNMENT, &task, vec![], vec![], operator_ctx, tran).await?;
+                    }
+
+                    if node.get_node_type() == NodeType::UserTask {
+                        notify_task(
+                            TaskNotifyEvent::Created, 
+                            &task, 
+                            listener_ctx.candidate_users, 
+                            listener_ctx.candidate_groups, 
+                            tran
+                        ).await?;
+                    }
                 }
                 _ => {}
             }
         }
 
+        operator_ctx.emit(EngineEvent::TaskCreated {
+            proc_inst_id: task.proc_inst_id.clone(),
+            task_id: task.id.clone(),
+            element_id: task.element_id_ex()?,
+        })?;
+
+        if task.assignee.is_some() {
+            operator_ctx.emit(EngineEvent::TaskAssigned {
+                proc_inst_id: task.proc_inst_id.clone(),
+                task_id: task.id.clone(),
+                assignee: task.assignee.clone(),
+            })?;
+        }
+
         // continue to handle service task
         if let BpmnElement::Node(node) = &self.base.element {
             if node.get_node_type() == NodeType::ServiceTask {
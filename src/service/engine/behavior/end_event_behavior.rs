@@ -5,7 +5,7 @@ use log4rs_macros::debug;
 use tokio_postgres::Transaction;
 
 use crate::{RcRefCell, get_now};
-use crate::service::engine::{BaseOperator, BpmnElement, OperatorContext};
+use crate::service::engine::{BaseOperator, BpmnElement, EngineEvent, OperatorContext};
 use crate::dao::{ApfHiProcinstDao, ApfRuExecutionDao, ApfRuVariableDao};
 use crate::model::{ApfRuExecution, ApfRuTask};
 
@@ -33,6 +33,7 @@ impl EndEventBehavior {
 
         if let None = self.base.terminate_element {
             self.base.create_hi_actinst(None, tran).await?;
+            self.base.on_activity_start(operator_ctx, tran).await?;
         }
 
         self.leave(operator_ctx, tran).await
@@ -41,6 +42,7 @@ impl EndEventBehavior {
     pub async fn leave<'a>(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
         // mark end of current execution
         if let None = self.base.terminate_element {
+            self.base.on_activity_end(operator_ctx, tran).await?;
             self.base.mark_end_execution(operator_ctx, tran).await?;
         }
 
@@ -67,6 +69,21 @@ impl EndEventBehavior {
         // delete proc_inst record
         exec_dao.delete(&procinst_id).await?;
 
+        let event = match &self.base.terminate_element {
+            None => EngineEvent::ProcessCompleted {
+                proc_inst_id: procinst_id,
+                proc_def_id: self.base.proc_inst.proc_def_id.clone(),
+                end_element_id: element_id,
+            },
+            Some(_) => EngineEvent::ProcessTerminated {
+                proc_inst_id: procinst_id,
+                proc_def_id: self.base.proc_inst.proc_def_id.clone(),
+                element_id,
+                reason: operator_ctx.bpmn_process_ex()?.terminate_on_false.clone(),
+            },
+        };
+        operator_ctx.emit(event)?;
+
         Ok(())
     }
 }
\ No newline at end of file
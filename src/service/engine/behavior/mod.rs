@@ -8,6 +8,9 @@ pub mod start_event_behavior;
 pub mod base_operator;
 pub mod create_task_cmd;
 pub mod complete_task_cmd;
+pub mod reject_task_cmd;
+pub mod withdraw_task_cmd;
+pub mod modify_activity_cmd;
 pub mod service_task_behavior;
 pub mod user_task_behavior;
 pub mod exclusive_gateway_behavior;
@@ -26,6 +29,9 @@ use crate::model::ApfRuExecution;
 pub use base_operator::*;
 pub use create_task_cmd::*;
 pub use complete_task_cmd::*;
+pub use reject_task_cmd::*;
+pub use withdraw_task_cmd::*;
+pub use modify_activity_cmd::*;
 pub use service_task_behavior::*;
 pub use user_task_behavior::*;
 pub use exclusive_gateway_behavior::*;
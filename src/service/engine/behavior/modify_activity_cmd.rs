@@ -0,0 +1,136 @@
+use std::rc::Rc;
+
+use color_eyre::Result;
+use serde::Serialize;
+use tokio_postgres::Transaction;
+
+use crate::get_now;
+use crate::error::{AppError, ErrorCode};
+use crate::dao::ApfRuExecutionDao;
+use crate::model::{ApfRuExecution, DeleteReason};
+use crate::service::engine::{
+    BaseOperator, BpmnElement, ContinueProcessOperator, NodeType, OperateRst, Operator, OperatorContext,
+    TakeOutgoingFlowsOperator
+};
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "instruction")]
+pub enum ModificationInstruction {
+    CancelActivity { element_id: String },
+    StartBefore { element_id: String },
+    StartAfter { element_id: String },
+}
+
+impl ModificationInstruction {
+    pub fn element_id(&self) -> &str {
+        match self {
+            ModificationInstruction::CancelActivity { element_id } => element_id,
+            ModificationInstruction::StartBefore { element_id } => element_id,
+            ModificationInstruction::StartAfter { element_id } => element_id,
+        }
+    }
+}
+
+/// Executes one instruction of the process instance modification on the element.
+#[derive(Debug)]
+pub struct ModifyActivityCmd {
+    base: BaseOperator,
+    instruction: ModificationInstruction,
+}
+
+impl ModifyActivityCmd {
+    pub fn new(element: BpmnElement, proc_inst: Rc<ApfRuExecution>, instruction: ModificationInstruction) -> Self {
+        Self {
+            base: BaseOperator::new(proc_inst, None, element, None, None),
+            instruction,
+        }
+    }
+
+    pub async fn execute(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<OperateRst> {
+        let node = match &self.base.element {
+            BpmnElement::Node(node) => node.clone(),
+            BpmnElement::Edge(edge) => Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("flow ({}) can not be modified", edge.get_id())),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?,
+        };
+
+        match &self.instruction {
+            ModificationInstruction::CancelActivity { element_id } => {
+                let exec_dao = ApfRuExecutionDao::new(tran);
+                let execs: Vec<ApfRuExecution> = exec_dao.find_children_by_proc_inst(&self.base.proc_inst.id)
+                    .await?
+                    .into_iter()
+                    .filter(|exec| exec.element_id.as_ref() == Some(element_id))
+                    .collect();
+
+                if execs.is_empty() {
+                    Err(
+                        AppError::new(
+                            ErrorCode::InvalidInput,
+                            Some(&format!("element ({}) is not active in process instance ({})", element_id, self.base.proc_inst.id)),
+                            concat!(file!(), ":", line!()),
+                            None
+                        )
+                    )?;
+                }
+
+                for exec in execs.iter() {
+                    self.base.clear_execution(&exec.id, DeleteReason::MODIFIED, operator_ctx, tran).await?;
+                    exec_dao.delete(&exec.id).await?;
+                }
+            },
+            ModificationInstruction::StartBefore { element_id } => {
+                if node.get_node_type() == NodeType::StartEvent {
+                    Err(
+                        AppError::new(
+                            ErrorCode::InvalidInput,
+                            Some(&format!("can not start before the start event ({})", element_id)),
+                            concat!(file!(), ":", line!()),
+                            None
+                        )
+                    )?;
+                }
+
+                let current_exec = self.base.create_current_execution(
+                    element_id, get_now(), operator_ctx.user_id.clone(), tran).await?;
+                let continue_operator = ContinueProcessOperator::new(
+                    self.base.element.clone(),
+                    None,
+                    self.base.proc_inst.clone(),
+                    Some(current_exec),
+                    None);
+                operator_ctx.queue.push(Operator::ContinueProcessOperator(continue_operator));
+            },
+            ModificationInstruction::StartAfter { element_id } => {
+                let bpmn_process = operator_ctx.bpmn_process_ex()?;
+                let out_flows = node.out_flows(&bpmn_process);
+                if out_flows.len() != 1 {
+                    Err(
+                        AppError::new(
+                            ErrorCode::InvalidInput,
+                            Some(&format!("element ({}) must have exactly one outgoing flow", element_id)),
+                            concat!(file!(), ":", line!()),
+                            None
+                        )
+                    )?;
+                }
+
+                let out_flow = out_flows[0].clone();
+                let current_exec = self.base.create_current_execution(
+                    &out_flow.get_id(), get_now(), operator_ctx.user_id.clone(), tran).await?;
+                let next_operator = TakeOutgoingFlowsOperator::new(
+                    BpmnElement::Edge(out_flow),
+                    self.base.proc_inst.clone(),
+                    Some(current_exec));
+                operator_ctx.queue.push(Operator::TakeOutgoingFlowsOperator(next_operator));
+            },
+        }
+
+        Ok(OperateRst::default())
+    }
+}
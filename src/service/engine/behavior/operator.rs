@@ -2,8 +2,8 @@ use color_eyre::Result;
 use tokio_postgres::Transaction;
 
 use crate::service::engine::{
-    CompleteTaskCmd, ContinueProcessOperator, CreateAndStartProcessInstanceCmd, CreateTaskCmd, OperateRst,
-    OperatorContext, TakeOutgoingFlowsOperator
+    CompleteTaskCmd, ContinueProcessOperator, CreateAndStartProcessInstanceCmd, CreateTaskCmd, ModifyActivityCmd, 
+    OperateRst, OperatorContext, RejectTaskCmd, TakeOutgoingFlowsOperator, WithdrawTaskCmd
 };
 
 #[derive(Debug)]
@@ -13,6 +13,9 @@ pub enum Operator {
     TakeOutgoingFlowsOperator(TakeOutgoingFlowsOperator),
     CreateTaskCmd(CreateTaskCmd),
     CompleteTaskCmd(CompleteTaskCmd),
+    RejectTaskCmd(RejectTaskCmd),
+    WithdrawTaskCmd(WithdrawTaskCmd),
+    ModifyActivityCmd(ModifyActivityCmd),
 }
 
 unsafe impl Send for Operator{}
@@ -30,6 +33,15 @@ impl Operator {
             Operator::CompleteTaskCmd(opt) => {
                 opt.execute(operator_ctx, tran).await
             },
+            Operator::RejectTaskCmd(opt) => {
+                opt.execute(operator_ctx, tran).await
+            },
+            Operator::WithdrawTaskCmd(opt) => {
+                opt.execute(operator_ctx, tran).await
+            },
+            Operator::ModifyActivityCmd(opt) => {
+                opt.execute(operator_ctx, tran).await
+            },
             Operator::CreateTaskCmd(opt) => {
                 opt.execute(operator_ctx, tran).await
             },
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use color_eyre::Result;
 
 use crate::error::{AppError, ErrorCode};
-use crate::service::engine::{BpmnProcess, Operator};
+use crate::service::engine::{BpmnProcess, dispatch_in_transaction, EngineEvent, Operator};
 use crate::model::WrappedValue;
 
 #[derive(Debug)]
@@ -14,6 +14,11 @@ pub struct OperatorContext {
     pub variables: HashMap<String, WrappedValue>,
     pub queue: Vec<Operator>,
     pub bpmn_process: Option<Arc<BpmnProcess>>,
+    pub events: Vec<EngineEvent>,
+    /// The task whose completion is being continued, the tasks created on the way record it.
+    pub completed_task_id: Option<String>,
+    /// The job being executed, it records the failures instead of the service tasks.
+    pub job_id: Option<String>,
 }
 
 #[allow(unused)]
@@ -25,6 +30,9 @@ impl OperatorContext {
             variables: HashMap::new(),
             queue: Vec::<Operator>::new(),
             bpmn_process: None,
+            events: vec![],
+            completed_task_id: None,
+            job_id: None,
         }
     }
 
@@ -35,6 +43,9 @@ impl OperatorContext {
             variables,
             queue: Vec::<Operator>::new(),
             bpmn_process: None,
+            events: vec![],
+            completed_task_id: None,
+            job_id: None,
         }
     }
 
@@ -53,6 +64,19 @@ impl OperatorContext {
         Ok(bpmn_process)
     }
 
+    /// Dispatches the event to the in-transaction subscribers, and keeps it for
+    /// the after-commit subscribers.
+    pub fn emit(&mut self, event: EngineEvent) -> Result<()> {
+        dispatch_in_transaction(&event)?;
+        self.events.push(event);
+
+        Ok(())
+    }
+
+    pub fn take_events(&mut self) -> Vec<EngineEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     pub fn is_terminated(&self) -> Result<bool> {
         let mut rst = false;
         let tmp_terminate_varname = self.bpmn_process_ex()?.terminate_on_false.clone();
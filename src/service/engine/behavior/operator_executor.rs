@@ -29,6 +29,7 @@ impl OperatorExecutor {
 
     pub async fn execute(&mut self, operator: Operator, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<OperateRst> 
     {
+        operator_ctx.completed_task_id = None;
         operator_ctx.queue.push(operator);
         let rst = self.run(operator_ctx, tran).await;
 
@@ -53,6 +53,7 @@ impl ParallelGatewayBehavior {
 
                 // create execution history for the element
                 self.base.create_hi_actinst(None, tran).await?; // 多创建了一次
+                self.base.on_activity_start(operator_ctx, tran).await?;
 
                 // only all flows have been merged then it can leave
                 self.leave(operator_ctx, tran).await?;
@@ -63,6 +64,7 @@ impl ParallelGatewayBehavior {
     }
 
     async fn leave<'a>(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<OperateRst> {
+        self.base.on_activity_end(operator_ctx, tran).await?;
         self.base.mark_end_execution(operator_ctx, tran).await?;
 
         let element = &self.base.element;
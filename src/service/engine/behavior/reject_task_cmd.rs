@@ -0,0 +1,169 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use color_eyre::Result;
+use tokio_postgres::Transaction;
+
+use crate::{get_now, RcRefCell};
+use crate::error::{AppError, ErrorCode};
+use crate::dao::{ApfHiActinstDao, ApfHiCommentDao, ApfRuExecutionDao};
+use crate::model::{ApfRuExecution, ApfRuTask, CommentType, DeleteReason, NewApfHiComment};
+use crate::service::engine::{
+    BaseOperator, BpmnElement, BpmnProcess, ContinueProcessOperator, EngineEvent, NodeType, OperateRst, Operator,
+    OperatorContext
+};
+
+/// Moves the process instance back to a user task which has been completed before. The
+/// executions between the target and the current task are removed, including the parallel
+/// branches which are forked after the target.
+#[derive(Debug)]
+pub struct RejectTaskCmd {
+    base: BaseOperator,
+    comment: Option<String>,
+}
+
+impl RejectTaskCmd {
+    pub fn new(
+        target_element: BpmnElement,
+        proc_inst: Rc<ApfRuExecution>,
+        current_exec: Option<RcRefCell<ApfRuExecution>>,
+        current_task: Option<Rc<ApfRuTask>>,
+        comment: Option<String>
+    ) -> Self {
+        Self {
+            base: BaseOperator::new(proc_inst, current_exec, target_element, None, current_task),
+            comment,
+        }
+    }
+
+    pub async fn execute(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<OperateRst> {
+        let task = self.base.current_task_ex()?;
+        let element_id = task.element_id_ex()?;
+        let target_element_id = self.base.element.get_element_id();
+        let bpmn_process = operator_ctx.bpmn_process_ex()?;
+        let element = bpmn_process.element_map.get(&element_id).ok_or(
+            AppError::notfound_error(concat!(file!(), ":", line!())))?;
+
+        self.base.check_complete_task_priviledge(task.clone(), element, operator_ctx, tran).await?;
+        self.check_target(&bpmn_process, &element_id, tran).await?;
+
+        // tear down the executions between the target and the current task
+        let current_exec = self.base.current_excution_ex()?;
+        let current_exec_id = current_exec.borrow().id.clone();
+        let torn_execs = self.find_torn_executions(&bpmn_process, &element_id, &current_exec_id, tran).await?;
+
+        self.base.clear_execution(&current_exec_id, DeleteReason::REJECTED, operator_ctx, tran).await?;
+        if let Some(comment) = &self.comment {
+            let comment_dao = ApfHiCommentDao::new(tran);
+            comment_dao.create(&NewApfHiComment {
+                comment_type: CommentType::REJECT.to_owned(),
+                user_id: operator_ctx.user_id.clone(),
+                task_id: Some(task.id.clone()),
+                proc_inst_id: Some(task.proc_inst_id.clone()),
+                message: Some(comment.clone()),
+                create_time: get_now(),
+            }).await?;
+        }
+
+        let exec_dao = ApfRuExecutionDao::new(tran);
+        for exec in torn_execs.iter() {
+            self.base.clear_execution(&exec.id, DeleteReason::REJECTED, operator_ctx, tran).await?;
+            exec_dao.delete(&exec.id).await?;
+        }
+
+        operator_ctx.emit(EngineEvent::TaskRejected {
+            proc_inst_id: task.proc_inst_id.clone(),
+            task_id: task.id.clone(),
+            element_id,
+            target_element_id: target_element_id.clone(),
+            user_id: operator_ctx.user_id.clone(),
+            comment: self.comment.clone(),
+        })?;
+
+        // recreate the target task on the current execution
+        self.base.mark_begin_exection(&target_element_id, operator_ctx.user_id.clone(), get_now(), tran).await?;
+
+        let continue_operator = ContinueProcessOperator::new(
+            self.base.element.clone(),
+            None,
+            self.base.proc_inst.clone(),
+            Some(current_exec),
+            None);
+        operator_ctx.queue.push(Operator::ContinueProcessOperator(continue_operator));
+
+        Ok(OperateRst::default())
+    }
+
+    /// The target must be a user task before the current one, and it has been completed in the process instance.
+    async fn check_target(&self, bpmn_process: &BpmnProcess, element_id: &str, tran: &Transaction<'_>) -> Result<()> {
+        let target_element_id = self.base.element.get_element_id();
+        let is_user_task = match &self.base.element {
+            BpmnElement::Node(node) => node.get_node_type() == NodeType::UserTask,
+            BpmnElement::Edge(_) => false,
+        };
+
+        if !is_user_task || !bpmn_process.upstream_nodes(element_id).contains(&target_element_id) {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("element ({}) is not a user task before ({})", target_element_id, element_id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        let hi_act_dao = ApfHiActinstDao::new(tran);
+        let finished = hi_act_dao.find_finished_by_element(&self.base.proc_inst.id, &target_element_id).await?;
+        if finished.is_empty() {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("element ({}) has not been completed in process instance ({})", target_element_id, self.base.proc_inst.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The executions on the path from the target to the current task, and the executions of the
+    /// parallel branches which are forked on that path.
+    async fn find_torn_executions(
+        &self,
+        bpmn_process: &BpmnProcess,
+        element_id: &str,
+        current_exec_id: &str,
+        tran: &Transaction<'_>
+    ) -> Result<Vec<ApfRuExecution>> {
+        let target_element_id = self.base.element.get_element_id();
+        let after_target = bpmn_process.downstream_nodes(&target_element_id);
+        let mut on_path = bpmn_process.upstream_nodes(element_id);
+        on_path.insert(element_id.to_owned());
+        on_path.retain(|id| after_target.contains(id));
+
+        let mut in_branches = HashSet::new();
+        for id in on_path.iter() {
+            if let Some(BpmnElement::Node(node)) = bpmn_process.element_map.get(id) {
+                if node.get_node_type() == NodeType::ParallelGateway {
+                    in_branches.extend(bpmn_process.downstream_nodes(id));
+                }
+            }
+        }
+
+        let exec_dao = ApfRuExecutionDao::new(tran);
+        let rst = exec_dao.find_children_by_proc_inst(&self.base.proc_inst.id)
+            .await?
+            .into_iter()
+            .filter(|exec| exec.id != current_exec_id)
+            .filter(|exec| match &exec.element_id {
+                Some(id) => on_path.contains(id) || in_branches.contains(id),
+                None => false,
+            })
+            .collect();
+
+        Ok(rst)
+    }
+}
@@ -19,6 +19,8 @@ impl ServiceTaskBehavior {
         }
     }
 
+    /// Invokes the delegate of the service task. The failure of a synchronous service task is
+    /// returned to the caller, unless the task declares `retries` and is retried by a job.
     pub async fn execute(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
         let task = self.base.current_task_ex()?;
 
@@ -30,10 +32,17 @@ impl ServiceTaskBehavior {
             task.form_key.clone().unwrap_or("?".to_owned()),
             task.id);
 
+        if let BpmnElement::Node(node) = &self.base.element {
+            if let Some(delegate) = node.get_delegate() {
+                self.base.invoke_delegate(&delegate, &task, operator_ctx, tran).await?;
+            }
+        }
+
         self.leave(operator_ctx, tran).await
     }
 
     pub async fn leave(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()>  {
+        self.base.on_activity_end(operator_ctx, tran).await?;
         self.base.mark_end_execution(operator_ctx, tran).await
     }
 }
\ No newline at end of file
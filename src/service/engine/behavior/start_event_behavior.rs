@@ -42,6 +42,7 @@ impl StartEventBehavior {
 
         // create execution history
         self.base.create_hi_actinst(None, tran).await?;
+        self.base.on_activity_start(operator_ctx, tran).await?;
 
         // #[cfg(debug_assertions)]
         debug!("StartEvent (process: {:?}, element: {}) is executed", self.base.proc_inst.id, self.base.element.get_element_id());
@@ -50,6 +51,7 @@ impl StartEventBehavior {
     }
 
     pub async fn leave<'a>(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()>  {
+        self.base.on_activity_end(operator_ctx, tran).await?;
         self.base.mark_end_execution(operator_ctx, tran).await?;
         self.base.continue_outflow(operator_ctx, tran).await?;
 
@@ -4,8 +4,10 @@ use color_eyre::Result;
 use log4rs_macros::debug;
 use tokio_postgres::Transaction;
 
-use crate::service::engine::{BaseOperator, BpmnElement, ContinueProcessOperator, OperateRst, Operator, OperatorContext};
-use crate::model::ApfRuExecution;
+use crate::service::engine::{
+    BaseOperator, BpmnElement, ContinueProcessOperator, ListenerEvent, OperateRst, Operator, OperatorContext
+};
+use crate::model::{ApfRuExecution, JobType};
 use crate::{get_now, RcRefCell};
 use crate::error::{AppError, ErrorCode};
 
@@ -41,11 +43,19 @@ impl TakeOutgoingFlowsOperator {
                 // #[cfg(debug_assertions)]
                 debug!("Sequence Flow (process: {:?}, element: {}, source: {}, target: {})", self.base.proc_inst.id, edge.get_id(), edge.get_source(), target_id);
 
+                self.base.fire_execution_listeners(ListenerEvent::TAKE, operator_ctx, tran).await?;
+
                 // handle target node
                 if let BpmnElement::Node(node) = target_element {
                     // set element id for current exection
                     self.base.mark_begin_exection(&node.get_id(), operator_ctx.user_id.clone(), get_now(), tran).await?;
 
+                    // the job executor continues with the target node after commit
+                    if node.is_async_before() {
+                        self.base.create_async_job(JobType::ASYNC_BEFORE, &node.get_id(), tran).await?;
+                        return Ok(OperateRst::default());
+                    }
+
                     let continue_operator = ContinueProcessOperator::new(
                         BpmnElement::Node(node.clone()),
                         None,
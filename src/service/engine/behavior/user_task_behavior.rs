@@ -34,6 +34,7 @@ impl UserTaskBehavior {
     }
 
     pub async fn leave(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()>  {
+        self.base.on_activity_end(operator_ctx, tran).await?;
         self.base.mark_end_execution(operator_ctx, tran).await
     }
 }
\ No newline at end of file
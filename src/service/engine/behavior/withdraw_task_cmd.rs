@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use color_eyre::Result;
+use tokio_postgres::Transaction;
+
+use crate::get_now;
+use crate::error::{AppError, ErrorCode};
+use crate::dao::{ApfHiOplogDao, ApfHiTaskinstDao, ApfRuExecutionDao, ApfRuTaskDao};
+use crate::model::{ApfHiTaskinst, ApfRuExecution, ApfRuTask, DeleteReason, NewApfHiOplog, OpType};
+use crate::service::engine::{
+    BaseOperator, BpmnElement, BpmnProcess, ContinueProcessOperator, EngineEvent, NodeType, OperateRst, Operator,
+    OperatorContext
+};
+
+/// Takes back the completion of a task. It's only allowed when the tasks created by the
+/// completion have not been handled yet, they are removed and the task is created again.
+#[derive(Debug)]
+pub struct WithdrawTaskCmd {
+    base: BaseOperator,
+    hi_task: ApfHiTaskinst,
+}
+
+impl WithdrawTaskCmd {
+    pub fn new(element: BpmnElement, proc_inst: Rc<ApfRuExecution>, hi_task: ApfHiTaskinst) -> Self {
+        Self {
+            base: BaseOperator::new(proc_inst, None, element, None, None),
+            hi_task,
+        }
+    }
+
+    pub async fn execute(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<OperateRst> {
+        let hi_task = &self.hi_task;
+        let element_id = self.base.element.get_element_id();
+
+        if hi_task.end_user_id.is_none() || hi_task.end_user_id != operator_ctx.user_id {
+            Err(
+                AppError::new(
+                    ErrorCode::UnAuthorized,
+                    Some(
+                        &format!(
+                            "Current user ({}) is not the one who completed the task ({})",
+                            operator_ctx.user_id.clone().unwrap_or("?".to_owned()),
+                            hi_task.id
+                        )
+                    ),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        if hi_task.end_time.is_none() || hi_task.delete_reason.is_some() {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("task ({}) is not completed", hi_task.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        let bpmn_process = operator_ctx.bpmn_process_ex()?;
+        let next_tasks = self.find_next_tasks(&bpmn_process, tran).await?;
+
+        // keep the execution of the completed task if it's still there
+        let exec_id = next_tasks
+            .iter()
+            .find(|t| t.execution_id == hi_task.execution_id)
+            .unwrap_or(&next_tasks[0])
+            .execution_id
+            .clone();
+
+        let exec_dao = ApfRuExecutionDao::new(tran);
+        for task in next_tasks.iter() {
+            self.base.clear_execution(&task.execution_id, DeleteReason::WITHDRAWN, operator_ctx, tran).await?;
+            if task.execution_id != exec_id {
+                exec_dao.delete(&task.execution_id).await?;
+            }
+        }
+
+        // the completion stays in the history, the withdrawal is recorded besides it
+        let detail = serde_json::json!({
+            "next_tasks": next_tasks.iter().map(|t| t.id.clone()).collect::<Vec<String>>(),
+        });
+        let oplog_dao = ApfHiOplogDao::new(tran);
+        oplog_dao.create(&NewApfHiOplog {
+            op_type: OpType::WITHDRAW_TASK.to_owned(),
+            proc_def_id: Some(hi_task.proc_def_id.clone()),
+            proc_inst_id: Some(hi_task.proc_inst_id.clone()),
+            task_id: Some(hi_task.id.clone()),
+            element_id: Some(element_id.clone()),
+            user_id: operator_ctx.user_id.clone(),
+            detail: Some(detail.to_string()),
+            create_time: get_now(),
+        }).await?;
+
+        operator_ctx.emit(EngineEvent::TaskWithdrawn {
+            proc_inst_id: hi_task.proc_inst_id.clone(),
+            task_id: hi_task.id.clone(),
+            element_id: element_id.clone(),
+            user_id: operator_ctx.user_id.clone(),
+        })?;
+
+        // create the task again on the kept execution
+        exec_dao.mark_begin(&exec_id, &element_id, operator_ctx.user_id.clone(), get_now()).await?;
+        let current_exec = exec_dao.get_by_id(&exec_id).await?;
+
+        let continue_operator = ContinueProcessOperator::new(
+            self.base.element.clone(),
+            None,
+            self.base.proc_inst.clone(),
+            Some(Rc::new(RefCell::new(current_exec))),
+            None);
+        operator_ctx.queue.push(Operator::ContinueProcessOperator(continue_operator));
+
+        Ok(OperateRst::default())
+    }
+
+    /// The tasks which are created by the completion, they must be untouched. Passing a joining
+    /// parallel gateway is not supported, since the other branches have been merged.
+    async fn find_next_tasks(&self, bpmn_process: &BpmnProcess, tran: &Transaction<'_>) -> Result<Vec<ApfRuTask>> {
+        let element_id = self.base.element.get_element_id();
+        let after_task = bpmn_process.downstream_nodes(&element_id);
+
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        let hi_next_tasks = hi_task_dao.find_by_prev_task(&self.hi_task.id).await?;
+        let handled = hi_next_tasks
+            .iter()
+            .find(|t| t.end_time.is_some() && t.delete_reason != Some(DeleteReason::WITHDRAWN.to_owned()));
+        if let Some(handled) = handled {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("the task ({}) after ({}) has been handled", handled.id, self.hi_task.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        let task_dao = ApfRuTaskDao::new(tran);
+        let mut next_tasks = vec![];
+        for hi_next_task in hi_next_tasks.iter().filter(|t| t.end_time.is_none()) {
+            next_tasks.push(task_dao.get_by_id(&hi_next_task.id).await?);
+        }
+
+        if next_tasks.is_empty() {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("there is no task to withdraw after ({})", self.hi_task.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        for task in next_tasks.iter() {
+            // the task which has been claimed or handed over is in someone's hands
+            let element_assignee = bpmn_process.element_map.get(&task.element_id_ex()?).and_then(|el| el.get_assignee());
+            if task.assignee != element_assignee {
+                Err(
+                    AppError::new(
+                        ErrorCode::InvalidInput,
+                        Some(&format!("the task ({}) after ({}) has been claimed", task.id, self.hi_task.id)),
+                        concat!(file!(), ":", line!()),
+                        None
+                    )
+                )?;
+            }
+
+            // so are the signers and the sub-tasks added to it
+            if !task_dao.find_by_sign_parent(&task.id).await?.is_empty() || !task_dao.find_by_parent(&task.id).await?.is_empty() {
+                Err(
+                    AppError::new(
+                        ErrorCode::InvalidInput,
+                        Some(&format!("the task ({}) after ({}) has been handled", task.id, self.hi_task.id)),
+                        concat!(file!(), ":", line!()),
+                        None
+                    )
+                )?;
+            }
+
+            let between = bpmn_process.upstream_nodes(&task.element_id_ex()?);
+            for id in between.iter().filter(|id| after_task.contains(*id)) {
+                if let Some(BpmnElement::Node(node)) = bpmn_process.element_map.get(id) {
+                    if node.get_node_type() == NodeType::ParallelGateway && node.in_flows(bpmn_process).len() > 1 {
+                        Err(
+                            AppError::new(
+                                ErrorCode::NotSupportError,
+                                Some(&format!("can not withdraw task ({}) across the joining gateway ({})", self.hi_task.id, id)),
+                                concat!(file!(), ":", line!()),
+                                None
+                            )
+                        )?;
+                    }
+                }
+            }
+        }
+
+        Ok(next_tasks)
+    }
+}
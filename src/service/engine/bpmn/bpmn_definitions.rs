@@ -39,15 +39,18 @@ impl BpmnDefinitions {
                         }
                         NodeType::UserTask => {
                             Self::allow_at_least_one_inflow(node, in_flows_len)?;
-                            Self::allow_one_outflow(node, out_flows_len)?;
+                            Self::allow_at_least_one_outflow(node, out_flows_len)?;
+                            Self::allow_unambiguous_outflows(node, bpmn_proc)?;
                         }
                         NodeType::ServiceTask => {
                             Self::allow_at_least_one_inflow(node, in_flows_len)?;
-                            Self::allow_one_outflow(node, out_flows_len)?;
+                            Self::allow_at_least_one_outflow(node, out_flows_len)?;
+                            Self::allow_unambiguous_outflows(node, bpmn_proc)?;
                         }
                         NodeType::ExclusiveGateway => {
                             Self::allow_at_least_one_inflow(node, in_flows_len)?;
                             Self::allow_at_least_one_outflow(node, out_flows_len)?;
+                            Self::allow_unambiguous_outflows(node, bpmn_proc)?;
                         }
                         NodeType::ParallelGateway => {
                             Self::allow_at_least_one_inflow(node, in_flows_len)?;
@@ -140,6 +143,62 @@ impl BpmnDefinitions {
         Ok(())
     }
 
+    /// The default flow must be an outgoing flow of the node without a condition, and
+    /// when a node has more than one outgoing flow, every other flow must have a condition.
+    fn allow_unambiguous_outflows(node: &Arc<dyn BpmnNode>, bpmn_proc: &BpmnProcess) -> Result<()> {
+        let out_flows = node.out_flows(bpmn_proc);
+        let default_flow = node.get_default_flow();
+
+        if let Some(default_flow_id) = &default_flow {
+            let flow = out_flows.iter().find(|flow| flow.get_id() == *default_flow_id);
+            match flow {
+                None => {
+                    let msg = format!("{:?}({}) 的默认输出边 ({}) 不存在", node.get_node_type(), node.get_id(), default_flow_id);
+
+                    Err(AppError::new(
+                        ErrorCode::ParseError,
+                        Some(&msg),
+                        concat!(file!(), ":", line!()),
+                        None
+                    ))?
+                },
+                Some(flow) => {
+                    if flow.get_condition_expr().is_some() {
+                        let msg = format!("{:?}({}) 的默认输出边 ({}) 不能设置条件表达式", node.get_node_type(), node.get_id(), default_flow_id);
+
+                        Err(AppError::new(
+                            ErrorCode::ParseError,
+                            Some(&msg),
+                            concat!(file!(), ":", line!()),
+                            None
+                        ))?
+                    }
+                }
+            }
+        }
+
+        if out_flows.len() > 1 {
+            for flow in out_flows.iter() {
+                if Some(flow.get_id()) == default_flow {
+                    continue;
+                }
+
+                if flow.get_condition_expr().is_none() {
+                    let msg = format!("{:?}({}) 有多条输出边时，非默认输出边 ({}) 必须设置条件表达式", node.get_node_type(), node.get_id(), flow.get_id());
+
+                    Err(AppError::new(
+                        ErrorCode::ParseError,
+                        Some(&msg),
+                        concat!(file!(), ":", line!()),
+                        None
+                    ))?
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     // validate rules for edge ---------------------------------------------------------
     fn allow_source_and_target(edge: &Arc<dyn BpmnEdge>, bpmn_proc: &BpmnProcess) -> Result<()> {
         let from_node = edge.from_node(bpmn_proc);
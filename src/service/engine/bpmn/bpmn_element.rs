@@ -64,5 +64,71 @@ impl BpmnElement {
         }
     }
 
+    pub fn get_assignee(&self) -> Option<String> {
+        match self {
+            BpmnElement::Edge(_) => {
+                None
+            }
+            BpmnElement::Node(el) => {
+                el.get_assignee()
+            }
+        }
+    }
+
+    pub fn get_priority(&self) -> Option<String> {
+        match self {
+            BpmnElement::Edge(_) => {
+                None
+            }
+            BpmnElement::Node(el) => {
+                el.get_priority()
+            }
+        }
+    }
+
+    pub fn get_due_date(&self) -> Option<String> {
+        match self {
+            BpmnElement::Edge(_) => {
+                None
+            }
+            BpmnElement::Node(el) => {
+                el.get_due_date()
+            }
+        }
+    }
+
+    pub fn get_follow_up_date(&self) -> Option<String> {
+        match self {
+            BpmnElement::Edge(_) => {
+                None
+            }
+            BpmnElement::Node(el) => {
+                el.get_follow_up_date()
+            }
+        }
+    }
+
+    pub fn get_business_calendar(&self) -> Option<String> {
+        match self {
+            BpmnElement::Edge(_) => {
+                None
+            }
+            BpmnElement::Node(el) => {
+                el.get_business_calendar()
+            }
+        }
+    }
+
+    pub fn get_initiator(&self) -> Option<String> {
+        match self {
+            BpmnElement::Edge(_) => {
+                None
+            }
+            BpmnElement::Node(el) => {
+                el.get_initiator()
+            }
+        }
+    }
+
 }
 
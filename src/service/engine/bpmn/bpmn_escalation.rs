@@ -0,0 +1,16 @@
+use crate::service::engine::IsoDuration;
+
+/// The reminders and the escalation of an overdue user task, declared by `escalation` in the
+/// `extensionElements`. The durations are counted from the due date of the task.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BpmnEscalation {
+    /// Reminds the handlers of the task again and again at the interval.
+    pub remind_interval: Option<IsoDuration>,
+    /// Escalates the task once when it has been overdue for so long.
+    pub escalate_after: Option<IsoDuration>,
+    /// The task is handed over to the user when it's escalated.
+    pub reassign_to: Option<String>,
+    /// The candidates added to the task when it's escalated, e.g. the manager group.
+    pub candidate_users: Vec<String>,
+    pub candidate_groups: Vec<String>,
+}
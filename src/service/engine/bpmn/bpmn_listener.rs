@@ -0,0 +1,67 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListenerType {
+    Execution,
+    Task,
+}
+
+impl Display for ListenerType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListenerType::Execution => write!(f, "executionListener"),
+            ListenerType::Task => write!(f, "taskListener"),
+        }
+    }
+}
+
+pub enum ListenerEvent {}
+
+impl ListenerEvent {
+    // events of execution listener
+    pub const START: &'static str = "start";
+    pub const END: &'static str = "end";
+    pub const TAKE: &'static str = "take";
+
+    // events of task listener
+    pub const CREATE: &'static str = "create";
+    pub const ASSIGNMENT: &'static str = "assignment";
+    pub const COMPLETE: &'static str = "complete";
+    pub const DELETE: &'static str = "delete";
+
+    pub fn is_valid(listener_type: ListenerType, event: &str) -> bool {
+        match listener_type {
+            ListenerType::Execution => [Self::START, Self::END, Self::TAKE].contains(&event),
+            ListenerType::Task => [Self::CREATE, Self::ASSIGNMENT, Self::COMPLETE, Self::DELETE].contains(&event),
+        }
+    }
+}
+
+/// A listener declared in the `extensionElements` of a bpmn element. It either calls a rust
+/// callback registered on the `ProcessEngine` by `delegate` name or runs a javascript `script`.
+#[derive(Debug, Clone)]
+pub struct BpmnListener {
+    pub listener_type: ListenerType,
+    pub event: String,
+    pub delegate: Option<String>,
+    pub script: Option<String>,
+    pub result_variable: Option<String>,
+}
+
+impl BpmnListener {
+    pub fn new(
+        listener_type: ListenerType, 
+        event: String, 
+        delegate: Option<String>, 
+        script: Option<String>, 
+        result_variable: Option<String>
+    ) -> Self {
+        Self {
+            listener_type,
+            event,
+            delegate,
+            script,
+            result_variable,
+        }
+    }
+}
@@ -49,6 +49,54 @@ pub trait BpmnNode : Debug + Send + Sync {
         None
     }
 
+    fn get_default_flow(&self) -> Option<String> {
+        None
+    }
+
+    fn is_async_before(&self) -> bool {
+        false
+    }
+
+    fn is_async_after(&self) -> bool {
+        false
+    }
+
+    fn get_delegate(&self) -> Option<String> {
+        None
+    }
+
+    /// The times the failed synchronous service task is retried by a job.
+    fn get_retries(&self) -> Option<i32> {
+        None
+    }
+
+    /// The user who gets the task as soon as it's created.
+    fn get_assignee(&self) -> Option<String> {
+        None
+    }
+
+    /// The expressions of the task attributes, they are evaluated when the task is created.
+    fn get_priority(&self) -> Option<String> {
+        None
+    }
+
+    fn get_due_date(&self) -> Option<String> {
+        None
+    }
+
+    fn get_follow_up_date(&self) -> Option<String> {
+        None
+    }
+
+    /// The name of the business calendar, which overrides the one of the process.
+    fn get_business_calendar(&self) -> Option<String> {
+        None
+    }
+
+    fn get_initiator(&self) -> Option<String> {
+        None
+    }
+
     fn out_flows(&self, process: &BpmnProcess) -> Vec<Arc<dyn BpmnEdge>> {
         let mut rst = vec![];
 
@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use crate::service::engine::{BpmnManager, NodeType};
-use super::BpmnElement;
+use super::{BpmnEdge, BpmnElement, BpmnEscalation, BpmnListener, ListenerType};
 use color_eyre::Result;
 use crate::error::{AppError, ErrorCode};
 
@@ -13,6 +13,9 @@ pub struct BpmnProcess {
     pub elements: Vec<BpmnElement>,
     pub element_map: HashMap<String, BpmnElement>,
     pub end_event_terminate_node:  Option<BpmnElement>,
+    pub listener_map: HashMap<String, Vec<BpmnListener>>,
+    pub escalation_map: HashMap<String, BpmnEscalation>,
+    pub business_calendar: Option<String>,
 }
 
 impl BpmnProcess {
@@ -25,6 +28,9 @@ impl BpmnProcess {
             elements: vec![],
             element_map: HashMap::new(),
             end_event_terminate_node: Some(BpmnManager::create_end_event_terminate_node()),
+            listener_map: HashMap::new(),
+            escalation_map: HashMap::new(),
+            business_calendar: None,
         }
     }
 
@@ -54,4 +60,62 @@ impl BpmnProcess {
 
         Ok(rst)
     }
+
+    pub fn get_listeners(&self, element_id: &str, listener_type: ListenerType, event: &str) -> Vec<BpmnListener> {
+        match self.listener_map.get(element_id) {
+            None => vec![],
+            Some(listeners) => {
+                listeners
+                    .iter()
+                    .filter(|l| l.listener_type == listener_type && l.event == event)
+                    .map(|l| l.clone())
+                    .collect()
+            }
+        }
+    }
+
+    pub fn get_escalation(&self, element_id: &str) -> Option<BpmnEscalation> {
+        self.escalation_map.get(element_id).cloned()
+    }
+
+    /// The business calendar of the element, or the one of the process.
+    pub fn get_business_calendar(&self, element_id: &str) -> Option<String> {
+        self.element_map
+            .get(element_id)
+            .and_then(|el| el.get_business_calendar())
+            .or(self.business_calendar.clone())
+    }
+
+    /// The nodes which can be reached from the element by following the sequence flows.
+    pub fn downstream_nodes(&self, element_id: &str) -> HashSet<String> {
+        self.reachable_nodes(element_id, true)
+    }
+
+    /// The nodes from which the element can be reached by following the sequence flows.
+    pub fn upstream_nodes(&self, element_id: &str) -> HashSet<String> {
+        self.reachable_nodes(element_id, false)
+    }
+
+    fn reachable_nodes(&self, element_id: &str, forward: bool) -> HashSet<String> {
+        let mut rst = HashSet::new();
+        let mut pending = vec![element_id.to_owned()];
+
+        while let Some(current) = pending.pop() {
+            for item in &self.elements {
+                if let BpmnElement::Edge(flow) = item {
+                    let (from, to) = if forward {
+                        (flow.get_source(), flow.get_target())
+                    } else {
+                        (flow.get_target(), flow.get_source())
+                    };
+
+                    if from == current && rst.insert(to.clone()) {
+                        pending.push(to);
+                    }
+                }
+            }
+        }
+
+        rst
+    }
 }
\ No newline at end of file
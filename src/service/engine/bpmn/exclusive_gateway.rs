@@ -4,6 +4,7 @@ use super::{BpmnNode, NodeType};
 pub struct ExclusiveGateway {
     pub id: String,
     pub description: Option<String>,
+    pub default_flow: Option<String>,
 }
 
 impl BpmnNode for ExclusiveGateway {
@@ -19,13 +20,17 @@ impl BpmnNode for ExclusiveGateway {
         self.description.clone()
     }
 
+    fn get_default_flow(&self) -> Option<String> {
+        self.default_flow.clone()
+    }
 }
 
 impl ExclusiveGateway {
-    pub fn new(id: String, description: Option<String>) -> Self {
+    pub fn new(id: String, description: Option<String>, default_flow: Option<String>) -> Self {
         Self {
             id,
             description,
+            default_flow,
         }
     }
 }
\ No newline at end of file
@@ -10,6 +10,8 @@ pub mod service_task;
 pub mod exclusive_gateway;
 pub mod parallel_gateway;
 pub mod sequence_flow;
+pub mod bpmn_listener;
+pub mod bpmn_escalation;
 
 pub use bpmn_definitions::*;
 pub use bpmn_process::*;
@@ -22,4 +24,6 @@ pub use user_task::*;
 pub use service_task::*;
 pub use exclusive_gateway::*;
 pub use parallel_gateway::*;
-pub use sequence_flow::*;
\ No newline at end of file
+pub use sequence_flow::*;
+pub use bpmn_listener::*;
+pub use bpmn_escalation::*;
\ No newline at end of file
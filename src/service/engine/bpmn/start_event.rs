@@ -5,6 +5,8 @@ use super::{BpmnNode, NodeType};
 pub struct StartEvent {
     pub id: String,
     pub description: Option<String>,
+    /// The name of the variable which the user who starts the process is copied into.
+    pub initiator: Option<String>,
 }
 
 impl BpmnNode for StartEvent {
@@ -19,6 +21,10 @@ impl BpmnNode for StartEvent {
     fn get_description(&self) -> Option<String> {
         self.description.clone()
     }
+
+    fn get_initiator(&self) -> Option<String> {
+        self.initiator.clone()
+    }
 }
 
 impl StartEvent {
@@ -26,6 +32,7 @@ impl StartEvent {
         Self {
             id,
             description,
+            initiator: None,
         }
     }
 }
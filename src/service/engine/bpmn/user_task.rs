@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use crate::service::engine::split_candidates;
 use super::{BpmnNode, NodeType};
 
 #[derive(Debug, Default)]
@@ -9,6 +10,14 @@ pub struct UserTask {
     pub description: Option<String>,
     pub candidate_groups: Arc<Vec<String>>,
     pub candidate_users: Arc<Vec<String>>,
+    pub default_flow: Option<String>,
+    pub async_before: bool,
+    pub async_after: bool,
+    pub assignee: Option<String>,
+    pub priority: Option<String>,
+    pub due_date: Option<String>,
+    pub follow_up_date: Option<String>,
+    pub business_calendar: Option<String>,
 }
 
 impl BpmnNode for UserTask {
@@ -39,6 +48,38 @@ impl BpmnNode for UserTask {
     fn candidate_users(&self) -> Arc<Vec<String>>{
         self.candidate_users.clone()
     }
+
+    fn get_default_flow(&self) -> Option<String> {
+        self.default_flow.clone()
+    }
+
+    fn is_async_before(&self) -> bool {
+        self.async_before
+    }
+
+    fn is_async_after(&self) -> bool {
+        self.async_after
+    }
+
+    fn get_assignee(&self) -> Option<String> {
+        self.assignee.clone()
+    }
+
+    fn get_priority(&self) -> Option<String> {
+        self.priority.clone()
+    }
+
+    fn get_due_date(&self) -> Option<String> {
+        self.due_date.clone()
+    }
+
+    fn get_follow_up_date(&self) -> Option<String> {
+        self.follow_up_date.clone()
+    }
+
+    fn get_business_calendar(&self) -> Option<String> {
+        self.business_calendar.clone()
+    }
 }
 
 impl UserTask {
@@ -48,28 +89,12 @@ impl UserTask {
         from_key: Option<String>, 
         description: Option<String>,
         candidate_groups: Option<String>, 
-        candidate_users: Option<String>
+        candidate_users: Option<String>,
+        default_flow: Option<String>
     ) -> Self {
-        let mut candidate_groups_arr = vec![];
-        let mut candidate_users_arr = vec![];
-
-        if let Some(cand) = candidate_groups {
-            let cand = cand.to_lowercase();
-            let cand_arr = cand.split(',');
-
-            for cand in cand_arr {
-                candidate_groups_arr.push(cand.to_owned());
-            }
-        }
-
-        if let Some(cand) = candidate_users {
-            let cand = cand.to_lowercase();
-            let cand_arr = cand.split(',');
-
-            for cand in cand_arr {
-                candidate_users_arr.push(cand.to_owned());
-            }
-        }
+        // the expressions are evaluated when the task is created
+        let candidate_groups_arr = candidate_groups.map_or(vec![], |cand| split_candidates(&cand));
+        let candidate_users_arr = candidate_users.map_or(vec![], |cand| split_candidates(&cand));
 
         Self {
             id,
@@ -78,6 +103,14 @@ impl UserTask {
             description,
             candidate_groups: Arc::new(candidate_groups_arr),
             candidate_users: Arc::new(candidate_users_arr),
+            default_flow,
+            async_before: false,
+            async_after: false,
+            assignee: None,
+            priority: None,
+            due_date: None,
+            follow_up_date: None,
+            business_calendar: None,
         }
     }
 }
\ No newline at end of file
@@ -2,13 +2,14 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use color_eyre::Result;
 use log4rs_macros::error;
-use xml_doc_log4rs::Document;
+use xml_doc_log4rs::{Document, Element};
 use crate::error::{AppError, ErrorCode};
+use crate::service::engine::IsoDuration;
 use super::{StartEvent, BpmnElement,
     BpmnProcess, EndEvent, UserTask,
     ServiceTask, SequenceFlow, BpmnNode,
     BpmnEdge, ExclusiveGateway, BpmnDefinitions,
-    ParallelGateway};
+    ParallelGateway, BpmnListener, ListenerType, ListenerEvent, BpmnEscalation};
 
 pub struct BpmnManager {}
 
@@ -56,12 +57,14 @@ impl BpmnManager {
         let terminate_on_false = proc_el.attribute(&doc, "terminate_on_false")
             .and_then(|s| Some(s.to_owned()));
 
-        let bpmn_proc = BpmnProcess::new(
+        let mut bpmn_proc = BpmnProcess::new(
             proc_id.to_owned(),
             proc_name,
             proc_description,
             terminate_on_false,
         );
+        bpmn_proc.business_calendar = proc_el.attribute(&doc, "businessCalendar")
+            .and_then(|s| Some(s.to_owned()));
 
         let mut bpmn_def = BpmnDefinitions::new(xml, bpmn_proc);
 
@@ -72,11 +75,23 @@ impl BpmnManager {
             let description = child_el.attribute(&doc, "description")
                 .and_then(|s| Some(s.to_owned()));
 
+            let listeners = Self::parse_listeners(&doc, &child_el, id)?;
+            if !listeners.is_empty() {
+                bpmn_def.process.listener_map.insert(id.to_owned(), listeners);
+            }
+            if let Some(escalation) = Self::parse_escalation(&doc, &child_el, id)? {
+                bpmn_def.process.escalation_map.insert(id.to_owned(), escalation);
+            }
+
             let pe_elements = &mut bpmn_def.process.elements;
             let element_map = &mut bpmn_def.process.element_map;
 
             if el_name == "startEvent" {
-                let node = Arc::new(StartEvent::new(id.to_owned(), description.clone()));
+                let mut start_event = StartEvent::new(id.to_owned(), description.clone());
+                start_event.initiator = child_el.attribute(&doc, "initiator")
+                    .and_then(|s| Some(s.to_owned()));
+
+                let node = Arc::new(start_event);
                 Self::add_node(id, node, pe_elements, element_map)?;
             } else if el_name == "endEvent" {
                 let node = Arc::new(EndEvent::new(id.to_owned(), description.clone()));
@@ -90,8 +105,24 @@ impl BpmnManager {
                     .and_then(|s| Some(s.to_owned()));
                 let candidate_users = child_el.attribute(&doc, "candidateUsers")
                     .and_then(|s| Some(s.to_owned()));
+                let default_flow = child_el.attribute(&doc, "default")
+                    .and_then(|s| Some(s.to_owned()));
 
-                let node = Arc::new(UserTask::new(id.to_owned(), name, from_key, description.clone(), candidate_groups, candidate_users));
+                let mut user_task = UserTask::new(id.to_owned(), name, from_key, description.clone(), candidate_groups, candidate_users, default_flow);
+                user_task.async_before = Self::parse_bool_attribute(&doc, &child_el, "asyncBefore");
+                user_task.async_after = Self::parse_bool_attribute(&doc, &child_el, "asyncAfter");
+                user_task.assignee = child_el.attribute(&doc, "assignee")
+                    .and_then(|s| Some(s.to_owned()));
+                user_task.priority = child_el.attribute(&doc, "priority")
+                    .and_then(|s| Some(s.to_owned()));
+                user_task.due_date = child_el.attribute(&doc, "dueDate")
+                    .and_then(|s| Some(s.to_owned()));
+                user_task.follow_up_date = child_el.attribute(&doc, "followUpDate")
+                    .and_then(|s| Some(s.to_owned()));
+                user_task.business_calendar = child_el.attribute(&doc, "businessCalendar")
+                    .and_then(|s| Some(s.to_owned()));
+
+                let node = Arc::new(user_task);
                 Self::add_node(id, node, pe_elements, element_map)?;
             } else if el_name == "serviceTask" {
                 let name = child_el.attribute(&doc, "name")
@@ -102,11 +133,33 @@ impl BpmnManager {
                     .and_then(|s| Some(s.to_owned()));
                 let candidate_users = child_el.attribute(&doc, "candidateUsers")
                     .and_then(|s| Some(s.to_owned()));
+                let default_flow = child_el.attribute(&doc, "default")
+                    .and_then(|s| Some(s.to_owned()));
 
-                let node = Arc::new(ServiceTask::new(id.to_owned(), name, from_key, description.clone(), candidate_groups, candidate_users));
+                let mut service_task = ServiceTask::new(id.to_owned(), name, from_key, description.clone(), candidate_groups, candidate_users, default_flow);
+                service_task.async_before = Self::parse_bool_attribute(&doc, &child_el, "asyncBefore");
+                service_task.async_after = Self::parse_bool_attribute(&doc, &child_el, "asyncAfter");
+                service_task.delegate = child_el.attribute(&doc, "delegate")
+                    .and_then(|s| Some(s.to_owned()));
+                service_task.retries = match child_el.attribute(&doc, "retries") {
+                    None => None,
+                    Some(s) => match s.trim().parse::<i32>() {
+                        Ok(retries) if retries > 0 => Some(retries),
+                        _ => Err(AppError::new(
+                            ErrorCode::ParseError,
+                            Some(&format!("serviceTask({}) 的 retries 属性 ({}) 不正确", id, s)),
+                            concat!(file!(), ":", line!()),
+                            None
+                        ))?,
+                    },
+                };
+
+                let node = Arc::new(service_task);
                 Self::add_node(id, node, pe_elements, element_map)?;
             } else if el_name == "exclusiveGateway" {
-                let node = Arc::new(ExclusiveGateway::new(id.to_owned(), description.clone()));
+                let default_flow = child_el.attribute(&doc, "default")
+                    .and_then(|s| Some(s.to_owned()));
+                let node = Arc::new(ExclusiveGateway::new(id.to_owned(), description.clone(), default_flow));
                 Self::add_node(id, node, pe_elements, element_map)?;
             } else if el_name == "parallelGateway" {
                 let node = Arc::new(ParallelGateway::new(id.to_owned(), description.clone()));
@@ -135,6 +188,108 @@ impl BpmnManager {
         Ok(bpmn_def)
     }
 
+    fn parse_bool_attribute(doc: &Document, el: &Element, name: &str) -> bool {
+        el.attribute(doc, name)
+            .and_then(|s| Some(s.trim().eq_ignore_ascii_case("true")))
+            .unwrap_or(false)
+    }
+
+    fn parse_listeners(doc: &Document, el: &Element, element_id: &str) -> Result<Vec<BpmnListener>> {
+        let mut listeners = vec![];
+        let ext_el = match el.find(doc, "extensionElements") {
+            None => return Ok(listeners),
+            Some(ext_el) => ext_el,
+        };
+
+        for listener_el in ext_el.child_elements(doc) {
+            let listener_type = match listener_el.name(doc) {
+                "executionListener" => ListenerType::Execution,
+                "taskListener" => ListenerType::Task,
+                _ => continue,
+            };
+
+            let event = listener_el.attribute(doc, "event").unwrap_or("").to_owned();
+            if !ListenerEvent::is_valid(listener_type, &event) {
+                Err(AppError::new(
+                    ErrorCode::ParseError, 
+                    Some(&format!("{}({}) 的 {} 事件类型 ({}) 不正确", el.name(doc), element_id, listener_type, event)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                ))?
+            }
+
+            let delegate = listener_el.attribute(doc, "delegate")
+                .and_then(|s| Some(s.to_owned()));
+            let result_variable = listener_el.attribute(doc, "resultVariable")
+                .and_then(|s| Some(s.to_owned()));
+            let script = listener_el.find(doc, "script")
+                .and_then(|script_el| Some(script_el.text_content(doc).trim().to_owned()));
+
+            if delegate.is_none() && script.is_none() {
+                Err(AppError::new(
+                    ErrorCode::ParseError, 
+                    Some(&format!("{}({}) 的 {} 必须设置 delegate 属性或 script 节点", el.name(doc), element_id, listener_type)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                ))?
+            }
+
+            listeners.push(BpmnListener::new(listener_type, event, delegate, script, result_variable));
+        }
+
+        Ok(listeners)
+    }
+
+    fn parse_escalation(doc: &Document, el: &Element, element_id: &str) -> Result<Option<BpmnEscalation>> {
+        let escalation_el = match el.find(doc, "extensionElements").and_then(|ext_el| ext_el.find(doc, "escalation")) {
+            None => return Ok(None),
+            Some(escalation_el) => escalation_el,
+        };
+
+        let parse_duration = |name: &str| -> Result<Option<IsoDuration>> {
+            match escalation_el.attribute(doc, name) {
+                None => Ok(None),
+                Some(s) => match IsoDuration::parse(s) {
+                    Ok(d) => Ok(Some(d)),
+                    Err(_) => Err(AppError::new(
+                        ErrorCode::ParseError,
+                        Some(&format!("{}({}) 的 escalation 属性 {} ({}) 不正确", el.name(doc), element_id, name, s)),
+                        concat!(file!(), ":", line!()),
+                        None
+                    ))?,
+                },
+            }
+        };
+        let split = |name: &str| -> Vec<String> {
+            escalation_el.attribute(doc, name)
+                .map_or(vec![], |s| s.split(',').map(|v| v.trim().to_owned()).filter(|v| !v.is_empty()).collect())
+        };
+
+        let escalation = BpmnEscalation {
+            remind_interval: parse_duration("remindInterval")?,
+            escalate_after: parse_duration("escalateAfter")?,
+            reassign_to: escalation_el.attribute(doc, "reassignTo")
+                .and_then(|s| Some(s.to_owned())),
+            candidate_users: split("candidateUsers"),
+            candidate_groups: split("candidateGroups"),
+        };
+
+        let has_target = escalation.reassign_to.is_some()
+            || !escalation.candidate_users.is_empty()
+            || !escalation.candidate_groups.is_empty();
+        if (escalation.remind_interval.is_none() && escalation.escalate_after.is_none())
+            || (escalation.escalate_after.is_some() && !has_target) {
+            Err(AppError::new(
+                ErrorCode::ParseError,
+                Some(&format!("{}({}) 的 escalation 必须设置 remindInterval，或者 escalateAfter 和升级的处理人", el.name(doc), element_id)),
+                concat!(file!(), ":", line!()),
+                None
+            ))?
+        }
+
+        Ok(Some(escalation))
+    }
+
     fn add_node(
         id: &str, 
         node: Arc<dyn BpmnNode>, 
@@ -212,6 +367,143 @@ mod tests {
             deploy_builder.new_deployment.new_bytearray.bytes.clone().unwrap_or(Vec::new())).unwrap();
     }
 
+    #[test]
+    fn test_parse_default_flow() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<definitions>
+    <process id="process_default_flow">
+        <startEvent id="startEvent_1"/>
+        <sequenceFlow id="flow_1" sourceRef="startEvent_1" targetRef="approval_1" />
+        <userTask id="approval_1" name="审批" default="flow_3" />
+        <sequenceFlow id="flow_2" sourceRef="approval_1" targetRef="decision_1">
+            <conditionExpression><![CDATA[ amount > 100 ]]></conditionExpression>
+        </sequenceFlow>
+        <sequenceFlow id="flow_3" sourceRef="approval_1" targetRef="endEvent_1" />
+        <exclusiveGateway id="decision_1" default="flow_5"/>
+        <sequenceFlow id="flow_4" sourceRef="decision_1" targetRef="endEvent_1">
+            <conditionExpression><![CDATA[ pass == true ]]></conditionExpression>
+        </sequenceFlow>
+        <sequenceFlow id="flow_5" sourceRef="decision_1" targetRef="endEvent_1" />
+        <endEvent id="endEvent_1"/>
+    </process>
+</definitions>"#;
+
+        let bpmn_def = BpmnManager::new().parse(xml.to_owned()).unwrap();
+        let element_map = &bpmn_def.process.element_map;
+        if let Some(BpmnElement::Node(node)) = element_map.get("decision_1") {
+            assert_eq!(node.get_default_flow(), Some("flow_5".to_owned()));
+        } else {
+            panic!("decision_1 not found");
+        }
+        if let Some(BpmnElement::Node(node)) = element_map.get("approval_1") {
+            assert_eq!(node.get_default_flow(), Some("flow_3".to_owned()));
+        } else {
+            panic!("approval_1 not found");
+        }
+
+        // two unconditional flows without default flow are ambiguous
+        let ambiguous_xml = xml.replace(r#" default="flow_5""#, "");
+        let rst = BpmnManager::new().parse(ambiguous_xml);
+        assert!(rst.is_err());
+
+        // default flow must be an outgoing flow of the node
+        let invalid_xml = xml.replace(r#"default="flow_3""#, r#"default="flow_4""#);
+        let rst = BpmnManager::new().parse(invalid_xml);
+        assert!(rst.is_err());
+    }
+
+    #[test]
+    fn test_parse_listeners() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<definitions>
+    <process id="process_listener">
+        <startEvent id="startEvent_1"/>
+        <sequenceFlow id="flow_1" sourceRef="startEvent_1" targetRef="approval_1">
+            <extensionElements>
+                <executionListener event="take" delegate="audit" />
+            </extensionElements>
+        </sequenceFlow>
+        <userTask id="approval_1" name="审批">
+            <extensionElements>
+                <executionListener event="start" delegate="audit" />
+                <taskListener event="create" resultVariable="approver">
+                    <script><![CDATA[ "user_" + applicant ]]></script>
+                </taskListener>
+            </extensionElements>
+        </userTask>
+        <sequenceFlow id="flow_2" sourceRef="approval_1" targetRef="endEvent_1" />
+        <endEvent id="endEvent_1"/>
+    </process>
+</definitions>"#;
+
+        let bpmn_def = BpmnManager::new().parse(xml.to_owned()).unwrap();
+        let process = &bpmn_def.process;
+        assert_eq!(process.get_listeners("flow_1", ListenerType::Execution, ListenerEvent::TAKE).len(), 1);
+        assert_eq!(process.get_listeners("approval_1", ListenerType::Execution, ListenerEvent::START).len(), 1);
+        assert_eq!(process.get_listeners("approval_1", ListenerType::Execution, ListenerEvent::END).len(), 0);
+
+        let task_listeners = process.get_listeners("approval_1", ListenerType::Task, ListenerEvent::CREATE);
+        assert_eq!(task_listeners.len(), 1);
+        assert_eq!(task_listeners[0].script, Some(r#""user_" + applicant"#.to_owned()));
+        assert_eq!(task_listeners[0].result_variable, Some("approver".to_owned()));
+
+        let invalid_xml = xml.replace(r#"event="take""#, r#"event="create""#);
+        assert!(BpmnManager::new().parse(invalid_xml).is_err());
+    }
+
+    #[test]
+    fn test_parse_async_continuation() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<definitions>
+    <process id="process_async">
+        <startEvent id="startEvent_1"/>
+        <sequenceFlow id="flow_1" sourceRef="startEvent_1" targetRef="notify_1" />
+        <serviceTask id="notify_1" name="通知" asyncBefore="true" asyncAfter="false" delegate="send_notice" />
+        <sequenceFlow id="flow_2" sourceRef="notify_1" targetRef="approval_1" />
+        <userTask id="approval_1" name="审批" asyncAfter="true" />
+        <sequenceFlow id="flow_3" sourceRef="approval_1" targetRef="endEvent_1" />
+        <endEvent id="endEvent_1"/>
+    </process>
+</definitions>"#;
+
+        let bpmn_def = BpmnManager::new().parse(xml.to_owned()).unwrap();
+        let element_map = &bpmn_def.process.element_map;
+        if let Some(BpmnElement::Node(node)) = element_map.get("notify_1") {
+            assert!(node.is_async_before());
+            assert!(!node.is_async_after());
+            assert_eq!(node.get_delegate(), Some("send_notice".to_owned()));
+        } else {
+            panic!("notify_1 not found");
+        }
+        if let Some(BpmnElement::Node(node)) = element_map.get("approval_1") {
+            assert!(!node.is_async_before());
+            assert!(node.is_async_after());
+        } else {
+            panic!("approval_1 not found");
+        }
+    }
+
+    #[test]
+    fn test_parse_assignee() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<definitions>
+    <process id="process_assignee">
+        <startEvent id="startEvent_1"/>
+        <sequenceFlow id="flow_1" sourceRef="startEvent_1" targetRef="apply_1" />
+        <userTask id="apply_1" name="申请" assignee="user_1" />
+        <sequenceFlow id="flow_2" sourceRef="apply_1" targetRef="approval_1" />
+        <userTask id="approval_1" name="审批" candidateUsers="user_2,user_3" />
+        <sequenceFlow id="flow_3" sourceRef="approval_1" targetRef="endEvent_1" />
+        <endEvent id="endEvent_1"/>
+    </process>
+</definitions>"#;
+
+        let bpmn_def = BpmnManager::new().parse(xml.to_owned()).unwrap();
+        let element_map = &bpmn_def.process.element_map;
+        assert_eq!(element_map.get("apply_1").unwrap().get_assignee(), Some("user_1".to_owned()));
+        assert_eq!(element_map.get("approval_1").unwrap().get_assignee(), None);
+    }
+
     #[test]
     fn test_create_end_event_node() {
         let _rst = BpmnManager::create_end_event_terminate_node();
@@ -0,0 +1,235 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use chrono::{Datelike, Duration, Local, NaiveDate, TimeZone, Weekday};
+use color_eyre::Result;
+use log4rs_macros::error;
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+use crate::common::global_cfg;
+use crate::error::{AppError, ErrorCode};
+use crate::service::engine::{BpmnProcess, IsoDuration};
+
+pub const DEFAULT_BUSINESS_CALENDAR: &'static str = "default";
+
+/// Decides the working days, the durations of due dates and escalations are counted in working
+/// days when a calendar is selected by `businessCalendar` of the process or the user task.
+pub trait BusinessCalendar: Send + Sync {
+    fn is_working_day(&self, date: NaiveDate) -> bool;
+
+    /// Adds the duration to the timestamp (milliseconds). The days are counted in working days
+    /// and the time of the day is kept, the hours, minutes and seconds are added as they are.
+    fn add_duration(&self, time: i64, duration: &IsoDuration) -> i64 {
+        let mut date = match Local.timestamp_millis_opt(time).single() {
+            Some(dt) => dt.naive_local().date(),
+            None => return time + duration.to_millis(),
+        };
+        let mut days = 0;
+        let mut counted = 0;
+        while counted < duration.days {
+            date = match date.succ_opt() {
+                Some(date) => date,
+                None => break,
+            };
+            days += 1;
+            if self.is_working_day(date) {
+                counted += 1;
+            }
+        }
+
+        time + Duration::days(days).num_milliseconds() + duration.millis
+    }
+}
+
+/// Monday to Friday are the working days.
+#[derive(Debug, Default)]
+pub struct DefaultBusinessCalendar {}
+
+impl BusinessCalendar for DefaultBusinessCalendar {
+    fn is_working_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CalendarFile {
+    #[serde(default)]
+    holidays: Vec<String>,
+    #[serde(default)]
+    workdays: Vec<String>,
+}
+
+/// The weekends and the holidays are off, except the make-up working days. They are loaded from
+/// a yaml file, in which a date is `2026-10-01` and a range of dates is `2026-10-01~2026-10-07`.
+#[derive(Debug, Default)]
+pub struct ConfigBusinessCalendar {
+    holidays: HashSet<NaiveDate>,
+    workdays: HashSet<NaiveDate>,
+}
+
+impl ConfigBusinessCalendar {
+    pub fn from_file(path: &str) -> Result<Self> {
+        let s = std::fs::read_to_string(path)?;
+        Self::from_yaml(&s)
+    }
+
+    pub fn from_yaml(s: &str) -> Result<Self> {
+        let file: CalendarFile = serde_yaml::from_str(s)?;
+
+        Ok(Self {
+            holidays: Self::parse_dates(&file.holidays)?,
+            workdays: Self::parse_dates(&file.workdays)?,
+        })
+    }
+
+    fn parse_dates(items: &[String]) -> Result<HashSet<NaiveDate>> {
+        let parse = |s: &str| NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+            .map_err(|_| AppError::new(
+                ErrorCode::InvalidInput,
+                Some(&format!("invalid date ({}) of business calendar", s)),
+                concat!(file!(), ":", line!()),
+                None
+            ));
+
+        let mut rst = HashSet::new();
+        for item in items {
+            let (begin, end) = match item.split_once('~') {
+                Some((begin, end)) => (parse(begin)?, parse(end)?),
+                None => (parse(item)?, parse(item)?),
+            };
+
+            let mut date = begin;
+            while date <= end {
+                rst.insert(date);
+                date = match date.succ_opt() {
+                    Some(date) => date,
+                    None => break,
+                };
+            }
+        }
+
+        Ok(rst)
+    }
+}
+
+impl BusinessCalendar for ConfigBusinessCalendar {
+    fn is_working_day(&self, date: NaiveDate) -> bool {
+        if self.workdays.contains(&date) {
+            true
+        } else if self.holidays.contains(&date) {
+            false
+        } else {
+            DefaultBusinessCalendar::default().is_working_day(date)
+        }
+    }
+}
+
+// The calendars of `engine.business_calendars` in config are loaded when it's first used.
+static CALENDAR_REGISTRY: OnceCell<RwLock<HashMap<String, Arc<dyn BusinessCalendar>>>> = OnceCell::new();
+
+fn calendar_registry() -> &'static RwLock<HashMap<String, Arc<dyn BusinessCalendar>>> {
+    CALENDAR_REGISTRY.get_or_init(|| {
+        let mut registry: HashMap<String, Arc<dyn BusinessCalendar>> = HashMap::new();
+        registry.insert(DEFAULT_BUSINESS_CALENDAR.to_owned(), Arc::new(DefaultBusinessCalendar::default()));
+
+        let files = global_cfg().engine
+            .as_ref()
+            .and_then(|engine| engine.business_calendars.clone())
+            .unwrap_or_default();
+        for (name, path) in files {
+            match ConfigBusinessCalendar::from_file(&path) {
+                Ok(calendar) => {
+                    registry.insert(name, Arc::new(calendar));
+                },
+                Err(e) => error!("load business calendar ({}) from ({}) failed: {:?}", name, path, e),
+            }
+        }
+
+        RwLock::new(registry)
+    })
+}
+
+/// Adds the calendar, the one which has the same name will be replaced.
+pub fn register_business_calendar(name: &str, calendar: Arc<dyn BusinessCalendar>) {
+    let mut registry = calendar_registry().write().unwrap();
+    registry.insert(name.to_owned(), calendar);
+}
+
+pub fn get_business_calendar(name: &str) -> Result<Arc<dyn BusinessCalendar>> {
+    let registry = calendar_registry().read().unwrap();
+    let calendar = registry.get(name).cloned().ok_or(
+        AppError::new(
+            ErrorCode::NotFound,
+            Some(&format!("business calendar ({}) is not registered", name)),
+            concat!(file!(), ":", line!()),
+            None
+        )
+    )?;
+
+    Ok(calendar)
+}
+
+/// The calendar selected by `businessCalendar` of the element or its process.
+pub fn find_business_calendar(bpmn_process: &BpmnProcess, element_id: &str) -> Result<Option<Arc<dyn BusinessCalendar>>> {
+    match bpmn_process.get_business_calendar(element_id) {
+        Some(name) => Ok(Some(get_business_calendar(&name)?)),
+        None => Ok(None),
+    }
+}
+
+/// The durations are counted in calendar days when no calendar is selected.
+pub fn add_duration(time: i64, duration: &IsoDuration, calendar: Option<&Arc<dyn BusinessCalendar>>) -> i64 {
+    match calendar {
+        Some(calendar) => calendar.add_duration(time, duration),
+        None => time + duration.to_millis(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(s: &str) -> i64 {
+        let dt = chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap();
+        Local.from_local_datetime(&dt).unwrap().timestamp_millis()
+    }
+
+    #[test]
+    fn test_add_duration() {
+        let calendar: Arc<dyn BusinessCalendar> = Arc::new(DefaultBusinessCalendar::default());
+        let duration = IsoDuration::parse("P1DT2H").unwrap();
+
+        // friday to monday
+        assert_eq!(add_duration(millis("2026-10-16T10:00:00"), &duration, Some(&calendar)), millis("2026-10-19T12:00:00"));
+        assert_eq!(add_duration(millis("2026-10-16T10:00:00"), &duration, None), millis("2026-10-17T12:00:00"));
+
+        let yaml = r#"
+holidays:
+  - 2026-10-01~2026-10-07
+workdays:
+  - 2026-09-20
+  - 2026-10-10
+"#;
+        let calendar = ConfigBusinessCalendar::from_yaml(yaml).unwrap();
+        assert!(!calendar.is_working_day(NaiveDate::from_ymd_opt(2026, 10, 5).unwrap()));
+        assert!(calendar.is_working_day(NaiveDate::from_ymd_opt(2026, 10, 10).unwrap()));
+
+        // the national day holidays are skipped, the saturday after them is a working day
+        let duration = IsoDuration::parse("P2D").unwrap();
+        assert_eq!(calendar.add_duration(millis("2026-09-30T09:00:00"), &duration), millis("2026-10-09T09:00:00"));
+        assert_eq!(calendar.add_duration(millis("2026-10-09T09:00:00"), &duration), millis("2026-10-12T09:00:00"));
+
+        assert!(ConfigBusinessCalendar::from_yaml("holidays: [2026-13-01]").is_err());
+    }
+
+    #[test]
+    fn test_config_calendar() {
+        // `cn` is configured by `engine.business_calendars`
+        let calendar = get_business_calendar("cn").unwrap();
+        assert!(!calendar.is_working_day(NaiveDate::from_ymd_opt(2026, 2, 17).unwrap()));
+        assert!(calendar.is_working_day(NaiveDate::from_ymd_opt(2026, 2, 14).unwrap()));
+        assert!(calendar.is_working_day(NaiveDate::from_ymd_opt(2026, 10, 19).unwrap()));
+        assert!(get_business_calendar("not_exist").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use color_eyre::Result;
+use once_cell::sync::OnceCell;
+use regex::Regex;
+
+use crate::model::WrappedValue;
+use crate::service::engine::{convert_js_value, run_script_with_vars};
+
+/// Finds the candidates by the arguments, e.g. `manager_of` returns the manager of the user.
+pub type CandidateFunction = Arc<dyn Fn(&[WrappedValue]) -> Result<Vec<String>> + Send + Sync>;
+
+static CANDIDATE_FUNCTIONS: OnceCell<RwLock<HashMap<String, CandidateFunction>>> = OnceCell::new();
+
+fn candidate_functions() -> &'static RwLock<HashMap<String, CandidateFunction>> {
+    CANDIDATE_FUNCTIONS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+pub fn register_candidate_function(name: &str, function: CandidateFunction) {
+    let mut functions = candidate_functions().write().unwrap();
+    functions.insert(name.to_owned(), function);
+}
+
+pub fn get_candidate_function(name: &str) -> Option<CandidateFunction> {
+    let functions = candidate_functions().read().unwrap();
+    functions.get(name).cloned()
+}
+
+pub fn is_expression(s: &str) -> bool {
+    let s = s.trim();
+    s.starts_with("${") && s.ends_with('}')
+}
+
+/// Splits `candidateUsers` or `candidateGroups` by the commas out of the brackets, so an
+/// expression with several arguments is kept. The static candidates are lowercased.
+pub fn split_candidates(s: &str) -> Vec<String> {
+    split_top_level(s)
+        .into_iter()
+        .filter(|item| !item.is_empty())
+        .map(|item| if is_expression(&item) { item } else { item.to_lowercase() })
+        .collect()
+}
+
+fn split_top_level(s: &str) -> Vec<String> {
+    let mut rst = vec![];
+    let mut depth = 0;
+    let mut item = String::new();
+    for c in s.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                rst.push(item.trim().to_owned());
+                item.clear();
+                continue;
+            },
+            _ => {},
+        }
+        item.push(c);
+    }
+    rst.push(item.trim().to_owned());
+
+    rst
+}
+
+/// Evaluates the expressions of the candidates against the process variables when the task
+/// is created, the static ones are kept as they are.
+///
+/// `${name(args)}` calls the registered candidate function, the arguments are evaluated as
+/// scripts. Other expressions are scripts, whose result is a candidate, an array of them, or
+/// a string of them separated by commas, e.g. `${initiator}` or `${approvers}`.
+pub fn resolve_candidates(candidates: &[String], variables: &HashMap<String, WrappedValue>) -> Result<Vec<String>> {
+    let mut rst: Vec<String> = vec![];
+    for candidate in candidates {
+        let resolved = if is_expression(candidate) {
+            let expr = candidate.trim();
+            eval_candidate_expression(&expr[2..expr.len() - 1], variables)?
+        } else {
+            vec![candidate.to_owned()]
+        };
+
+        for item in resolved {
+            if !rst.contains(&item) {
+                rst.push(item);
+            }
+        }
+    }
+
+    Ok(rst)
+}
+
+fn eval_candidate_expression(expr: &str, variables: &HashMap<String, WrappedValue>) -> Result<Vec<String>> {
+    let re = Regex::new(r"^\s*([A-Za-z_][A-Za-z0-9_]*)\s*\((.*)\)\s*$").unwrap();
+    if let Some(caps) = re.captures(expr) {
+        if let Some(function) = get_candidate_function(&caps[1]) {
+            let mut args = vec![];
+            for arg in split_top_level(&caps[2]).iter().filter(|arg| !arg.is_empty()) {
+                let mut variables = variables.clone();
+                let value = run_script_with_vars(arg.to_owned(), &mut variables)?;
+                args.push(convert_js_value(&value).unwrap_or_default());
+            }
+
+            return Ok(normalize(function(&args)?));
+        }
+    }
+
+    // an array is joined, so the result is always a string
+    let mut variables = variables.clone();
+    let script = format!("[].concat({}).join(',')", expr);
+    let value = run_script_with_vars(script, &mut variables)?;
+    let rst = match convert_js_value(&value) {
+        Some(WrappedValue::Str(s)) => normalize(s.split(',').map(|s| s.to_owned()).collect()),
+        _ => vec![],
+    };
+
+    Ok(rst)
+}
+
+fn normalize(candidates: Vec<String>) -> Vec<String> {
+    candidates
+        .into_iter()
+        .map(|s| s.trim().to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_candidates() {
+        let candidates = split_candidates("User_1, ${initiator}, ${manager_of(applicant, 'Dept_1')}, ${approvers}");
+        assert_eq!(candidates, vec!["user_1", "${initiator}", "${manager_of(applicant, 'Dept_1')}", "${approvers}"]);
+
+        register_candidate_function("manager_of", Arc::new(|args: &[WrappedValue]| -> Result<Vec<String>> {
+            match args {
+                [WrappedValue::Str(user), WrappedValue::Str(dept)] => Ok(vec![format!("{}_{}_manager", dept, user)]),
+                _ => Ok(vec![]),
+            }
+        }));
+
+        let mut variables = HashMap::new();
+        variables.insert("initiator".to_owned(), WrappedValue::Str("user_2".to_owned()));
+        variables.insert("applicant".to_owned(), WrappedValue::Str("user_3".to_owned()));
+        variables.insert("approvers".to_owned(), WrappedValue::Str("user_4, user_1".to_owned()));
+
+        let rst = resolve_candidates(&candidates, &variables).unwrap();
+        assert_eq!(rst, vec!["user_1", "user_2", "dept_1_user_3_manager", "user_4"]);
+
+        let rst = resolve_candidates(&split_candidates("${[initiator, 'user_5']}, ${nobody || ''}"), &variables);
+        assert!(rst.is_err());
+        let rst = resolve_candidates(&split_candidates("${[initiator, 'user_5']}, ${approvers ? '' : 'x'}"), &variables).unwrap();
+        assert_eq!(rst, vec!["user_2", "user_5"]);
+    }
+}
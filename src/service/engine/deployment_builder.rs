@@ -182,4 +182,34 @@ pub mod tests {
 
         procdef
     }
+
+    /// Removes the committed data of the deployment, for the tests which need more than one transaction.
+    pub async fn delete_test_deploy(deployment_id: &str, tran: &Transaction<'_>) {
+        let procdef_ids = "select id from apf_re_procdef where deployment_id = $1";
+        let proc_inst_ids = format!("select proc_inst_id from apf_hi_procinst where proc_def_id in ({})", procdef_ids);
+        let sqls = vec![
+            format!("delete from apf_ru_incident where proc_def_id in ({})", procdef_ids),
+            format!("delete from apf_ru_job where proc_def_id in ({})", procdef_ids),
+            format!("delete from apf_ru_event_outbox where proc_inst_id in ({})", proc_inst_ids),
+            format!("delete from apf_hi_oplog where proc_inst_id in ({})", proc_inst_ids),
+            format!("delete from apf_hi_comment where proc_inst_id in ({})", proc_inst_ids),
+            format!("delete from apf_hi_attachment where proc_inst_id in ({})", proc_inst_ids),
+            format!("delete from apf_hi_varinst where proc_inst_id in ({})", proc_inst_ids),
+            format!("delete from apf_ru_variable where proc_inst_id in ({})", proc_inst_ids),
+            format!("delete from apf_ru_identitylink where proc_def_id in ({})", procdef_ids),
+            format!("delete from apf_hi_identitylink where proc_def_id in ({})", procdef_ids),
+            format!("delete from apf_ru_task where proc_def_id in ({})", procdef_ids),
+            format!("delete from apf_hi_taskinst where proc_def_id in ({})", procdef_ids),
+            format!("delete from apf_hi_actinst where proc_def_id in ({})", procdef_ids),
+            format!("delete from apf_ru_execution where proc_def_id in ({})", procdef_ids),
+            format!("delete from apf_hi_procinst where proc_def_id in ({})", procdef_ids),
+            "delete from apf_re_procdef where deployment_id = $1".to_owned(),
+            "delete from apf_ge_bytearray where deployment_id = $1".to_owned(),
+            "delete from apf_re_deployment where id = $1".to_owned(),
+        ];
+
+        for sql in sqls {
+            tran.execute(sql.as_str(), &[&deployment_id]).await.unwrap();
+        }
+    }
 }
\ No newline at end of file
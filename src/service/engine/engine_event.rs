@@ -0,0 +1,255 @@
+use std::sync::{Arc, RwLock};
+
+use color_eyre::Result;
+use log4rs_macros::error;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+
+use crate::model::WrappedValue;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "event_type")]
+pub enum EngineEvent {
+    ProcessStarted {
+        proc_inst_id: String,
+        proc_def_id: String,
+        business_key: Option<String>,
+        start_user: Option<String>,
+    },
+    ProcessCompleted {
+        proc_inst_id: String,
+        proc_def_id: String,
+        end_element_id: String,
+    },
+    ProcessTerminated {
+        proc_inst_id: String,
+        proc_def_id: String,
+        element_id: String,
+        reason: Option<String>,
+    },
+    ProcessDeleted {
+        proc_inst_id: String,
+        proc_def_id: String,
+        reason: Option<String>,
+        user_id: Option<String>,
+    },
+    ActivityStarted {
+        proc_inst_id: String,
+        execution_id: String,
+        element_id: String,
+        element_type: String,
+    },
+    ActivityCompleted {
+        proc_inst_id: String,
+        execution_id: String,
+        element_id: String,
+        element_type: String,
+    },
+    TaskCreated {
+        proc_inst_id: String,
+        task_id: String,
+        element_id: String,
+    },
+    TaskAssigned {
+        proc_inst_id: String,
+        task_id: String,
+        assignee: Option<String>,
+    },
+    TaskCompleted {
+        proc_inst_id: String,
+        task_id: String,
+        element_id: String,
+        user_id: Option<String>,
+    },
+    TaskRejected {
+        proc_inst_id: String,
+        task_id: String,
+        element_id: String,
+        target_element_id: String,
+        user_id: Option<String>,
+        comment: Option<String>,
+    },
+    TaskWithdrawn {
+        proc_inst_id: String,
+        task_id: String,
+        element_id: String,
+        user_id: Option<String>,
+    },
+    TaskDelegated {
+        proc_inst_id: String,
+        task_id: String,
+        owner: Option<String>,
+        assignee: String,
+    },
+    TaskResolved {
+        proc_inst_id: String,
+        task_id: String,
+        owner: Option<String>,
+        user_id: Option<String>,
+    },
+    TaskReminded {
+        proc_inst_id: String,
+        task_id: String,
+        assignee: Option<String>,
+        remind_count: i32,
+    },
+    TaskEscalated {
+        proc_inst_id: String,
+        task_id: String,
+        reassign_to: Option<String>,
+        candidate_users: Vec<String>,
+        candidate_groups: Vec<String>,
+    },
+    VariableUpdated {
+        proc_inst_id: String,
+        name: String,
+        value: WrappedValue,
+    },
+}
+
+impl EngineEvent {
+    pub fn proc_inst_id(&self) -> &str {
+        match self {
+            EngineEvent::ProcessStarted { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::ProcessCompleted { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::ProcessTerminated { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::ProcessDeleted { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::ActivityStarted { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::ActivityCompleted { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskCreated { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskAssigned { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskCompleted { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskRejected { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskWithdrawn { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskDelegated { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskResolved { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskReminded { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::TaskEscalated { proc_inst_id, .. } => proc_inst_id,
+            EngineEvent::VariableUpdated { proc_inst_id, .. } => proc_inst_id,
+        }
+    }
+
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            EngineEvent::ProcessStarted { .. } => "ProcessStarted",
+            EngineEvent::ProcessCompleted { .. } => "ProcessCompleted",
+            EngineEvent::ProcessTerminated { .. } => "ProcessTerminated",
+            EngineEvent::ProcessDeleted { .. } => "ProcessDeleted",
+            EngineEvent::ActivityStarted { .. } => "ActivityStarted",
+            EngineEvent::ActivityCompleted { .. } => "ActivityCompleted",
+            EngineEvent::TaskCreated { .. } => "TaskCreated",
+            EngineEvent::TaskAssigned { .. } => "TaskAssigned",
+            EngineEvent::TaskCompleted { .. } => "TaskCompleted",
+            EngineEvent::TaskRejected { .. } => "TaskRejected",
+            EngineEvent::TaskWithdrawn { .. } => "TaskWithdrawn",
+            EngineEvent::TaskDelegated { .. } => "TaskDelegated",
+            EngineEvent::TaskResolved { .. } => "TaskResolved",
+            EngineEvent::TaskReminded { .. } => "TaskReminded",
+            EngineEvent::TaskEscalated { .. } => "TaskEscalated",
+            EngineEvent::VariableUpdated { .. } => "VariableUpdated",
+        }
+    }
+}
+
+/// `InTransaction` subscribers are called when the event is emitted, an error of them rolls back
+/// the transaction. `AfterCommit` subscribers are called after the transaction is committed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DispatchMode {
+    InTransaction,
+    AfterCommit,
+}
+
+pub type EventCallback = Arc<dyn Fn(&EngineEvent) -> Result<()> + Send + Sync>;
+
+struct EventSubscriber {
+    name: String,
+    mode: DispatchMode,
+    callback: EventCallback,
+}
+
+static EVENT_SUBSCRIBERS: OnceCell<RwLock<Vec<EventSubscriber>>> = OnceCell::new();
+
+fn event_subscribers() -> &'static RwLock<Vec<EventSubscriber>> {
+    EVENT_SUBSCRIBERS.get_or_init(|| RwLock::new(vec![]))
+}
+
+/// Adds the subscriber, the one which has the same name will be replaced.
+pub fn subscribe_event(name: &str, mode: DispatchMode, callback: EventCallback) {
+    let mut subscribers = event_subscribers().write().unwrap();
+    subscribers.retain(|s| s.name != name);
+    subscribers.push(EventSubscriber { name: name.to_owned(), mode, callback });
+}
+
+pub fn unsubscribe_event(name: &str) {
+    let mut subscribers = event_subscribers().write().unwrap();
+    subscribers.retain(|s| s.name != name);
+}
+
+fn get_callbacks(mode: DispatchMode) -> Vec<EventCallback> {
+    let subscribers = event_subscribers().read().unwrap();
+    subscribers
+        .iter()
+        .filter(|s| s.mode == mode)
+        .map(|s| s.callback.clone())
+        .collect()
+}
+
+pub fn dispatch_in_transaction(event: &EngineEvent) -> Result<()> {
+    for callback in get_callbacks(DispatchMode::InTransaction) {
+        callback(event)?;
+    }
+
+    Ok(())
+}
+
+pub fn dispatch_after_commit(events: Vec<EngineEvent>) {
+    if events.is_empty() {
+        return;
+    }
+
+    let callbacks = get_callbacks(DispatchMode::AfterCommit);
+    for event in events.iter() {
+        for callback in callbacks.iter() {
+            if let Err(e) = callback(event) {
+                error!("dispatch event ({}) of process ({}) failed: {:?}", event.event_type(), event.proc_inst_id(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use crate::error::{AppError, ErrorCode};
+    use super::*;
+
+    #[test]
+    fn test_dispatch() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let received_clone = received.clone();
+        subscribe_event("test_dispatch_after_commit", DispatchMode::AfterCommit, Arc::new(move |event: &EngineEvent| {
+            received_clone.lock().unwrap().push(event.clone());
+            Ok(())
+        }));
+        subscribe_event("test_dispatch_in_tran", DispatchMode::InTransaction, Arc::new(|event: &EngineEvent| {
+            if event.proc_inst_id() == "rejected" {
+                Err(AppError::new(ErrorCode::InvalidInput, Some("rejected"), concat!(file!(), ":", line!()), None))?;
+            }
+            Ok(())
+        }));
+
+        let event = EngineEvent::TaskCreated {
+            proc_inst_id: "rejected".to_owned(),
+            task_id: "task_1".to_owned(),
+            element_id: "approval_1".to_owned(),
+        };
+        assert!(dispatch_in_transaction(&event).is_err());
+
+        dispatch_after_commit(vec![event.clone()]);
+        assert!(received.lock().unwrap().contains(&event));
+
+        unsubscribe_event("test_dispatch_after_commit");
+        unsubscribe_event("test_dispatch_in_tran");
+        assert!(dispatch_in_transaction(&event).is_ok());
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use color_eyre::Result;
+use once_cell::sync::OnceCell;
+
+use crate::error::{AppError, ErrorCode};
+use crate::model::WrappedValue;
+use crate::service::engine::{BpmnListener, convert_js_value, run_script_with_vars};
+
+/// The data passed to a listener. Changes of `variables` are saved into the process instance,
+/// changes of the candidates are only used by the `create` event of task listener.
+#[derive(Debug, Default, Clone)]
+pub struct ListenerContext {
+    pub event: String,
+    pub proc_inst_id: String,
+    pub proc_def_id: String,
+    pub business_key: Option<String>,
+    pub element_id: String,
+    pub task_id: Option<String>,
+    pub user_id: Option<String>,
+    pub variables: HashMap<String, WrappedValue>,
+    pub candidate_users: Vec<String>,
+    pub candidate_groups: Vec<String>,
+}
+
+pub type ListenerCallback = Arc<dyn Fn(&mut ListenerContext) -> Result<()> + Send + Sync>;
+
+// The services create `ProcessEngine` on demand, so the callbacks are kept globally.
+static LISTENER_REGISTRY: OnceCell<RwLock<HashMap<String, ListenerCallback>>> = OnceCell::new();
+
+fn listener_registry() -> &'static RwLock<HashMap<String, ListenerCallback>> {
+    LISTENER_REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+pub fn register_listener(name: &str, callback: ListenerCallback) {
+    let mut registry = listener_registry().write().unwrap();
+    registry.insert(name.to_owned(), callback);
+}
+
+pub fn get_listener(name: &str) -> Option<ListenerCallback> {
+    let registry = listener_registry().read().unwrap();
+    registry.get(name).and_then(|cb| Some(cb.clone()))
+}
+
+pub fn invoke_listeners(listeners: &[BpmnListener], listener_ctx: &mut ListenerContext) -> Result<()> {
+    for listener in listeners {
+        if let Some(delegate) = &listener.delegate {
+            let callback = get_listener(delegate)
+                .ok_or(AppError::new(
+                    ErrorCode::NotFound,
+                    Some(&format!("listener ({}) of element ({}) is not registered", delegate, listener_ctx.element_id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                ))?;
+            callback(listener_ctx)?;
+        }
+
+        if let Some(script) = &listener.script {
+            let rst = run_script_with_vars(script.to_owned(), &mut listener_ctx.variables)?;
+
+            if let Some(result_variable) = &listener.result_variable {
+                if let Some(value) = convert_js_value(&rst) {
+                    listener_ctx.variables.insert(result_variable.to_owned(), value);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::engine::ListenerType;
+    use super::*;
+
+    #[test]
+    fn test_invoke_listeners() {
+        register_listener("test_invoke_listeners", Arc::new(|ctx: &mut ListenerContext| {
+            ctx.variables.insert("approver".to_owned(), WrappedValue::Str("user1".to_owned()));
+            ctx.candidate_users.push("user1".to_owned());
+            Ok(())
+        }));
+
+        let listeners = vec![
+            BpmnListener::new(ListenerType::Task, "create".to_owned(), Some("test_invoke_listeners".to_owned()), None, None),
+            BpmnListener::new(ListenerType::Task, "create".to_owned(), None, Some("approver + '_1'".to_owned()), Some("next".to_owned())),
+        ];
+        let mut ctx = ListenerContext::default();
+        invoke_listeners(&listeners, &mut ctx).unwrap();
+
+        assert_eq!(ctx.candidate_users, vec!["user1".to_owned()]);
+        assert_eq!(ctx.variables.get("next"), Some(&WrappedValue::Str("user1_1".to_owned())));
+
+        let not_registered = vec![
+            BpmnListener::new(ListenerType::Task, "create".to_owned(), Some("not_registered".to_owned()), None, None),
+        ];
+        assert!(invoke_listeners(&not_registered, &mut ctx).is_err());
+    }
+}
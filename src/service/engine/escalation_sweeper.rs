@@ -0,0 +1,363 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use color_eyre::Result;
+use log4rs_macros::{debug, error};
+use tokio_postgres::Transaction;
+
+use crate::get_now;
+use crate::common::db;
+use crate::dao::{ApfHiIdentitylinkDao, ApfHiOplogDao, ApfReProcdefDao, ApfRuIdentitylinkDao, ApfRuTaskDao};
+use crate::model::{ApfRuTask, IdentType, NewApfHiOplog, NewApfRuIdentitylink, OpType};
+use crate::service::engine::{
+    add_duration, BackgroundWorker, BpmnEscalation, BusinessCalendar, dispatch_after_commit, EngineEvent,
+    find_business_calendar, notify_task, notify_task_candidates, OperatorContext, ProcessEngine, TaskNotifyEvent,
+    write_outbox
+};
+
+#[derive(Debug, Clone)]
+pub struct EscalationSweeperConfig {
+    /// waiting time between two sweeps
+    pub interval: Duration,
+    /// the most tasks handled by one sweep
+    pub batch_size: i64,
+}
+
+impl Default for EscalationSweeperConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            batch_size: 100,
+        }
+    }
+}
+
+/// Looks for the overdue tasks in a background thread, it reminds and escalates them by the
+/// `escalation` of their user tasks. Several sweepers can work on the same database, a task
+/// is locked while it is handled.
+pub struct EscalationSweeper {
+    config: Arc<EscalationSweeperConfig>,
+    worker: BackgroundWorker,
+}
+
+impl EscalationSweeper {
+    pub fn new(config: EscalationSweeperConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            worker: BackgroundWorker::new("apf-escalation-sweeper"),
+        }
+    }
+
+    /// Must be called in a multi-thread tokio runtime, the database connections are driven by it.
+    pub fn start(&mut self) -> Result<()> {
+        let config = self.config.clone();
+        self.worker.start(1, move |running| run_sweeps(config, running))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    pub fn shutdown(&mut self) {
+        self.worker.shutdown();
+    }
+}
+
+async fn run_sweeps(config: Arc<EscalationSweeperConfig>, running: Arc<AtomicBool>) {
+    while running.load(Ordering::SeqCst) {
+        if let Err(e) = sweep_overdue_tasks(config.batch_size).await {
+            error!("sweep overdue tasks failed: {:?}", e);
+        }
+
+        tokio::time::sleep(config.interval).await;
+    }
+}
+
+/// Handles at most `batch_size` overdue tasks, each in its own transaction. Returns the number
+/// of the tasks which are reminded or escalated.
+pub async fn sweep_overdue_tasks(batch_size: i64) -> Result<usize> {
+    let mut conn = db::get_connect().await?;
+    let tran = conn.transaction().await?;
+    let task_ids = ApfRuTaskDao::new(&tran).find_overdue_ids(get_now(), batch_size).await?;
+    tran.commit().await?;
+
+    let mut count = 0;
+    for task_id in task_ids.iter() {
+        match sweep_task(task_id).await {
+            Ok(true) => count += 1,
+            Ok(false) => {},
+            Err(e) => error!("sweep overdue task({}) failed: {:?}", task_id, e),
+        }
+    }
+
+    Ok(count)
+}
+
+async fn sweep_task(task_id: &str) -> Result<bool> {
+    let mut conn = db::get_connect().await?;
+    let tran = conn.transaction().await?;
+
+    let task = match ApfRuTaskDao::new(&tran).lock_by_id(task_id).await? {
+        Some(task) => task,
+        None => {
+            debug!("task({}) is gone or handled by another sweeper", task_id);
+            tran.rollback().await?;
+            return Ok(false);
+        }
+    };
+
+    let mut operator_ctx = OperatorContext::default();
+    let handled = _sweep_task(&task, get_now(), &mut operator_ctx, &tran).await?;
+    write_outbox(&operator_ctx.events, &tran).await?;
+    tran.commit().await?;
+
+    dispatch_after_commit(operator_ctx.take_events());
+
+    Ok(handled)
+}
+
+/// Escalates the task once it has been overdue for `escalateAfter` (counted by the business
+/// calendar), otherwise reminds it at every `remindInterval`. Returns false if there is nothing
+/// to do at `now`. The time of the next sweep is saved on the task.
+pub async fn _sweep_task(task: &ApfRuTask, now: i64, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<bool> {
+    let task_dao = ApfRuTaskDao::new(tran);
+    let (due_date, escalation, calendar) = match (task.due_date, load_escalation(task, tran).await?) {
+        (Some(due_date), Some((escalation, calendar))) => (due_date, escalation, calendar),
+        _ => {
+            // nothing to do until the due date is changed
+            task_dao.update_next_sweep_time(&task.id, None).await?;
+            return Ok(false);
+        }
+    };
+
+    let mut escalate_time = task.escalate_time;
+    let mut last_remind_time = task.last_remind_time;
+    let mut handled = false;
+
+    if due_date < now {
+        if let Some(escalate_after) = &escalation.escalate_after {
+            if escalate_time.is_none() && now >= add_duration(due_date, escalate_after, calendar.as_ref()) {
+                escalate(task, &escalation, now, operator_ctx, tran).await?;
+                escalate_time = Some(now);
+                handled = true;
+            }
+        }
+    }
+
+    if due_date < now && !handled {
+        if let Some(remind_interval) = &escalation.remind_interval {
+            if last_remind_time.map_or(true, |last| now - last >= remind_interval.to_millis()) {
+                remind(task, now, operator_ctx, tran).await?;
+                last_remind_time = Some(now);
+                handled = true;
+            }
+        }
+    }
+
+    let escalate_at = match (&escalation.escalate_after, escalate_time) {
+        (Some(escalate_after), None) => Some(add_duration(due_date, escalate_after, calendar.as_ref())),
+        _ => None,
+    };
+    let remind_at = escalation.remind_interval
+        .as_ref()
+        .map(|interval| last_remind_time.map_or(due_date + 1, |last| last + interval.to_millis()));
+    task_dao.update_next_sweep_time(&task.id, escalate_at.into_iter().chain(remind_at).min()).await?;
+
+    Ok(handled)
+}
+
+/// The escalation of the task with the business calendar of its user task.
+async fn load_escalation(
+    task: &ApfRuTask,
+    tran: &Transaction<'_>
+) -> Result<Option<(BpmnEscalation, Option<Arc<dyn BusinessCalendar>>)>> {
+    let element_id = match &task.element_id {
+        Some(element_id) => element_id,
+        None => return Ok(None),
+    };
+
+    let procdef_dao = ApfReProcdefDao::new(tran);
+    let re_def = procdef_dao.get_by_id(&task.proc_def_id).await?;
+    let repository_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_repository_service();
+    let bpmn_process = repository_service.load_bpmn_by_deployment(&re_def.deployment_id, tran).await?;
+
+    match bpmn_process.get_escalation(element_id) {
+        Some(escalation) => Ok(Some((escalation, find_business_calendar(&bpmn_process, element_id)?))),
+        None => Ok(None),
+    }
+}
+
+async fn remind(task: &ApfRuTask, now: i64, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+    ApfRuTaskDao::new(tran).mark_reminded(&task.id, now).await?;
+    let remind_count = task.remind_count + 1;
+
+    operator_ctx.emit(EngineEvent::TaskReminded {
+        proc_inst_id: task.proc_inst_id.clone(),
+        task_id: task.id.clone(),
+        assignee: task.assignee.clone(),
+        remind_count,
+    })?;
+    notify_task_candidates(TaskNotifyEvent::Reminded, task, tran).await?;
+
+    let detail = serde_json::json!({
+        "remind_count": remind_count,
+        "due_date": task.due_date,
+    });
+    create_oplog(task, OpType::REMIND_TASK, detail.to_string(), now, tran).await
+}
+
+async fn escalate(
+    task: &ApfRuTask,
+    escalation: &BpmnEscalation,
+    now: i64,
+    operator_ctx: &mut OperatorContext,
+    tran: &Transaction<'_>
+) -> Result<()> {
+    if escalation.reassign_to.is_some() {
+        let task_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_task_service();
+        task_service.assign(task, escalation.reassign_to.clone(), operator_ctx, tran).await?;
+    }
+
+    // the candidates are added beside the current ones
+    let ru_ident_dao = ApfRuIdentitylinkDao::new(tran);
+    let hi_ident_dao = ApfHiIdentitylinkDao::new(tran);
+    let links = ru_ident_dao.find_by_task_id(&task.id).await?;
+    let new_links = escalation.candidate_users
+        .iter()
+        .filter(|user| !links.iter().any(|link| link.user_id.as_ref() == Some(*user)))
+        .map(|user| (IdentType::user, None, Some(user.to_owned())))
+        .chain(
+            escalation.candidate_groups
+                .iter()
+                .filter(|group| !links.iter().any(|link| link.group_id.as_ref() == Some(*group)))
+                .map(|group| (IdentType::group, Some(group.to_owned()), None))
+        );
+    for (ident_type, group_id, user_id) in new_links {
+        let new_ru_ident = NewApfRuIdentitylink {
+            ident_type,
+            group_id,
+            user_id,
+            task_id: Some(task.id.clone()),
+            proc_inst_id: Some(task.proc_inst_id.clone()),
+            proc_def_id: Some(task.proc_def_id.clone()),
+        };
+        let ru_ident = ru_ident_dao.create(&new_ru_ident).await?;
+        hi_ident_dao.create_from_ident_link(&ru_ident).await?;
+    }
+
+    ApfRuTaskDao::new(tran).mark_escalated(&task.id, now).await?;
+
+    operator_ctx.emit(EngineEvent::TaskEscalated {
+        proc_inst_id: task.proc_inst_id.clone(),
+        task_id: task.id.clone(),
+        reassign_to: escalation.reassign_to.clone(),
+        candidate_users: escalation.candidate_users.clone(),
+        candidate_groups: escalation.candidate_groups.clone(),
+    })?;
+
+    // the notification is sent to the new assignee and candidates
+    let escalated_task = ApfRuTaskDao::new(tran).get_by_id(&task.id).await?;
+    let links = ru_ident_dao.find_by_task_id(&task.id).await?;
+    let mut candidate_users: Vec<String> = links.iter().filter_map(|link| link.user_id.clone()).collect();
+    let candidate_groups = links.iter().filter_map(|link| link.group_id.clone()).collect();
+    if let Some(assignee) = &escalated_task.assignee {
+        if !candidate_users.contains(assignee) {
+            candidate_users.push(assignee.clone());
+        }
+    }
+    notify_task(TaskNotifyEvent::Escalated, &escalated_task, candidate_users, candidate_groups, tran).await?;
+
+    let detail = serde_json::json!({
+        "from_assignee": task.assignee,
+        "reassign_to": escalation.reassign_to,
+        "candidate_users": escalation.candidate_users,
+        "candidate_groups": escalation.candidate_groups,
+    });
+    create_oplog(task, OpType::ESCALATE_TASK, detail.to_string(), now, tran).await
+}
+
+async fn create_oplog(task: &ApfRuTask, op_type: &str, detail: String, now: i64, tran: &Transaction<'_>) -> Result<()> {
+    let oplog_dao = ApfHiOplogDao::new(tran);
+    oplog_dao.create(&NewApfHiOplog {
+        op_type: op_type.to_owned(),
+        proc_def_id: Some(task.proc_def_id.clone()),
+        proc_inst_id: Some(task.proc_inst_id.clone()),
+        task_id: Some(task.id.clone()),
+        element_id: task.element_id.clone(),
+        user_id: None,
+        detail: Some(detail),
+        create_time: now,
+    }).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::engine::query::TaskQuery;
+    use crate::service::engine::tests::create_test_deploy;
+    use super::*;
+
+    const HOUR: i64 = 3600 * 1000;
+
+    #[tokio::test]
+    async fn test_sweep_task() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_escalation.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let due_date = task.due_date.unwrap();
+        assert_eq!(task.next_sweep_time, Some(due_date));
+
+        let mut operator_ctx = OperatorContext::default();
+        assert!(!_sweep_task(&task, due_date - 1, &mut operator_ctx, &tran).await.unwrap());
+
+        // reminded as soon as it's overdue, then at the interval
+        assert!(_sweep_task(&task, due_date + 1, &mut operator_ctx, &tran).await.unwrap());
+        let task = TaskQuery::new(&tran).id(&task.id).fetch_one().await.unwrap();
+        assert_eq!(task.remind_count, 1);
+        assert_eq!(task.next_sweep_time, Some(due_date + 1 + 4 * HOUR));
+        let task_dao = ApfRuTaskDao::new(&tran);
+        assert!(!task_dao.find_overdue_ids(due_date + 2 * HOUR, 10000).await.unwrap().contains(&task.id));
+        assert!(task_dao.find_overdue_ids(due_date + 5 * HOUR, 10000).await.unwrap().contains(&task.id));
+        assert!(!_sweep_task(&task, due_date + 2 * HOUR, &mut operator_ctx, &tran).await.unwrap());
+        assert!(_sweep_task(&task, due_date + 5 * HOUR, &mut operator_ctx, &tran).await.unwrap());
+
+        // escalated once to the manager group
+        let task = TaskQuery::new(&tran).id(&task.id).fetch_one().await.unwrap();
+        assert_eq!(task.remind_count, 2);
+        assert!(_sweep_task(&task, due_date + 49 * HOUR, &mut operator_ctx, &tran).await.unwrap());
+        let task = TaskQuery::new(&tran).id(&task.id).fetch_one().await.unwrap();
+        assert!(task.escalate_time.is_some());
+        // the reminder missed by the escalation is sent by the next sweep
+        assert_eq!(task.next_sweep_time, Some(due_date + 9 * HOUR));
+        let links = ApfRuIdentitylinkDao::new(&tran).find_by_task_id(&task.id).await.unwrap();
+        assert!(links.iter().any(|link| link.group_id == Some("manager".to_owned())));
+
+        let tasks = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_group(Some("manager".to_owned()))
+            .fetch_all()
+            .await
+            .unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        let oplogs = ApfHiOplogDao::new(&tran).find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(oplogs.iter().filter(|log| log.op_type == OpType::REMIND_TASK).count(), 2);
+        assert_eq!(oplogs.iter().filter(|log| log.op_type == OpType::ESCALATE_TASK).count(), 1);
+        assert!(operator_ctx.events.iter().any(|e| e.event_type() == "TaskEscalated"));
+
+        tran.rollback().await.unwrap();
+    }
+}
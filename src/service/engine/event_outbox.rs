@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use color_eyre::Result;
+use futures::future::BoxFuture;
+use log4rs_macros::error;
+use tokio::task::JoinHandle;
+use tokio_postgres::Transaction;
+
+use crate::common::db;
+use crate::dao::ApfRuEventOutboxDao;
+use crate::get_now;
+use crate::model::{ApfRuEventOutbox, NewApfRuEventOutbox, OutboxStatus};
+use crate::service::engine::EngineEvent;
+
+/// Writes the events into the outbox table, it must be called in the transaction which
+/// produced the events.
+pub async fn write_outbox(events: &[EngineEvent], tran: &Transaction<'_>) -> Result<()> {
+    let outbox_dao = ApfRuEventOutboxDao::new(tran);
+    let create_time = get_now();
+
+    for event in events {
+        let obj = NewApfRuEventOutbox {
+            proc_inst_id: event.proc_inst_id().to_owned(),
+            event_type: event.event_type().to_owned(),
+            payload: serde_json::to_string(event)?,
+            create_time,
+        };
+        outbox_dao.create(&obj).await?;
+    }
+
+    Ok(())
+}
+
+/// The downstream system which receives the events of outbox, e.g. a message queue.
+pub trait OutboxSink: Send + Sync {
+    fn deliver<'a>(&'a self, event: &'a ApfRuEventOutbox) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Delivers the outbox events to the sink at least once. The events of a process instance are
+/// delivered in the order they were written: when one fails, the later ones wait for its retry.
+/// After `max_retries` failures the event is marked `FAILED`, and the process instance stays blocked
+/// until it's retried or skipped by `ManagementService`. The other process instances go on.
+pub struct OutboxDispatcher {
+    sink: Arc<dyn OutboxSink>,
+    batch_size: i64,
+    max_retries: i32,
+    retry_backoff: i64,
+}
+
+impl OutboxDispatcher {
+    pub fn new(sink: Arc<dyn OutboxSink>) -> Self {
+        Self {
+            sink,
+            batch_size: 100,
+            max_retries: 10,
+            retry_backoff: 5000,
+        }
+    }
+
+    pub fn batch_size(mut self, batch_size: i64) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn max_retries(mut self, max_retries: i32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The delay (millisecond) before the next retry, it is doubled by each failure.
+    pub fn retry_backoff(mut self, retry_backoff: i64) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Delivers one batch of pending events, returns the count of delivered events.
+    pub async fn dispatch_once(&self) -> Result<usize> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let count = self._dispatch_once(&tran).await?;
+        tran.commit().await?;
+
+        Ok(count)
+    }
+
+    pub(crate) async fn _dispatch_once(&self, tran: &Transaction<'_>) -> Result<usize> {
+        let outbox_dao = ApfRuEventOutboxDao::new(tran);
+
+        // another dispatcher is working
+        if !outbox_dao.try_lock_dispatch().await? {
+            return Ok(0);
+        }
+
+        let events = outbox_dao.find_pending(get_now(), self.batch_size).await?;
+        let mut blocked_proc_insts = HashSet::new();
+        let mut count = 0;
+
+        for event in events.iter() {
+            if blocked_proc_insts.contains(&event.proc_inst_id) {
+                continue;
+            }
+
+            match self.sink.deliver(event).await {
+                Ok(_) => {
+                    outbox_dao.mark_delivered(&event.id, get_now()).await?;
+                    count += 1;
+                },
+                Err(e) => {
+                    error!("deliver outbox event ({}) failed: {:?}", event.id, e);
+
+                    let retries = event.retries + 1;
+                    let status = if retries >= self.max_retries { 
+                        OutboxStatus::FAILED 
+                    } else { 
+                        OutboxStatus::PENDING 
+                    };
+                    blocked_proc_insts.insert(event.proc_inst_id.clone());
+                    let next_retry_time = get_now() + self.retry_backoff * (1_i64 << retries.min(16));
+                    outbox_dao.mark_error(&event.id, status, next_retry_time, &format!("{}", e)).await?;
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Runs the dispatcher in the background until the handle is aborted.
+    pub fn start(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.dispatch_once().await {
+                    error!("dispatch outbox events failed: {:?}", e);
+                }
+
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use crate::dao::apf_ru_event_outbox_dao::tests::create_test_event_outbox;
+    use crate::error::{AppError, ErrorCode};
+    use crate::service::engine::ManagementService;
+    use super::*;
+
+    struct TestSink {
+        failing: Mutex<Vec<String>>,
+        delivered: Mutex<Vec<String>>,
+    }
+
+    impl TestSink {
+        fn new(failing: Vec<String>) -> Self {
+            Self {
+                failing: Mutex::new(failing),
+                delivered: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    impl OutboxSink for TestSink {
+        fn deliver<'a>(&'a self, event: &'a ApfRuEventOutbox) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                if self.failing.lock().unwrap().contains(&event.id) {
+                    Err(AppError::new(ErrorCode::InternalError, Some("sink error"), concat!(file!(), ":", line!()), None))?
+                }
+                self.delivered.lock().unwrap().push(event.id.clone());
+
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_once() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let event_1 = create_test_event_outbox("test_dispatch_ok", &tran).await;
+        let event_2 = create_test_event_outbox("test_dispatch_ok", &tran).await;
+        let event_3 = create_test_event_outbox("test_dispatch_fail", &tran).await;
+        let event_4 = create_test_event_outbox("test_dispatch_fail", &tran).await;
+
+        let sink = Arc::new(TestSink::new(vec![event_3.id.clone()]));
+        let dispatcher = OutboxDispatcher::new(sink.clone()).batch_size(10000);
+        dispatcher._dispatch_once(&tran).await.unwrap();
+
+        let delivered = sink.delivered.lock().unwrap().clone();
+        let pos_1 = delivered.iter().position(|id| *id == event_1.id).unwrap();
+        let pos_2 = delivered.iter().position(|id| *id == event_2.id).unwrap();
+        assert!(pos_1 < pos_2);
+        assert!(!delivered.contains(&event_3.id));
+
+        // the later event waits for the failed one of the same process instance
+        let outbox_dao = ApfRuEventOutboxDao::new(&tran);
+        let events = outbox_dao.find_by_proc_inst("test_dispatch_fail").await.unwrap();
+        assert_eq!(events[0].id, event_3.id);
+        assert_eq!(events[0].retries, 1);
+        assert_eq!(events[1].id, event_4.id);
+        assert_eq!(events[1].retries, 0);
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_blocked_proc_inst() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        // deliver the events left by the others first
+        let sink = Arc::new(TestSink::new(vec![]));
+        OutboxDispatcher::new(sink.clone()).batch_size(10000)._dispatch_once(&tran).await.unwrap();
+
+        let fail_1 = create_test_event_outbox("test_dispatch_fail", &tran).await;
+        let fail_2 = create_test_event_outbox("test_dispatch_fail", &tran).await;
+        let fail_3 = create_test_event_outbox("test_dispatch_fail", &tran).await;
+        let ok_1 = create_test_event_outbox("test_dispatch_ok", &tran).await;
+        let ok_2 = create_test_event_outbox("test_dispatch_ok", &tran).await;
+
+        let sink = Arc::new(TestSink::new(vec![fail_1.id.clone(), ok_2.id.clone()]));
+        let dispatcher = OutboxDispatcher::new(sink.clone()).batch_size(2).max_retries(1);
+        dispatcher._dispatch_once(&tran).await.unwrap();
+
+        // the events of the failed process instance don't take up the batch
+        dispatcher._dispatch_once(&tran).await.unwrap();
+        dispatcher._dispatch_once(&tran).await.unwrap();
+        let delivered = sink.delivered.lock().unwrap().clone();
+        assert_eq!(delivered, vec![ok_1.id.clone()]);
+
+        // the failed event keeps the later ones of its process instance until it's retried
+        let outbox_dao = ApfRuEventOutboxDao::new(&tran);
+        let events = outbox_dao.find_by_proc_inst("test_dispatch_fail").await.unwrap();
+        assert_eq!(events[0].status, OutboxStatus::FAILED);
+        assert_eq!(events[1].status, OutboxStatus::PENDING);
+
+        sink.failing.lock().unwrap().retain(|id| *id != fail_1.id);
+        let mgmt_service = ManagementService::new();
+        assert_eq!(mgmt_service._retry_outbox_events("test_dispatch_fail", &tran).await.unwrap(), 1);
+        dispatcher._dispatch_once(&tran).await.unwrap();
+        dispatcher._dispatch_once(&tran).await.unwrap();
+        let delivered = sink.delivered.lock().unwrap().clone();
+        assert_eq!(delivered[1..], [fail_1.id.clone(), fail_2.id.clone(), fail_3.id.clone()]);
+
+        // or the failed one is skipped
+        let events = outbox_dao.find_by_proc_inst("test_dispatch_ok").await.unwrap();
+        assert_eq!(events[1].id, ok_2.id);
+        assert_eq!(events[1].status, OutboxStatus::FAILED);
+        let ok_3 = create_test_event_outbox("test_dispatch_ok", &tran).await;
+        dispatcher._dispatch_once(&tran).await.unwrap();
+        assert!(!sink.delivered.lock().unwrap().contains(&ok_3.id));
+
+        mgmt_service._skip_outbox_event(&ok_2.id, &tran).await.unwrap();
+        dispatcher._dispatch_once(&tran).await.unwrap();
+        assert!(sink.delivered.lock().unwrap().contains(&ok_3.id));
+        assert!(!sink.delivered.lock().unwrap().contains(&ok_2.id));
+
+        tran.rollback().await.unwrap();
+    }
+}
@@ -0,0 +1,578 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use color_eyre::{Report, Result};
+use log4rs_macros::{debug, error};
+use tokio_postgres::Transaction;
+
+use crate::{gen_id, get_now};
+use crate::common::{db, global_cfg};
+use crate::dao::{ApfReProcdefDao, ApfRuExecutionDao, ApfRuIncidentDao, ApfRuJobDao, ApfRuTaskDao, ApfRuVariableDao};
+use crate::error::{AppError, ErrorCode};
+use crate::model::{ApfRuJob, ApfRuVariable, IncidentType, JobType, NewApfRuIncident};
+use crate::service::engine::{
+    BackgroundWorker, BaseOperator, BpmnElement, check_not_suspended, CompleteTaskCmd, ContinueProcessOperator, 
+    dispatch_after_commit, Operator, OperatorContext, OperatorExecutor, ProcessEngine, write_outbox
+};
+
+const DEFAULT_JOB_RETRY_BACKOFF: i64 = 10000;
+const MAX_ERROR_MSG_LEN: usize = 4000;
+
+/// The retries of a new job, `engine.job_retries` in config.
+pub fn job_retries() -> i32 {
+    global_cfg().engine
+        .as_ref()
+        .and_then(|engine| engine.job_retries)
+        .unwrap_or(JobType::DEFAULT_RETRIES)
+}
+
+/// The delay (millisecond) before retrying a failed job, it is doubled by each failure.
+pub fn job_retry_backoff() -> i64 {
+    global_cfg().engine
+        .as_ref()
+        .and_then(|engine| engine.job_retry_backoff)
+        .unwrap_or(DEFAULT_JOB_RETRY_BACKOFF)
+}
+
+#[derive(Debug, Clone)]
+pub struct JobExecutorConfig {
+    pub thread_count: usize,
+    pub lock_owner: String,
+    /// milliseconds that an acquired job is locked by the owner
+    pub lock_time: i64,
+    pub max_jobs_per_acquisition: i64,
+    /// waiting time when there is no due job
+    pub idle_wait: Duration,
+}
+
+impl Default for JobExecutorConfig {
+    fn default() -> Self {
+        Self {
+            thread_count: 2,
+            lock_owner: gen_id(),
+            lock_time: 5 * 60 * 1000,
+            max_jobs_per_acquisition: 3,
+            idle_wait: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Executes the async continuations in background threads.
+/// 
+/// Several executors (in one process or on different nodes) can work on the same database,
+/// each of them must have a distinct `lock_owner`.
+pub struct JobExecutor {
+    config: Arc<JobExecutorConfig>,
+    worker: BackgroundWorker,
+}
+
+impl JobExecutor {
+    pub fn new(config: JobExecutorConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            worker: BackgroundWorker::new("apf-job-executor"),
+        }
+    }
+
+    /// Must be called in a multi-thread tokio runtime, the database connections are driven by it.
+    pub fn start(&mut self) -> Result<()> {
+        let config = self.config.clone();
+        self.worker.start(self.config.thread_count, move |running| run_jobs(config, running))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.worker.is_running()
+    }
+
+    /// Stops acquiring new jobs and waits for the running ones.
+    pub fn shutdown(&mut self) {
+        self.worker.shutdown();
+    }
+}
+
+async fn run_jobs(config: Arc<JobExecutorConfig>, running: Arc<AtomicBool>) {
+    while running.load(Ordering::SeqCst) {
+        let jobs = match acquire_jobs(&config).await {
+            Ok(jobs) => jobs,
+            Err(e) => {
+                error!("acquire jobs failed: {:?}", e);
+                vec![]
+            }
+        };
+
+        if jobs.is_empty() {
+            tokio::time::sleep(config.idle_wait).await;
+            continue;
+        }
+
+        // the jobs are executed one by one, keep the lock of the waiting ones
+        let ids = jobs.iter().map(|job| job.id.clone()).collect::<Vec<String>>();
+        let renewal = tokio::spawn(renew_locks(ids, config.lock_owner.clone(), config.lock_time));
+
+        for job in jobs {
+            if let Err(e) = execute_job(&job, &config.lock_owner).await {
+                error!("execute job({}) failed: {:?}", job.id, e);
+            }
+        }
+
+        renewal.abort();
+    }
+}
+
+async fn renew_locks(ids: Vec<String>, lock_owner: String, lock_time: i64) {
+    let interval = Duration::from_millis((lock_time / 3).max(1) as u64);
+
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let rst: Result<()> = async {
+            let mut conn = db::get_connect().await?;
+            let tran = conn.transaction().await?;
+            let job_dao = ApfRuJobDao::new(&tran);
+            job_dao.renew_locks(&ids, &lock_owner, get_now() + lock_time).await?;
+            tran.commit().await?;
+
+            Ok(())
+        }.await;
+
+        if let Err(e) = rst {
+            error!("renew locks of jobs failed: {:?}", e);
+        }
+    }
+}
+
+async fn acquire_jobs(config: &JobExecutorConfig) -> Result<Vec<ApfRuJob>> {
+    let mut conn = db::get_connect().await?;
+    let tran = conn.transaction().await?;
+
+    let now = get_now();
+    let job_dao = ApfRuJobDao::new(&tran);
+    let jobs = job_dao.acquire(
+        &config.lock_owner, 
+        now + config.lock_time, 
+        now, 
+        config.max_jobs_per_acquisition
+    ).await?;
+    tran.commit().await?;
+
+    Ok(jobs)
+}
+
+/// Executes the job in a new transaction, the job is deleted when it succeeds. Otherwise
+/// the retries of the job is decreased and it is retried after the backoff, an incident
+/// is created when there is no retry left.
+/// 
+/// The job row is locked during the execution, so it is executed only once even if its lock
+/// is expired and acquired by another node in the meantime.
+pub async fn execute_job(job: &ApfRuJob, lock_owner: &str) -> Result<()> {
+    let mut conn = db::get_connect().await?;
+    let tran = conn.transaction().await?;
+
+    let job_dao = ApfRuJobDao::new(&tran);
+    let job = match job_dao.lock_for_execution(&job.id, lock_owner).await? {
+        Some(job) => job,
+        None => {
+            debug!("job({}) is not owned by {} any more", job.id, lock_owner);
+            tran.rollback().await?;
+            return Ok(());
+        }
+    };
+    let job = &job;
+
+    // the job waits until the process instance is activated
+    let execution_dao = ApfRuExecutionDao::new(&tran);
+    let proc_inst = execution_dao.get_by_id(&job.proc_inst_id).await?;
+    if let Err(e) = check_not_suspended(&proc_inst) {
+        job_dao.unlock(&job.id).await?;
+        tran.commit().await?;
+        return Err(e);
+    }
+
+    let mut operator_ctx = OperatorContext::default();
+    let rst = _execute_job(job, &mut operator_ctx, &tran).await;
+    let rst = match rst {
+        Ok(_) => write_outbox(&operator_ctx.events, &tran).await,
+        Err(e) => Err(e),
+    };
+
+    match rst {
+        Ok(_) => {
+            tran.commit().await?;
+            dispatch_after_commit(operator_ctx.take_events());
+        },
+        Err(e) => {
+            tran.rollback().await?;
+
+            let tran = conn.transaction().await?;
+            handle_job_failure(job, lock_owner, &e, &tran).await?;
+            tran.commit().await?;
+
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Records the failure of the job, it's dropped when another owner has taken the job over
+/// after the lock expired, since the job is that owner's now.
+pub(crate) async fn handle_job_failure(job: &ApfRuJob, lock_owner: &str, err: &Report, tran: &Transaction<'_>) -> Result<()> {
+    let now = get_now();
+    let retries = (job.retries - 1).max(0);
+    let failures = (job_retries() - retries).max(1);
+    let due_time = now + job_retry_backoff() * (1_i64 << (failures - 1).min(16));
+    let error_msg = truncate_msg(&err.to_string());
+
+    let job_dao = ApfRuJobDao::new(tran);
+    if job_dao.mark_failure(&job.id, lock_owner, retries, due_time, &error_msg).await? == 0 {
+        debug!("job({}) is not owned by {} any more, its failure is dropped", job.id, lock_owner);
+        return Ok(());
+    }
+
+    if retries == 0 {
+        let error_location = err.downcast_ref::<AppError>()
+            .and_then(|e| Some(e.location.clone()));
+        let incident = NewApfRuIncident {
+            incident_type: IncidentType::FAILED_JOB.to_owned(),
+            proc_def_id: job.proc_def_id.clone(),
+            proc_inst_id: job.proc_inst_id.clone(),
+            execution_id: job.execution_id.clone(),
+            element_id: job.element_id.clone(),
+            job_id: Some(job.id.clone()),
+            error_msg: Some(error_msg),
+            error_location,
+            error_stack: Some(format!("{:?}", err)),
+            create_time: now,
+        };
+
+        let incident_dao = ApfRuIncidentDao::new(tran);
+        incident_dao.create(&incident).await?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn truncate_msg(msg: &str) -> String {
+    msg.chars().take(MAX_ERROR_MSG_LEN).collect()
+}
+
+pub async fn _execute_job(job: &ApfRuJob, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+    debug!("execute job(id: {}, type: {}, element: {})", job.id, job.job_type, job.element_id);
+
+    let procdef_dao = ApfReProcdefDao::new(tran);
+    let re_def = procdef_dao.get_by_id(&job.proc_def_id).await?;
+    let repository_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_repository_service();
+    let bpmn_process = Arc::new(repository_service.load_bpmn_by_deployment(&re_def.deployment_id, tran).await?);
+    let element = bpmn_process.element_map
+        .get(&job.element_id)
+        .ok_or(
+            AppError::new(
+                ErrorCode::NotFound,
+                Some(&format!("element({}) of job({}) is not exist", job.element_id, job.id)),
+                concat!(file!(), ":", line!()),
+                None
+            )
+        )?
+        .clone();
+    operator_ctx.bpmn_process = Some(bpmn_process.clone());
+    operator_ctx.job_id = Some(job.id.clone());
+
+    let var_dao = ApfRuVariableDao::new(tran);
+    let var_insts = var_dao.find_all_by_proc_inst(&job.proc_inst_id).await?;
+    operator_ctx.variables = ApfRuVariable::convert_variables_to_map(&var_insts);
+
+    let execution_dao = ApfRuExecutionDao::new(tran);
+    let proc_inst = Rc::new(execution_dao.get_by_id(&job.proc_inst_id).await?);
+    let current_exec = Some(Rc::new(RefCell::new(execution_dao.get_by_id(&job.execution_id).await?)));
+
+    let handler_cfg = job.handler_cfg.clone().unwrap_or_default();
+    match (job.job_type.as_str(), handler_cfg.as_str()) {
+        (JobType::ASYNC_CONTINUATION, JobType::ASYNC_BEFORE) => {
+            let continue_operator = ContinueProcessOperator::new(element, None, proc_inst, current_exec, None);
+            operator_ctx.queue.push(Operator::ContinueProcessOperator(continue_operator));
+        },
+        (JobType::ASYNC_CONTINUATION, JobType::RETRY) => {
+            let task_dao = ApfRuTaskDao::new(tran);
+            let task = task_dao.find_by_execution(&job.execution_id)
+                .await?
+                .into_iter()
+                .find(|t| t.element_id.as_ref() == Some(&job.element_id))
+                .ok_or(
+                    AppError::new(
+                        ErrorCode::NotFound,
+                        Some(&format!("service task({}) of job({}) is not exist", job.element_id, job.id)),
+                        concat!(file!(), ":", line!()),
+                        None
+                    )
+                )?;
+            let complete_cmd = CompleteTaskCmd::new(element, proc_inst, current_exec, Some(Rc::new(task)));
+            operator_ctx.queue.push(Operator::CompleteTaskCmd(complete_cmd));
+        },
+        (JobType::ASYNC_CONTINUATION, JobType::ASYNC_AFTER) => {
+            if operator_ctx.is_terminated()? {
+                let end_event_terminate = bpmn_process.end_event_terminate_node_ex()?;
+                let continue_operator = ContinueProcessOperator::new(
+                    end_event_terminate, 
+                    Some(element), 
+                    proc_inst, 
+                    current_exec, 
+                    None
+                );
+                operator_ctx.queue.push(Operator::ContinueProcessOperator(continue_operator));
+            } else {
+                let base = BaseOperator::new(proc_inst, current_exec, element, None, None);
+                base.continue_outflow(operator_ctx, tran).await?;
+            }
+        },
+        _ => {
+            Err(
+                AppError::new(
+                    ErrorCode::NotSupportError,
+                    Some(&format!("job({}) type({}) with cfg({}) is not supported", job.id, job.job_type, handler_cfg)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?
+        }
+    }
+
+    let mut operator_exec = OperatorExecutor::new();
+    operator_exec.run(operator_ctx, tran).await?;
+
+    let job_dao = ApfRuJobDao::new(tran);
+    job_dao.delete(&job.id).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicI32;
+    use crate::service::engine::{ListenerContext, register_listener};
+    use crate::service::engine::query::TaskQuery;
+    use crate::service::engine::tests::{create_test_deploy, delete_test_deploy};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_execute_async_jobs() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_async.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(
+                &procdef.key,
+                &procdef.company_id,
+                None,
+                &mut operator_ctx,
+                &tran
+            )
+            .await
+            .unwrap();
+
+        // asyncBefore: the service task is not started until the job is executed
+        let job_dao = ApfRuJobDao::new(&tran);
+        let jobs = job_dao.find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].handler_cfg, Some(JobType::ASYNC_BEFORE.to_owned()));
+
+        let mut operator_ctx = OperatorContext::default();
+        _execute_job(&jobs[0], &mut operator_ctx, &tran).await.unwrap();
+
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_1".to_owned()))
+            .fetch_one()
+            .await
+            .unwrap();
+
+        // asyncAfter: the process stays at the user task until the job is executed
+        let task_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_task_service();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), Default::default());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        let jobs = job_dao.find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].handler_cfg, Some(JobType::ASYNC_AFTER.to_owned()));
+
+        let mut operator_ctx = OperatorContext::default();
+        _execute_job(&jobs[0], &mut operator_ctx, &tran).await.unwrap();
+
+        let jobs = job_dao.find_by_proc_inst(&procinst.id).await.unwrap();
+        assert!(jobs.is_empty());
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_service_task_failure() {
+        register_listener("test_failing_delegate", Arc::new(|_ctx: &mut ListenerContext| -> Result<()> {
+            Err(Report::new(AppError::new(ErrorCode::InternalError, Some("service is down"), "test.rs:1", None)))
+        }));
+
+        let mut conn = db::get_connect().await.unwrap();
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+
+        // the synchronous service task fails with the caller
+        let tran = conn.transaction().await.unwrap();
+        let procdef = create_test_deploy("bpmn/process_service_failure.bpmn.xml", &tran).await;
+        let mut operator_ctx = OperatorContext::default();
+        let rst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await;
+        let err = rst.unwrap_err();
+        assert_eq!(err.downcast_ref::<AppError>().unwrap().location, "test.rs:1");
+        tran.rollback().await.unwrap();
+
+        // unless it declares retries, then it's retried by a job
+        static FLAKY_FAILURES: AtomicI32 = AtomicI32::new(2);
+        register_listener("test_flaky_delegate", Arc::new(|_ctx: &mut ListenerContext| -> Result<()> {
+            if FLAKY_FAILURES.fetch_sub(1, Ordering::SeqCst) > 0 {
+                Err(Report::new(AppError::new(ErrorCode::InternalError, Some("service is down"), "test.rs:2", None)))?
+            }
+            Ok(())
+        }));
+
+        let tran = conn.transaction().await.unwrap();
+        let procdef = create_test_deploy("bpmn/process_service_retry.bpmn.xml", &tran).await;
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let job_dao = ApfRuJobDao::new(&tran);
+        let job = job_dao.find_by_proc_inst(&procinst.id).await.unwrap().remove(0);
+        assert_eq!(job.handler_cfg, Some(JobType::RETRY.to_owned()));
+        assert_eq!(job.retries, 2);
+        assert!(job.exception_msg.unwrap().contains("service is down"));
+
+        let mut operator_ctx = OperatorContext::default();
+        let err = _execute_job(&job, &mut operator_ctx, &tran).await.unwrap_err();
+        let now = get_now();
+        job_dao.lock(&job.id, "test_owner", now + 60000, now).await.unwrap().unwrap();
+        handle_job_failure(&job, "test_owner", &err, &tran).await.unwrap();
+
+        let job = job_dao.get_by_id(&job.id).await.unwrap();
+        assert_eq!(job.retries, 1);
+        let mut operator_ctx = OperatorContext::default();
+        _execute_job(&job, &mut operator_ctx, &tran).await.unwrap();
+
+        assert!(job_dao.find_by_proc_inst(&procinst.id).await.unwrap().is_empty());
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        assert_eq!(task.element_id, Some("approval_1".to_owned()));
+        assert!(ApfRuIncidentDao::new(&tran).find_by_proc_inst(&procinst.id).await.unwrap().is_empty());
+        tran.rollback().await.unwrap();
+
+        // the asynchronous service task is retried, and turns into an incident at last
+        let tran = conn.transaction().await.unwrap();
+        let procdef = create_test_deploy("bpmn/process_service_failure_async.bpmn.xml", &tran).await;
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let job_dao = ApfRuJobDao::new(&tran);
+        let incident_dao = ApfRuIncidentDao::new(&tran);
+        for _ in 0..job_retries() {
+            let job = job_dao.find_by_proc_inst(&procinst.id).await.unwrap().remove(0);
+            assert!(incident_dao.find_by_proc_inst(&procinst.id).await.unwrap().is_empty());
+
+            let mut operator_ctx = OperatorContext::default();
+            let err = _execute_job(&job, &mut operator_ctx, &tran).await.unwrap_err();
+            let now = get_now();
+            job_dao.lock(&job.id, "test_owner", now + 60000, now).await.unwrap().unwrap();
+            handle_job_failure(&job, "test_owner", &err, &tran).await.unwrap();
+        }
+
+        let incidents = incident_dao.find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].element_id, "notify_1".to_owned());
+        assert_eq!(incidents[0].error_location, Some("test.rs:1".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_multiple_executors() {
+        log4rs_macros::prepare_log();
+
+        // the executors only see the committed jobs
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+        let procdef = create_test_deploy("bpmn/process_async.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut proc_inst_ids = vec![];
+        for _ in 0..6 {
+            let mut operator_ctx = OperatorContext::default();
+            let procinst = rt_service
+                ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+                .await
+                .unwrap();
+            proc_inst_ids.push(procinst.id.clone());
+        }
+        tran.commit().await.unwrap();
+
+        let mut executors = vec![];
+        for lock_owner in ["test_node_a", "test_node_b"] {
+            let config = JobExecutorConfig {
+                thread_count: 2,
+                lock_owner: lock_owner.to_owned(),
+                max_jobs_per_acquisition: 2,
+                idle_wait: Duration::from_millis(100),
+                ..Default::default()
+            };
+            let mut executor = JobExecutor::new(config);
+            executor.start().unwrap();
+            executors.push(executor);
+        }
+
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+
+            let tran = conn.transaction().await.unwrap();
+            let job_dao = ApfRuJobDao::new(&tran);
+            let mut remaining = 0;
+            for proc_inst_id in proc_inst_ids.iter() {
+                remaining += job_dao.find_by_proc_inst(proc_inst_id).await.unwrap().len();
+            }
+            tran.rollback().await.unwrap();
+
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        for executor in executors.iter_mut() {
+            tokio::task::block_in_place(|| executor.shutdown());
+        }
+
+        // every job is executed exactly once, the committed data is removed before checking it
+        let tran = conn.transaction().await.unwrap();
+        let job_dao = ApfRuJobDao::new(&tran);
+        let mut remaining_jobs = 0;
+        let mut task_elements = vec![];
+        for proc_inst_id in proc_inst_ids.iter() {
+            remaining_jobs += job_dao.find_by_proc_inst(proc_inst_id).await.unwrap().len();
+
+            let tasks = TaskQuery::new(&tran).proc_inst_id(proc_inst_id).fetch_all().await.unwrap();
+            task_elements.push(tasks.into_iter().map(|t| t.element_id).collect::<Vec<Option<String>>>());
+        }
+
+        delete_test_deploy(&procdef.deployment_id, &tran).await;
+        tran.commit().await.unwrap();
+
+        assert_eq!(remaining_jobs, 0);
+        for elements in task_elements {
+            assert_eq!(elements, vec![Some("approval_1".to_owned())]);
+        }
+    }
+}
@@ -33,6 +33,70 @@ pub fn run_script(js_code: String, global_vars: &HashMap<String, JsValue>) -> Re
     rst
 }
 
+/// Runs the script with the variables as globals, then writes the globals that hold
+/// a supported value back into `variables`, so the script is able to modify them.
+pub fn run_script_with_vars(js_code: String, variables: &mut HashMap<String, WrappedValue>) -> Result<JsValue> {
+    let context = &mut Context::default();
+    let g_obj = context.global_object();
+    let global_vars = convert_map(variables);
+
+    for (k, v) in global_vars.iter() {
+        g_obj.set(
+            k.to_string(), 
+            v, 
+            true, 
+            context
+        )
+        .map_err(|e| {
+            let s = format!("Uncaught {}", e.display());
+            AppError::new(ErrorCode::InternalError, Some(&s), concat!(file!(), ":", line!()), None)
+        })?;
+    }
+
+    let rst = context.eval(js_code)
+        .map_err(|e| {
+            let s = format!("Uncaught {}", e.display());
+            AppError::new(ErrorCode::InternalError, Some(&s), concat!(file!(), ":", line!()), None)
+        })?;
+
+    for (k, v) in variables.iter_mut() {
+        let js_value = g_obj.get(k.to_string(), context)
+            .map_err(|e| {
+                let s = format!("Uncaught {}", e.display());
+                AppError::new(ErrorCode::InternalError, Some(&s), concat!(file!(), ":", line!()), None)
+            })?;
+
+        if let Some(value) = convert_js_value(&js_value) {
+            *v = value;
+        }
+    }
+
+    Ok(rst)
+}
+
+/// Evaluates the attribute of an element, `${...}` is run as a script over the variables and
+/// other text is taken as it is. The variables are not changed by the script.
+pub fn eval_expression(expr: &str, variables: &HashMap<String, WrappedValue>) -> Result<Option<WrappedValue>> {
+    let expr = expr.trim();
+    if expr.starts_with("${") && expr.ends_with('}') {
+        let mut variables = variables.clone();
+        let rst = run_script_with_vars(expr[2..expr.len() - 1].to_owned(), &mut variables)?;
+        Ok(convert_js_value(&rst))
+    } else {
+        Ok(Some(WrappedValue::Str(expr.to_owned())))
+    }
+}
+
+pub fn convert_js_value(js_value: &JsValue) -> Option<WrappedValue> {
+    match js_value {
+        JsValue::String(v) => Some(WrappedValue::Str(v.to_string())),
+        JsValue::Integer(v) => Some(WrappedValue::Int(*v)),
+        JsValue::Rational(v) => Some(WrappedValue::Double(*v)),
+        JsValue::Boolean(v) => Some(WrappedValue::Bool(*v)),
+        _ => None,
+    }
+}
+
 pub fn convert_map(type_wrap_map: &HashMap<String, WrappedValue>) -> HashMap<String, JsValue> {
     let mut rst: HashMap<String, JsValue> = HashMap::new();
 
@@ -47,4 +111,31 @@ pub fn convert_map(type_wrap_map: &HashMap<String, WrappedValue>) -> HashMap<Str
     });
 
     rst
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_script_with_vars() {
+        let mut variables = HashMap::new();
+        variables.insert("amount".to_owned(), WrappedValue::Int(100));
+        variables.insert("pass".to_owned(), WrappedValue::Bool(false));
+
+        let rst = run_script_with_vars("pass = amount > 50; amount + 1".to_owned(), &mut variables).unwrap();
+
+        assert_eq!(convert_js_value(&rst), Some(WrappedValue::Int(101)));
+        assert_eq!(variables.get("pass"), Some(&WrappedValue::Bool(true)));
+        assert_eq!(variables.get("amount"), Some(&WrappedValue::Int(100)));
+    }
+
+    #[test]
+    fn test_eval_expression() {
+        let mut variables = HashMap::new();
+        variables.insert("amount".to_owned(), WrappedValue::Int(100));
+
+        assert_eq!(eval_expression("${amount > 50 ? 80 : 50}", &variables).unwrap(), Some(WrappedValue::Int(80)));
+        assert_eq!(eval_expression(" P3D ", &variables).unwrap(), Some(WrappedValue::Str("P3D".to_owned())));
+    }
+}
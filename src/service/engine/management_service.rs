@@ -0,0 +1,227 @@
+use color_eyre::Result;
+use tokio_postgres::Transaction;
+
+use crate::{gen_id, get_now};
+use crate::common::db;
+use crate::dao::{ApfRuEventOutboxDao, ApfRuIncidentDao, ApfRuJobDao};
+use crate::error::{AppError, ErrorCode};
+use crate::model::{ApfRuIncident, ApfRuJob};
+use crate::service::engine::execute_job;
+
+const MANUAL_LOCK_TIME: i64 = 5 * 60 * 1000;
+
+#[derive(Debug)]
+pub struct ManagementService {
+
+}
+
+impl ManagementService {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Lists the incidents of the process instance, or all incidents when it is `None`.
+    pub async fn find_incidents(&self, proc_inst_id: Option<&str>) -> Result<Vec<ApfRuIncident>> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let incident_dao = ApfRuIncidentDao::new(&tran);
+        let rst = match proc_inst_id {
+            Some(proc_inst_id) => incident_dao.find_by_proc_inst(proc_inst_id).await?,
+            None => incident_dao.find_all().await?,
+        };
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    pub async fn find_jobs(&self, proc_inst_id: &str) -> Result<Vec<ApfRuJob>> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let job_dao = ApfRuJobDao::new(&tran);
+        let rst = job_dao.find_by_proc_inst(proc_inst_id).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    /// Resolves the incidents of the job, the job executor picks it up again.
+    pub async fn set_job_retries(&self, job_id: &str, retries: i32) -> Result<()> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        self._set_job_retries(job_id, retries, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn _set_job_retries(&self, job_id: &str, retries: i32, tran: &Transaction<'_>) -> Result<()> {
+        if retries <= 0 {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("retries({}) of job({}) must be greater than 0", retries, job_id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?
+        }
+
+        let now = get_now();
+        let job_dao = ApfRuJobDao::new(tran);
+        job_dao.set_retries(job_id, retries, now, now).await?;
+
+        let incident_dao = ApfRuIncidentDao::new(tran);
+        incident_dao.delete_by_job_id(job_id).await?;
+
+        Ok(())
+    }
+
+    /// Puts the failed outbox events of the process instance back to the queue, they are
+    /// delivered again before the later events of the process instance.
+    pub async fn retry_outbox_events(&self, proc_inst_id: &str) -> Result<u64> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let rst = self._retry_outbox_events(proc_inst_id, &tran).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    pub(crate) async fn _retry_outbox_events(&self, proc_inst_id: &str, tran: &Transaction<'_>) -> Result<u64> {
+        let outbox_dao = ApfRuEventOutboxDao::new(tran);
+        let rst = outbox_dao.retry_failed(proc_inst_id, get_now()).await?;
+
+        Ok(rst)
+    }
+
+    /// Gives up the failed outbox event, the later events of its process instance are delivered.
+    pub async fn skip_outbox_event(&self, event_id: &str) -> Result<()> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        self._skip_outbox_event(event_id, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn _skip_outbox_event(&self, event_id: &str, tran: &Transaction<'_>) -> Result<()> {
+        let outbox_dao = ApfRuEventOutboxDao::new(tran);
+        outbox_dao.skip_failed(event_id).await?;
+
+        Ok(())
+    }
+
+    /// Executes the job immediately in the current thread. When it fails again without
+    /// retries left, a new incident is created.
+    pub async fn execute_job(&self, job_id: &str) -> Result<()> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        // the job being executed by a job executor must not be executed again
+        let job_dao = ApfRuJobDao::new(&tran);
+        job_dao.get_by_id(job_id).await?;
+        let now = get_now();
+        let lock_owner = format!("manual-{}", gen_id());
+        let mut job = job_dao.lock(job_id, &lock_owner, now + MANUAL_LOCK_TIME, now)
+            .await?
+            .ok_or(
+                AppError::new(
+                    ErrorCode::NotSupportError,
+                    Some(&format!("job({}) is locked by a job executor", job_id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+
+        job.retries = job.retries.max(1);
+        job_dao.set_retries_by_owner(job_id, job.retries, &lock_owner).await?;
+        let incident_dao = ApfRuIncidentDao::new(&tran);
+        incident_dao.delete_by_job_id(job_id).await?;
+        tran.commit().await?;
+
+        execute_job(&job, &lock_owner).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::Report;
+
+    use crate::service::engine::{handle_job_failure, OperatorContext, ProcessEngine};
+    use crate::service::engine::tests::create_test_deploy;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_incident_of_failed_job() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_async.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(
+                &procdef.key,
+                &procdef.company_id,
+                None,
+                &mut operator_ctx,
+                &tran
+            )
+            .await
+            .unwrap();
+
+        let job_dao = ApfRuJobDao::new(&tran);
+        let job = job_dao.find_by_proc_inst(&procinst.id).await.unwrap().remove(0);
+
+        // the job is retried until there is no retry left
+        let err = Report::new(AppError::new(ErrorCode::InternalError, Some("job error"), "test.rs:1", None));
+        let now = get_now();
+        job_dao.lock(&job.id, "test_owner", now + 60000, now).await.unwrap().unwrap();
+        handle_job_failure(&job, "test_owner", &err, &tran).await.unwrap();
+        let job = job_dao.get_by_id(&job.id).await.unwrap();
+        assert_eq!(job.retries, 2);
+        assert!(job.due_time > job.create_time);
+
+        job_dao.set_retries(&job.id, 1, job.due_time, get_now()).await.unwrap();
+        let job = job_dao.get_by_id(&job.id).await.unwrap();
+
+        // the failure is dropped when the job has been taken over by another owner
+        let now = get_now();
+        job_dao.lock(&job.id, "test_owner_2", now + 60000, now).await.unwrap().unwrap();
+        handle_job_failure(&job, "test_owner", &err, &tran).await.unwrap();
+        let locked = job_dao.get_by_id(&job.id).await.unwrap();
+        assert_eq!(locked.retries, 1);
+        assert_eq!(locked.lock_owner, Some("test_owner_2".to_owned()));
+
+        handle_job_failure(&job, "test_owner_2", &err, &tran).await.unwrap();
+
+        let incident_dao = ApfRuIncidentDao::new(&tran);
+        let incidents = incident_dao.find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].job_id, Some(job.id.clone()));
+        assert_eq!(incidents[0].error_location, Some("test.rs:1".to_owned()));
+
+        // resolve the incident
+        let management_service = ManagementService::new();
+        management_service._set_job_retries(&job.id, 3, &tran).await.unwrap();
+        let incidents = incident_dao.find_by_proc_inst(&procinst.id).await.unwrap();
+        assert!(incidents.is_empty());
+        let job = job_dao.get_by_id(&job.id).await.unwrap();
+        assert_eq!(job.retries, 3);
+
+        // the lock of the executor running the job is kept
+        let now = get_now();
+        job_dao.lock(&job.id, "executor_1", now + 60000, now).await.unwrap().unwrap();
+        let rst = management_service._set_job_retries(&job.id, 5, &tran).await;
+        assert!(rst.is_err());
+        let job = job_dao.get_by_id(&job.id).await.unwrap();
+        assert_eq!(job.lock_owner, Some("executor_1".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+}
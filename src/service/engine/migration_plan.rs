@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use color_eyre::Result;
+use tokio_postgres::Transaction;
+
+use crate::common::db;
+use crate::dao::{
+    ApfHiActinstDao, ApfHiIdentitylinkDao, ApfHiOplogDao, ApfHiProcinstDao, ApfHiTaskinstDao, ApfReProcdefDao,
+    ApfRuExecutionDao, ApfRuIdentitylinkDao, ApfRuIncidentDao, ApfRuJobDao, ApfRuTaskDao
+};
+use crate::error::{AppError, ErrorCode};
+use crate::get_now;
+use crate::model::{ApfRuExecution, NewApfHiOplog, OpType};
+use crate::service::engine::{BpmnElement, BpmnProcess, ProcessEngine};
+
+/// Builds the plan which moves the running instances from the source process definition to the target one.
+pub struct MigrationPlanBuilder {
+    source_procdef_id: String,
+    target_procdef_id: String,
+    map_equal_elements: bool,
+    mappings: HashMap<String, String>,
+}
+
+impl MigrationPlanBuilder {
+    pub fn new(source_procdef_id: &str, target_procdef_id: &str) -> Self {
+        Self {
+            source_procdef_id: source_procdef_id.to_owned(),
+            target_procdef_id: target_procdef_id.to_owned(),
+            map_equal_elements: false,
+            mappings: HashMap::new(),
+        }
+    }
+
+    /// Maps the nodes which have the same id and type in both process definitions.
+    pub fn map_equal_elements(mut self) -> Self {
+        self.map_equal_elements = true;
+        self
+    }
+
+    /// Maps the source element to the target element, it overrides the equal mapping.
+    pub fn map_element(mut self, source_element_id: &str, target_element_id: &str) -> Self {
+        self.mappings.insert(source_element_id.to_owned(), target_element_id.to_owned());
+        self
+    }
+
+    pub async fn build(self) -> Result<MigrationPlan> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let rst = self._build(&tran).await?;
+        tran.rollback().await?;
+
+        Ok(rst)
+    }
+
+    pub async fn _build(self, tran: &Transaction<'_>) -> Result<MigrationPlan> {
+        let source_process = load_bpmn_process(&self.source_procdef_id, tran).await?;
+        let target_process = load_bpmn_process(&self.target_procdef_id, tran).await?;
+
+        let mut mappings = HashMap::new();
+        if self.map_equal_elements {
+            for element in source_process.elements.iter() {
+                if let BpmnElement::Node(node) = element {
+                    if let Some(BpmnElement::Node(target)) = target_process.element_map.get(&node.get_id()) {
+                        if target.get_node_type() == node.get_node_type() {
+                            mappings.insert(node.get_id(), node.get_id());
+                        }
+                    }
+                }
+            }
+        }
+        mappings.extend(self.mappings);
+
+        // validate the plan against both processes
+        let mut problems = vec![];
+        for (source_id, target_id) in mappings.iter() {
+            match (source_process.element_map.get(source_id), target_process.element_map.get(target_id)) {
+                (Some(BpmnElement::Node(source)), Some(BpmnElement::Node(target))) => {
+                    if source.get_node_type() != target.get_node_type() {
+                        problems.push(format!(
+                            "{} ({}) can not be mapped to {} ({})",
+                            source.get_node_type(), source_id, target.get_node_type(), target_id
+                        ));
+                    }
+                },
+                (Some(BpmnElement::Node(_)), _) => problems.push(format!("target node ({}) is not found", target_id)),
+                _ => problems.push(format!("source node ({}) is not found", source_id)),
+            }
+        }
+
+        if !problems.is_empty() {
+            Err(AppError::new(ErrorCode::InvalidInput, Some(&problems.join("; ")), concat!(file!(), ":", line!()), None))?;
+        }
+
+        Ok(MigrationPlan {
+            source_procdef_id: self.source_procdef_id,
+            target_procdef_id: self.target_procdef_id,
+            mappings,
+            target_process,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct MigrationPlan {
+    pub source_procdef_id: String,
+    pub target_procdef_id: String,
+    pub mappings: HashMap<String, String>,
+    target_process: Arc<BpmnProcess>,
+}
+
+/// The problems which stop the process instance from being migrated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    pub proc_inst_id: String,
+    pub problems: Vec<String>,
+}
+
+impl MigrationReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl MigrationPlan {
+    /// Migrates the process instances. Nothing is changed in dry-run mode, or when any of the
+    /// instances can not be migrated, the reports tell what would break.
+    pub async fn migrate(&self, proc_inst_ids: &[String], user_id: Option<String>, dry_run: bool) -> Result<Vec<MigrationReport>> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let rst = self._migrate(proc_inst_ids, user_id, dry_run, &tran).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    pub async fn _migrate(
+        &self,
+        proc_inst_ids: &[String],
+        user_id: Option<String>,
+        dry_run: bool,
+        tran: &Transaction<'_>
+    ) -> Result<Vec<MigrationReport>> {
+        // the instances can not be moved onto a target which refuses to run them
+        let mut target_problems = vec![];
+        let target_procdef = ApfReProcdefDao::new(tran).get_by_id(&self.target_procdef_id).await?;
+        if target_procdef.is_deleted != 0 {
+            target_problems.push(format!("target process definition ({}) is deleted", target_procdef.id));
+        } else if target_procdef.is_suspended() {
+            target_problems.push(format!("target process definition ({}) is suspended", target_procdef.id));
+        }
+
+        let exec_dao = ApfRuExecutionDao::new(tran);
+        let mut reports = vec![];
+        let mut proc_insts = vec![];
+
+        for proc_inst_id in proc_inst_ids.iter() {
+            let mut problems = target_problems.clone();
+            match exec_dao.get_by_id(proc_inst_id).await {
+                Err(_) => problems.push("process instance is not running".to_owned()),
+                Ok(proc_inst) => {
+                    if proc_inst.proc_def_id != self.source_procdef_id {
+                        problems.push(format!("process definition ({}) is not the source of the plan", proc_inst.proc_def_id));
+                    }
+                    if proc_inst.is_suspended() {
+                        problems.push("process instance is suspended".to_owned());
+                    }
+
+                    let execs = exec_dao.find_children_by_proc_inst(proc_inst_id).await?;
+                    for exec in execs.iter() {
+                        let element_id = exec.element_id.clone().unwrap_or_default();
+                        if !self.mappings.contains_key(&element_id) {
+                            problems.push(format!("activity ({}) is not mapped", element_id));
+                        }
+                    }
+                    proc_insts.push((proc_inst, execs));
+                },
+            }
+
+            reports.push(MigrationReport { proc_inst_id: proc_inst_id.clone(), problems });
+        }
+
+        if dry_run {
+            return Ok(reports);
+        }
+
+        let failed: Vec<String> = reports
+            .iter()
+            .filter(|r| !r.is_ok())
+            .map(|r| format!("{}: {}", r.proc_inst_id, r.problems.join(", ")))
+            .collect();
+        if !failed.is_empty() {
+            Err(AppError::new(ErrorCode::InvalidInput, Some(&failed.join("; ")), concat!(file!(), ":", line!()), None))?;
+        }
+
+        for (proc_inst, execs) in proc_insts.iter() {
+            self.migrate_proc_inst(proc_inst, execs, user_id.clone(), tran).await?;
+        }
+
+        Ok(reports)
+    }
+
+    async fn migrate_proc_inst(
+        &self,
+        proc_inst: &ApfRuExecution,
+        execs: &[ApfRuExecution],
+        user_id: Option<String>,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let exec_dao = ApfRuExecutionDao::new(tran);
+        let hi_act_dao = ApfHiActinstDao::new(tran);
+        let job_dao = ApfRuJobDao::new(tran);
+        let incident_dao = ApfRuIncidentDao::new(tran);
+
+        for exec in execs.iter() {
+            let element_id = exec.element_id()?;
+            let target_id = self.target_element_id(&element_id)?;
+            if *target_id != element_id {
+                exec_dao.update_element(&exec.id, target_id).await?;
+                hi_act_dao.update_open_element(&exec.id, target_id, self.target_element_name(target_id)).await?;
+                job_dao.update_element_by_execution(&exec.id, target_id).await?;
+                incident_dao.update_element_by_execution(&exec.id, target_id).await?;
+            }
+        }
+
+        let task_dao = ApfRuTaskDao::new(tran);
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        for task in task_dao.find_by_proc_inst(&proc_inst.id).await? {
+            let element_id = task.element_id_ex()?;
+            let target_id = self.target_element_id(&element_id)?;
+            if *target_id != element_id {
+                task_dao.update_element(&task.id, target_id, self.target_element_name(target_id)).await?;
+                hi_task_dao.update_element(&task.id, target_id, self.target_element_name(target_id)).await?;
+            }
+        }
+
+        let proc_def_id = &self.target_procdef_id;
+        exec_dao.update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+        task_dao.update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+        job_dao.update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+        incident_dao.update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+        ApfRuIdentitylinkDao::new(tran).update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+        ApfHiIdentitylinkDao::new(tran).update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+        ApfHiProcinstDao::new(tran).update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+        hi_task_dao.update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+        hi_act_dao.update_procdef_by_proc_inst(&proc_inst.id, proc_def_id).await?;
+
+        let detail = serde_json::json!({
+            "source_procdef_id": self.source_procdef_id,
+            "target_procdef_id": self.target_procdef_id,
+        });
+        ApfHiOplogDao::new(tran).create(&NewApfHiOplog {
+            op_type: OpType::MIGRATE_PROCESS_INSTANCE.to_owned(),
+            proc_def_id: Some(proc_def_id.clone()),
+            proc_inst_id: Some(proc_inst.id.clone()),
+            task_id: None,
+            element_id: None,
+            user_id,
+            detail: Some(detail.to_string()),
+            create_time: get_now(),
+        }).await?;
+
+        Ok(())
+    }
+
+    fn target_element_id(&self, element_id: &str) -> Result<&String> {
+        let rst = self.mappings
+            .get(element_id)
+            .ok_or(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("activity ({}) is not mapped", element_id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+
+        Ok(rst)
+    }
+
+    fn target_element_name(&self, element_id: &str) -> Option<String> {
+        self.target_process.element_map.get(element_id).and_then(|el| el.get_element_name())
+    }
+}
+
+async fn load_bpmn_process(procdef_id: &str, tran: &Transaction<'_>) -> Result<Arc<BpmnProcess>> {
+    let procdef_dao = ApfReProcdefDao::new(tran);
+    let re_def = procdef_dao.get_by_id(procdef_id).await?;
+    let repository_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_repository_service();
+    let bpmn_process = repository_service.load_bpmn_by_deployment(&re_def.deployment_id, tran).await?;
+
+    Ok(Arc::new(bpmn_process))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use crate::model::SuspensionState;
+    use crate::service::engine::{OperatorContext, TaskService};
+    use crate::service::engine::query::TaskQuery;
+    use crate::service::engine::tests::create_test_deploy;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_migrate_process_instance() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let source = create_test_deploy("bpmn/process_reject.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&source.key, &source.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        // approval_1 is renamed to dept_approval_1 in the new version
+        let target = create_test_deploy("bpmn/process_reject_v2.bpmn.xml", &tran).await;
+        let proc_inst_ids = vec![procinst.id.clone()];
+
+        let rst = rt_service.create_migration_plan(&source.id, &target.id)
+            .map_element("approval_1", "fork_1")
+            ._build(&tran)
+            .await;
+        assert!(rst.is_err());
+
+        let plan = rt_service.create_migration_plan(&source.id, &target.id)
+            .map_equal_elements()
+            ._build(&tran)
+            .await
+            .unwrap();
+        let reports = plan._migrate(&proc_inst_ids, None, true, &tran).await.unwrap();
+        assert_eq!(reports[0].problems, vec!["activity (approval_1) is not mapped".to_owned()]);
+        assert!(plan._migrate(&proc_inst_ids, None, false, &tran).await.is_err());
+
+        // the suspended instance is not migrated
+        rt_service._update_suspension_state(&procinst.id, SuspensionState::TRUE, &tran).await.unwrap();
+        let reports = plan._migrate(&proc_inst_ids, None, true, &tran).await.unwrap();
+        assert!(reports[0].problems.contains(&"process instance is suspended".to_owned()));
+        rt_service._update_suspension_state(&procinst.id, SuspensionState::FALSE, &tran).await.unwrap();
+
+        let mut plan = rt_service.create_migration_plan(&source.id, &target.id)
+            .map_equal_elements()
+            .map_element("approval_1", "dept_approval_1")
+            ._build(&tran)
+            .await
+            .unwrap();
+
+        // the activity which is left out of the mappings is reported, nothing is changed
+        let exec_dao = ApfRuExecutionDao::new(&tran);
+        let proc_inst = exec_dao.get_by_id(&procinst.id).await.unwrap();
+        let execs = exec_dao.find_children_by_proc_inst(&procinst.id).await.unwrap();
+        let target_id = plan.mappings.remove("approval_1").unwrap();
+        let err = plan.migrate_proc_inst(&proc_inst, &execs, None, &tran).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<AppError>().unwrap().msg, "activity (approval_1) is not mapped");
+        plan.mappings.insert("approval_1".to_owned(), target_id);
+
+        let reports = plan._migrate(&proc_inst_ids, Some("admin".to_owned()), false, &tran).await.unwrap();
+        assert!(reports[0].is_ok());
+
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        assert_eq!(task.element_id, Some("dept_approval_1".to_owned()));
+        assert_eq!(task.proc_def_id, target.id);
+
+        // the instance continues on the new version
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 2);
+
+        // nothing can be migrated onto the deleted definition
+        ApfReProcdefDao::new(&tran).delete_by_id(&target.id, "admin").await.unwrap();
+        let plan = rt_service.create_migration_plan(&source.id, &target.id)
+            .map_equal_elements()
+            ._build(&tran)
+            .await
+            .unwrap();
+        let reports = plan._migrate(&proc_inst_ids, None, true, &tran).await.unwrap();
+        assert!(reports[0].problems.contains(&format!("target process definition ({}) is deleted", target.id)));
+
+        tran.rollback().await.unwrap();
+    }
+}
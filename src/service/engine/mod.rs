@@ -9,6 +9,19 @@ pub mod js_engine;
 pub mod query;
 pub mod process_engine;
 pub mod history_service;
+pub mod engine_listener;
+pub mod engine_event;
+pub mod event_outbox;
+pub mod task_notifier;
+pub mod job_executor;
+pub mod management_service;
+pub mod process_instance_modification;
+pub mod migration_plan;
+pub mod task_deadline;
+pub mod escalation_sweeper;
+pub mod business_calendar;
+pub mod candidate_resolver;
+pub mod background_worker;
 
 
 pub use process_engine::*;
@@ -23,6 +36,19 @@ pub use behavior::operator_executor::*;
 pub use behavior::operator::*;
 pub use behavior::*;
 pub use js_engine::*;
+pub use engine_listener::*;
+pub use engine_event::*;
+pub use event_outbox::*;
+pub use task_notifier::*;
+pub use job_executor::*;
+pub use management_service::*;
+pub use process_instance_modification::*;
+pub use migration_plan::*;
+pub use task_deadline::*;
+pub use escalation_sweeper::*;
+pub use business_calendar::*;
+pub use candidate_resolver::*;
+pub use background_worker::*;
 
 pub fn get_default_process_engine() -> ProcessEngine {
     ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE)
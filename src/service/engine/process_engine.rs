@@ -3,6 +3,15 @@ use super::RuntimeService;
 use super::RepositoryService;
 use super::HistoryService;
 use super::TaskService;
+use super::ManagementService;
+use super::{ListenerCallback, register_listener};
+use super::{DispatchMode, EventCallback, subscribe_event, unsubscribe_event};
+use super::{subscribe_task_notifications, TaskEventStream};
+use super::{JobExecutor, JobExecutorConfig};
+use super::{EscalationSweeper, EscalationSweeperConfig};
+use super::{BusinessCalendar, register_business_calendar};
+use super::{CandidateFunction, register_candidate_function};
+use color_eyre::Result;
 
 #[derive(Debug)]
 pub struct ProcessEngine {
@@ -11,6 +20,7 @@ pub struct ProcessEngine {
     runtime_service: Arc<RuntimeService>,
     history_service: Arc<HistoryService>,
     task_service: Arc<TaskService>,
+    management_service: Arc<ManagementService>,
 }
 
 #[allow(unused)]
@@ -25,6 +35,7 @@ impl ProcessEngine {
             runtime_service: Arc::new(RuntimeService::new()),
             history_service: Arc::new(HistoryService::new()),
             task_service: Arc::new(TaskService::new()),
+            management_service: Arc::new(ManagementService::new()),
         }
     }
 
@@ -47,4 +58,59 @@ impl ProcessEngine {
     pub fn get_task_service(&self) -> Arc<TaskService> {
         self.task_service.clone()
     }
+
+    pub fn get_management_service(&self) -> Arc<ManagementService> {
+        self.management_service.clone()
+    }
+
+    /// Registers a rust callback, which is referenced by the `delegate` attribute of
+    /// `executionListener` or `taskListener`.
+    pub fn register_listener(&self, name: &str, callback: ListenerCallback) {
+        register_listener(name, callback);
+    }
+
+    /// Registers a business calendar, which is referenced by the `businessCalendar` attribute
+    /// of the process or the user task.
+    pub fn register_business_calendar(&self, name: &str, calendar: Arc<dyn BusinessCalendar>) {
+        register_business_calendar(name, calendar);
+    }
+
+    /// Registers a function which finds the candidates, it's called by the expression like
+    /// `${manager_of(applicant)}` in `candidateUsers` or `candidateGroups`.
+    pub fn register_candidate_function(&self, name: &str, function: CandidateFunction) {
+        register_candidate_function(name, function);
+    }
+
+    /// Subscribes the engine events, `mode` decides whether the callback runs in the
+    /// transaction of the operation or after it is committed.
+    pub fn subscribe(&self, name: &str, mode: DispatchMode, callback: EventCallback) {
+        subscribe_event(name, mode, callback);
+    }
+
+    pub fn unsubscribe(&self, name: &str) {
+        unsubscribe_event(name);
+    }
+
+    /// Listens on the postgres channel of task notifications with a dedicated connection,
+    /// `engine.task_notify` must be enabled to make the engine send them.
+    pub async fn subscribe_task_events(&self) -> Result<TaskEventStream> {
+        subscribe_task_notifications().await
+    }
+
+    /// Starts the background threads which execute the jobs of async continuations,
+    /// the executor stops when it is shut down or dropped.
+    pub fn start_job_executor(&self, config: JobExecutorConfig) -> Result<JobExecutor> {
+        let mut job_executor = JobExecutor::new(config);
+        job_executor.start()?;
+
+        Ok(job_executor)
+    }
+
+    /// Starts the background thread which reminds and escalates the overdue tasks.
+    pub fn start_escalation_sweeper(&self, config: EscalationSweeperConfig) -> Result<EscalationSweeper> {
+        let mut sweeper = EscalationSweeper::new(config);
+        sweeper.start()?;
+
+        Ok(sweeper)
+    }
 }
\ No newline at end of file
@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use color_eyre::Result;
+use tokio_postgres::Transaction;
+
+use crate::common::db;
+use crate::dao::{ApfHiOplogDao, ApfHiProcinstDao, ApfReProcdefDao, ApfRuExecutionDao, ApfRuVariableDao};
+use crate::error::{AppError, ErrorCode};
+use crate::get_now;
+use crate::model::{ApfRuVariable, NewApfHiOplog, OpType};
+use crate::service::engine::{
+    check_not_suspended, dispatch_after_commit, ModificationInstruction, ModifyActivityCmd, Operator, OperatorContext,
+    OperatorExecutor, ProcessEngine, write_outbox
+};
+
+/// Moves the tokens of a running process instance, it's used by the administrators to fix the data.
+/// The instructions are executed in order and every one of them is recorded in `apf_hi_oplog`.
+pub struct ProcessInstanceModificationBuilder {
+    proc_inst_id: String,
+    instructions: Vec<ModificationInstruction>,
+    admin: bool,
+    user_id: Option<String>,
+    reason: Option<String>,
+}
+
+impl ProcessInstanceModificationBuilder {
+    pub fn new(proc_inst_id: &str) -> Self {
+        Self {
+            proc_inst_id: proc_inst_id.to_owned(),
+            instructions: vec![],
+            admin: false,
+            user_id: None,
+            reason: None,
+        }
+    }
+
+    /// Removes the executions on the element, with their tasks and jobs.
+    pub fn cancel_activity(mut self, element_id: &str) -> Self {
+        self.instructions.push(ModificationInstruction::CancelActivity { element_id: element_id.to_owned() });
+        self
+    }
+
+    /// Creates an execution which enters the element.
+    pub fn start_before(mut self, element_id: &str) -> Self {
+        self.instructions.push(ModificationInstruction::StartBefore { element_id: element_id.to_owned() });
+        self
+    }
+
+    /// Creates an execution which leaves the element through its only outgoing flow.
+    pub fn start_after(mut self, element_id: &str) -> Self {
+        self.instructions.push(ModificationInstruction::StartAfter { element_id: element_id.to_owned() });
+        self
+    }
+
+    /// The modification is refused unless it's executed by an administrator.
+    pub fn admin(mut self, user_id: &str) -> Self {
+        self.admin = true;
+        self.user_id = Some(user_id.to_owned());
+        self
+    }
+
+    pub fn reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_owned());
+        self
+    }
+
+    pub async fn execute(self) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, self.user_id.clone(), HashMap::new());
+        self._execute(&mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub async fn _execute(&self, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        if !self.admin {
+            Err(
+                AppError::new(
+                    ErrorCode::UnAuthorized,
+                    Some("process instance modification is only allowed for administrators"),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        if self.instructions.is_empty() {
+            Err(AppError::new(ErrorCode::InvalidInput, Some("no modification instruction"), concat!(file!(), ":", line!()), None))?;
+        }
+
+        let exec_dao = ApfRuExecutionDao::new(tran);
+        let proc_inst = exec_dao.get_by_id(&self.proc_inst_id).await?;
+        check_not_suspended(&proc_inst)?;
+
+        let procdef_dao = ApfReProcdefDao::new(tran);
+        let re_def = procdef_dao.get_by_id(&proc_inst.proc_def_id).await?;
+        let repository_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_repository_service();
+        let bpmn_process = Arc::new(repository_service.load_bpmn_by_deployment(&re_def.deployment_id, tran).await?);
+        operator_ctx.bpmn_process = Some(bpmn_process.clone());
+
+        let var_dao = ApfRuVariableDao::new(tran);
+        let var_insts = var_dao.find_all_by_proc_inst(&proc_inst.id).await?;
+        operator_ctx.variables = ApfRuVariable::convert_variables_to_map(&var_insts);
+
+        let proc_inst = Rc::new(proc_inst);
+        let oplog_dao = ApfHiOplogDao::new(tran);
+        for instruction in self.instructions.iter() {
+            let element = bpmn_process.element_map.get(instruction.element_id()).ok_or(
+                AppError::new(
+                    ErrorCode::NotFound,
+                    Some(&format!("element ({}) is not found", instruction.element_id())),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+
+            let modify_cmd = ModifyActivityCmd::new(element.clone(), proc_inst.clone(), instruction.clone());
+            let mut operator_exec = OperatorExecutor::new();
+            operator_exec.execute(Operator::ModifyActivityCmd(modify_cmd), operator_ctx, tran).await?;
+
+            let detail = serde_json::json!({
+                "instruction": instruction,
+                "reason": self.reason,
+            });
+            oplog_dao.create(&NewApfHiOplog {
+                op_type: OpType::MODIFY_PROCESS_INSTANCE.to_owned(),
+                proc_def_id: Some(proc_inst.proc_def_id.clone()),
+                proc_inst_id: Some(proc_inst.id.clone()),
+                task_id: None,
+                element_id: Some(instruction.element_id().to_owned()),
+                user_id: self.user_id.clone(),
+                detail: Some(detail.to_string()),
+                create_time: get_now(),
+            }).await?;
+        }
+
+        // the process instance which is still running must have an activity
+        let hi_procinst_dao = ApfHiProcinstDao::new(tran);
+        let hi_procinst = hi_procinst_dao.get_by_id(&proc_inst.id).await?;
+        if hi_procinst.end_time.is_none() && exec_dao.find_children_by_proc_inst(&proc_inst.id).await?.is_empty() {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("no activity is left in process instance ({}), delete it instead", proc_inst.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::service::engine::query::TaskQuery;
+    use crate::service::engine::tests::create_test_deploy;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_modify_process_instance() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_reject.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        // the admin flag is required
+        let mut operator_ctx = OperatorContext::default();
+        let rst = rt_service.create_modification(&procinst.id)
+            .cancel_activity("apply_1")
+            .start_before("accountant_approval_1")
+            ._execute(&mut operator_ctx, &tran)
+            .await;
+        assert!(rst.is_err());
+
+        let mut operator_ctx = OperatorContext::new(None, Some("admin".to_owned()), HashMap::new());
+        rt_service.create_modification(&procinst.id)
+            .cancel_activity("apply_1")
+            .start_before("accountant_approval_1")
+            .admin("admin")
+            .reason("skip the approval")
+            ._execute(&mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].element_id, Some("accountant_approval_1".to_owned()));
+
+        // leaving the department approval forks both approvals
+        let mut operator_ctx = OperatorContext::new(None, Some("admin".to_owned()), HashMap::new());
+        rt_service.create_modification(&procinst.id)
+            .cancel_activity("accountant_approval_1")
+            .start_after("approval_1")
+            .admin("admin")
+            ._execute(&mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 2);
+
+        let oplogs = ApfHiOplogDao::new(&tran).find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(oplogs.len(), 4);
+        assert_eq!(oplogs[0].user_id, Some("admin".to_owned()));
+
+        // cancelling all the activities is refused
+        let mut operator_ctx = OperatorContext::new(None, Some("admin".to_owned()), HashMap::new());
+        let rst = rt_service.create_modification(&procinst.id)
+            .cancel_activity("accountant_approval_1")
+            .cancel_activity("lawyer_approval_1")
+            .admin("admin")
+            ._execute(&mut operator_ctx, &tran)
+            .await;
+        assert!(rst.is_err());
+
+        tran.rollback().await.unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use color_eyre::Result;
+use log4rs_macros::*;
+use regex::Regex;
+use tokio_postgres::Transaction;
+use tokio_postgres::types::ToSql;
+
+use crate::dao::{SqlFragment as SF, BaseDao};
+use crate::model::ApfHiProcinst;
+use crate::common::StringBuilder;
+
+/// Queries the process instances in history, both the running and the finished ones.
+pub struct HistoricProcessInstanceQuery<'a> {
+    id: Option<String>,
+    business_key: Option<String>,
+    process_definition_key: Option<String>,
+    started_by: Option<String>,
+    finished: Option<bool>,
+    order_by: Option<String>,
+    count: Option<String>,
+    base_dao: BaseDao<'a>,
+}
+
+#[allow(unused)]
+impl<'a> HistoricProcessInstanceQuery<'a> {
+    pub const SELECT_FIELD:&'static str = r#"
+        t1.id, t1.rev, t1.proc_inst_id, t1.business_key, t1.proc_def_id,
+        t1.start_time, t1.end_time, t1.duration, t1.start_user, t1.start_element_id,
+        t1.end_element_id, t1.end_user, t1.delete_reason
+    "#;
+
+    pub fn new(tran: &'a Transaction<'a>) -> Self {
+        Self {
+            base_dao: BaseDao::new(tran),
+            id: None,
+            business_key: None,
+            process_definition_key: None,
+            started_by: None,
+            finished: None,
+            order_by: None,
+            count: None,
+        }
+    }
+
+    pub fn count(mut self, field: &str) -> Self {
+        self.count = Some(field.to_owned());
+        self
+    }
+
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    pub fn business_key(mut self, business_key: &str) -> Self {
+        self.business_key = Some(business_key.to_owned());
+        self
+    }
+
+    pub fn process_definition_key(mut self, process_definition_key: &str) -> Self {
+        self.process_definition_key = Some(process_definition_key.to_owned());
+        self
+    }
+
+    /// The process instances started by the user.
+    pub fn started_by(mut self, user_id: &str) -> Self {
+        self.started_by = Some(user_id.to_owned());
+        self
+    }
+
+    pub fn finished(mut self) -> Self {
+        self.finished = Some(true);
+        self
+    }
+
+    pub fn unfinished(mut self) -> Self {
+        self.finished = Some(false);
+        self
+    }
+
+    pub fn order_by_start_time_desc(mut self) -> Self {
+        self.order_by = Some("t1.start_time desc".to_owned());
+        self
+    }
+
+    pub async fn fetch_all(&self) -> Result<Vec<ApfHiProcinst>> {
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        let sql= self.build_sql(&mut params);
+        let rst = self.base_dao.fetcth_all::<ApfHiProcinst>(&sql, &params).await?;
+
+        Ok(rst)
+    }
+
+    pub async fn fetch_one(&self) -> Result<ApfHiProcinst> {
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        let sql= self.build_sql(&mut params);
+        let rst = self.base_dao.fetch_one::<ApfHiProcinst>(&sql, &params).await?;
+
+        Ok(rst)
+    }
+
+    pub async fn fetch_count(&self) -> Result<i64> {
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        let sql= self.build_sql(&mut params);
+        let rst = self.base_dao.fetch_i64(&sql, &params).await?;
+
+        Ok(rst)
+    }
+
+    fn build_sql<'b: 'a>(&'b self, params: &mut Vec<&'a (dyn ToSql + Sync)>) -> String {
+        let mut sql_builder = StringBuilder::new();
+        sql_builder.append(SF::SELECT);
+
+        if let Some(v) = &self.count {
+            sql_builder.ltrim().append(SF::COUNT(v.to_owned()));
+        } else {
+            let re = Regex::new(r"\n\s*").unwrap();
+            let field = re.replace_all(HistoricProcessInstanceQuery::SELECT_FIELD, " ");
+            let field = field.trim();
+            sql_builder.ltrim().append(SF::FIELD(field.to_owned()));
+        }
+
+        sql_builder.ltrim().append(SF::FROM("apf_hi_procinst t1".to_owned()));
+
+        if let Some(_) = &self.process_definition_key {
+            sql_builder.ltrim().append(SF::JOIN("apf_re_procdef t2 on t2.id = t1.proc_def_id".to_owned()));
+        }
+
+        sql_builder.ltrim().append(SF::WHERE);
+
+        match self.finished {
+            Some(true) => { sql_builder.ltrim().append(SF::AND("t1.end_time is not null".to_owned())); },
+            Some(false) => { sql_builder.ltrim().append(SF::AND("t1.end_time is null".to_owned())); },
+            None => {},
+        }
+
+        let mut idx = 0;
+        if let Some(v) = &self.id {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t1.id = ${}", idx)));
+            params.push(v);
+        }
+
+        if let Some(v) = &self.business_key {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t1.business_key = ${}", idx)));
+            params.push(v);
+        }
+
+        if let Some(v) = &self.process_definition_key {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t2.key = ${}", idx)));
+            params.push(v);
+        }
+
+        if let Some(v) = &self.started_by {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t1.start_user = ${}", idx)));
+            params.push(v);
+        }
+
+        if let Some(v) = &self.order_by {
+            sql_builder.ltrim().append(SF::ORDER_BY(v.to_owned()));
+        }
+
+        let sql = sql_builder.string();
+
+        debug!("{}", sql);
+
+        sql
+    }
+}
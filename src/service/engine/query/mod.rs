@@ -1,3 +1,7 @@
 pub mod task_query;
+pub mod process_instance_query;
+pub mod historic_process_instance_query;
 
-pub use task_query::*;
\ No newline at end of file
+pub use task_query::*;
+pub use process_instance_query::*;
+pub use historic_process_instance_query::*;
@@ -5,8 +5,9 @@ use tokio_postgres::Transaction;
 use tokio_postgres::types::ToSql;
 
 use crate::dao::{SqlFragment as SF, BaseDao};
-use crate::model::ApfRuTask;
+use crate::model::{ApfRuTask, SuspensionState};
 use crate::common::StringBuilder;
+use crate::get_now;
 
 pub struct TaskQuery<'a> {
     id: Option<String>,
@@ -14,8 +15,12 @@ pub struct TaskQuery<'a> {
     proc_inst_id: Option<String>,
     candidate_group: Option<String>,
     candidate_user: Option<String>,
+    assignee: Option<String>,
+    parent_task_id: Option<String>,
+    due_before: Option<i64>,
     business_key: Option<String>,
     process_definition_key: Option<String>,
+    suspension_state: Option<i32>,
     order_by: Option<String>,
     count: Option<String>,
     base_dao: BaseDao<'a>,
@@ -27,7 +32,11 @@ impl<'a> TaskQuery<'a> {
         t1.id, t1.rev, t1.execution_id, t1.proc_inst_id, t1.proc_def_id,
         t1.element_id, t1.element_name, t1.element_type, t1.business_key,
         t1.description, t1.start_user_id, t1.create_time,
-        t1.suspension_state, t1.form_key
+        t1.suspension_state, t1.form_key, t1.assignee, t1.owner, t1.delegation,
+        t1.sign_parent_id, t1.sign_mode, t1.parent_task_id, t1.block_parent,
+        t1.priority, t1.due_date, t1.follow_up_date, t1.remind_count,
+        t1.last_remind_time, t1.escalate_time, t1.prev_task_id,
+        t1.next_sweep_time
     "#;
 
     const FROM_TABLE:&'static str = " from apf_ru_task ";
@@ -40,8 +49,12 @@ impl<'a> TaskQuery<'a> {
             proc_inst_id: None,
             candidate_group: None,
             candidate_user: None,
+            assignee: None,
+            parent_task_id: None,
+            due_before: None,
             business_key: None,
             process_definition_key: None,
+            suspension_state: None,
             order_by: None,
             count: None,
         }
@@ -77,6 +90,31 @@ impl<'a> TaskQuery<'a> {
         self
     }
 
+    pub fn assignee(mut self, assignee: &str) -> Self {
+        self.assignee = Some(assignee.to_owned());
+        self
+    }
+
+    pub fn parent_task_id(mut self, parent_task_id: &str) -> Self {
+        self.parent_task_id = Some(parent_task_id.to_owned());
+        self
+    }
+
+    pub fn due_before(mut self, due_before: i64) -> Self {
+        self.due_before = Some(due_before);
+        self
+    }
+
+    /// The tasks whose due date has passed.
+    pub fn overdue(self) -> Self {
+        self.due_before(get_now())
+    }
+
+    pub fn order_by_priority_desc(mut self) -> Self {
+        self.order_by = Some("t1.priority desc, t1.create_time".to_owned());
+        self
+    }
+
     pub fn business_key(mut self, business_key: &str) -> Self {
         self.business_key = Some(business_key.to_owned());
         self
@@ -87,6 +125,16 @@ impl<'a> TaskQuery<'a> {
         self
     }
 
+    pub fn active(mut self) -> Self {
+        self.suspension_state = Some(SuspensionState::FALSE);
+        self
+    }
+
+    pub fn suspended(mut self) -> Self {
+        self.suspension_state = Some(SuspensionState::TRUE);
+        self
+    }
+
     pub async fn fetch_all(&self) -> Result<Vec<ApfRuTask>> {
         let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
         let sql= self.build_sql(&mut params);
@@ -136,7 +184,18 @@ impl<'a> TaskQuery<'a> {
 
         sql_builder.ltrim().append(SF::WHERE);
 
+        // the claimed tasks are not offered to the other candidates
+        if self.candidate_group != None || self.candidate_user != None {
+            sql_builder.ltrim().append(SF::AND("t1.assignee is null".to_owned()));
+        }
+
         let mut idx = 0;
+        if let Some(v) = &self.id {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t1.id = ${}", idx)));
+            params.push(v);
+        }
+
         if let Some(v) = &self.execution_id {
             idx += 1;
             sql_builder.ltrim().append(SF::AND(format!("t1.execution_id = ${}", idx)));
@@ -169,12 +228,36 @@ impl<'a> TaskQuery<'a> {
             params.push(v);
         }
 
+        if let Some(v) = &self.assignee {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t1.assignee = ${}", idx)));
+            params.push(v);
+        }
+
+        if let Some(v) = &self.parent_task_id {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t1.parent_task_id = ${}", idx)));
+            params.push(v);
+        }
+
+        if let Some(v) = &self.due_before {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t1.due_date < ${}", idx)));
+            params.push(v);
+        }
+
         if let Some(v) = &self.process_definition_key {
             idx += 1;
             sql_builder.ltrim().append(SF::AND(format!("t2.key = ${}", idx)));
             params.push(v);
         }
 
+        if let Some(v) = &self.suspension_state {
+            idx += 1;
+            sql_builder.ltrim().append(SF::AND(format!("t1.suspension_state = ${}", idx)));
+            params.push(v);
+        }
+
         if let Some(v) = &self.order_by {
             sql_builder.ltrim().append(SF::ORDER_BY(v.to_owned()));
         }
@@ -231,6 +314,15 @@ pub mod tests {
             .await
             .unwrap();
         assert_eq!(rst, 1);
+
+        let task3 = create_test_task(&proc_inst, &tran).await;
+        let tasks = TaskQuery::new(&tran)
+            .id(&task3.id)
+            .fetch_all()
+            .await
+            .unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, task3.id);
     }
 
 }
\ No newline at end of file
@@ -3,10 +3,10 @@ use dysql::{PageDto, Pagination};
 use tokio_postgres::Transaction;
 
 use crate::common::{db, md5};
-use crate::dao::{ApfGeBytearrayDao, ApfReDeploymentDao, ApfReProcdefDao};
+use crate::dao::{ApfGeBytearrayDao, ApfReDeploymentDao, ApfReProcdefDao, ApfRuExecutionDao, ApfRuTaskDao};
 use crate::dto::{DeploymentDto, BpmnResultDto, ProcdefDto};
 use crate::error::{AppError, ErrorCode};
-use crate::model::{ApfReDeployment, ApfReProcdef};
+use crate::model::{ApfReDeployment, ApfReProcdef, SuspensionState};
 use crate::service::engine::{BpmnManager, BpmnProcess};
 use super::DeploymentBuilder;
 
@@ -168,6 +168,50 @@ impl RepositoryService {
         Ok(pg_deployment)
     }
 
+    /// Suspends the process definition so that no new instance can be started, the running
+    /// instances are suspended too when `cascade` is true.
+    pub async fn suspend_procdef(&self, procdef_id: &str, user_id: &str, cascade: bool) -> Result<()> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        self._update_procdef_suspension_state(procdef_id, SuspensionState::TRUE, user_id, cascade, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn activate_procdef(&self, procdef_id: &str, user_id: &str, cascade: bool) -> Result<()> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        self._update_procdef_suspension_state(procdef_id, SuspensionState::FALSE, user_id, cascade, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn _update_procdef_suspension_state(
+        &self, 
+        procdef_id: &str, 
+        suspension_state: i32, 
+        user_id: &str, 
+        cascade: bool, 
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let procdef_dao = ApfReProcdefDao::new(tran);
+        procdef_dao.update_suspension_state(procdef_id, suspension_state, user_id).await?;
+
+        if cascade {
+            let exec_dao = ApfRuExecutionDao::new(tran);
+            exec_dao.update_suspension_state_by_procdef(procdef_id, suspension_state).await?;
+
+            let task_dao = ApfRuTaskDao::new(tran);
+            task_dao.update_suspension_state_by_procdef(procdef_id, suspension_state).await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn delete_procdef_by_id(&self, procdef_id: &str, user_id: &str) -> Result<()> {
         let mut conn = db::get_connect().await?;
         let tran = conn.transaction().await?;
@@ -183,5 +227,55 @@ impl RepositoryService {
 
 #[cfg(test)]
 mod tests {
-
+    use crate::service::engine::{OperatorContext, RuntimeService};
+    use crate::service::engine::query::TaskQuery;
+    use crate::service::engine::tests::create_test_deploy;
+    use super::*;
+
+    #[tokio::test]
+    async fn test_suspend_procdef() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_2.bpmn.xml", &tran).await;
+        let rt_service = RuntimeService::new();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let repository_service = RepositoryService::new();
+        repository_service._update_procdef_suspension_state(&procdef.id, SuspensionState::TRUE, "admin", true, &tran)
+            .await
+            .unwrap();
+
+        // no new instance can be started
+        let mut operator_ctx = OperatorContext::default();
+        let rst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await;
+        assert_eq!(rst.unwrap_err().downcast_ref::<AppError>().unwrap().code, ErrorCode::Suspended);
+
+        // the running instances are suspended by cascade
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).suspended().fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        repository_service._update_procdef_suspension_state(&procdef.id, SuspensionState::FALSE, "admin", true, &tran)
+            .await
+            .unwrap();
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).active().fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        // the deleted definition can not be started
+        let procdef_dao = ApfReProcdefDao::new(&tran);
+        procdef_dao.delete_by_id(&procdef.id, "admin").await.unwrap();
+        let mut operator_ctx = OperatorContext::default();
+        let rst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await;
+        assert!(rst.is_err());
+
+        tran.rollback().await.unwrap();
+    }
 }
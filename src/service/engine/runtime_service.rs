@@ -6,9 +6,16 @@ use color_eyre::Result;
 use tokio_postgres::Transaction;
 
 use crate::common::db;
-use crate::service::engine::{CreateAndStartProcessInstanceCmd, Operator, OperatorContext, OperatorExecutor, ProcessEngine};
-use crate::model::{ApfRuExecution, WrappedValue};
-use crate::dao::ApfReProcdefDao;
+use crate::get_now;
+use crate::service::engine::{
+    BaseOperator, CreateAndStartProcessInstanceCmd, dispatch_after_commit, EngineEvent, ListenerEvent, MigrationPlanBuilder, 
+    notify_task_completed, Operator, OperatorContext, OperatorExecutor, ProcessEngine, ProcessInstanceModificationBuilder, write_outbox
+};
+use crate::model::{ApfRuExecution, ApfRuVariable, SuspensionState, WrappedValue};
+use crate::dao::{
+    ApfHiActinstDao, ApfHiProcinstDao, ApfHiTaskinstDao, ApfReProcdefDao, ApfRuExecutionDao, ApfRuIdentitylinkDao, 
+    ApfRuIncidentDao, ApfRuJobDao, ApfRuTaskDao, ApfRuVariableDao
+};
 use crate::error::{AppError, ErrorCode};
 
 #[derive(Debug)]
@@ -39,8 +46,11 @@ impl RuntimeService {
         let rst = self._start_process_instance_by_key(
             process_definition_key, company_id, business_key, &mut operator_ctx, &tran).await?;
 
+        write_outbox(&operator_ctx.events, &tran).await?;
         tran.commit().await?;
 
+        dispatch_after_commit(operator_ctx.take_events());
+
         Ok(rst)
     }
 
@@ -54,6 +64,16 @@ impl RuntimeService {
     -> Result<Rc<ApfRuExecution>>  {
         let procdef_dao = ApfReProcdefDao::new(tran);
         let re_def = procdef_dao.get_lastest_by_key(process_definition_key, company_id).await?;
+        if re_def.is_suspended() {
+            Err(
+                AppError::new(
+                    ErrorCode::Suspended, 
+                    Some(&format!("process definition({}) is suspended", re_def.id)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
 
         let repository_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_repository_service();
         let bpmn_process = repository_service.load_bpmn_by_deployment(&re_def.deployment_id, tran).await?;
@@ -77,12 +97,170 @@ impl RuntimeService {
         }
     }
 
+    /// Suspends the process instance with its executions and tasks, the tasks can not be
+    /// completed and the jobs are not executed until it is activated.
+    pub async fn suspend_process_instance_by_id(&self, proc_inst_id: &str) -> Result<()> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        self._update_suspension_state(proc_inst_id, SuspensionState::TRUE, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn activate_process_instance_by_id(&self, proc_inst_id: &str) -> Result<()> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        self._update_suspension_state(proc_inst_id, SuspensionState::FALSE, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn _update_suspension_state(&self, proc_inst_id: &str, suspension_state: i32, tran: &Transaction<'_>) -> Result<()> {
+        let exec_dao = ApfRuExecutionDao::new(tran);
+        let proc_inst = exec_dao.get_by_id(proc_inst_id).await?;
+        if proc_inst.proc_inst_id.as_deref() != Some(proc_inst_id) {
+            Err(
+                AppError::new(
+                    ErrorCode::NotFound, 
+                    Some(&format!("process instance({}) is not exist", proc_inst_id)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        exec_dao.update_suspension_state_by_proc_inst(proc_inst_id, suspension_state).await?;
+
+        let task_dao = ApfRuTaskDao::new(tran);
+        task_dao.update_suspension_state_by_proc_inst(proc_inst_id, suspension_state).await?;
+
+        Ok(())
+    }
+
+    /// Creates the builder to modify the running process instance, it must be executed as an administrator.
+    pub fn create_modification(&self, proc_inst_id: &str) -> ProcessInstanceModificationBuilder {
+        ProcessInstanceModificationBuilder::new(proc_inst_id)
+    }
+
+    /// Creates the builder of the plan which migrates the running instances to another process definition.
+    pub fn create_migration_plan(&self, source_procdef_id: &str, target_procdef_id: &str) -> MigrationPlanBuilder {
+        MigrationPlanBuilder::new(source_procdef_id, target_procdef_id)
+    }
+
+    /// Aborts the running process instance: the runtime data is removed, and the history is
+    /// closed with the reason and the user.
+    pub async fn delete_process_instance(&self, proc_inst_id: &str, reason: Option<String>, user_id: Option<String>) -> Result<()> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let mut operator_ctx = OperatorContext::new(None, user_id, HashMap::new());
+        self._delete_process_instance(proc_inst_id, reason, &mut operator_ctx, &tran).await?;
+
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub(crate) async fn _delete_process_instance(
+        &self, 
+        proc_inst_id: &str, 
+        reason: Option<String>, 
+        operator_ctx: &mut OperatorContext, 
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let exec_dao = ApfRuExecutionDao::new(tran);
+        let proc_inst = exec_dao.get_by_id(proc_inst_id).await?;
+        if proc_inst.proc_inst_id.as_deref() != Some(proc_inst_id) {
+            Err(
+                AppError::new(
+                    ErrorCode::NotFound, 
+                    Some(&format!("process instance({}) is not exist", proc_inst_id)), 
+                    concat!(file!(), ":", line!()), 
+                    None
+                )
+            )?
+        }
+
+        // the tasks are deleted with the delete listeners, and removed from the inboxes
+        if operator_ctx.bpmn_process.is_none() {
+            let re_def = ApfReProcdefDao::new(tran).get_by_id(&proc_inst.proc_def_id).await?;
+            let repository_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_repository_service();
+            let bpmn_process = repository_service.load_bpmn_by_deployment(&re_def.deployment_id, tran).await?;
+            operator_ctx.bpmn_process = Some(Arc::new(bpmn_process));
+        }
+        let var_insts = ApfRuVariableDao::new(tran).find_all_by_proc_inst(proc_inst_id).await?;
+        for (key, value) in ApfRuVariable::convert_variables_to_map(&var_insts) {
+            operator_ctx.variables.entry(key).or_insert(value);
+        }
+
+        let proc_inst = Rc::new(proc_inst);
+        let tasks = ApfRuTaskDao::new(tran).find_by_proc_inst(proc_inst_id).await?;
+        for task in tasks.iter() {
+            BaseOperator::fire_listeners_of_task(proc_inst.clone(), ListenerEvent::DELETE, task, operator_ctx, tran).await?;
+            notify_task_completed(task, tran).await?;
+        }
+
+        let end_time = get_now();
+        let user_id = operator_ctx.user_id.clone();
+
+        // close history
+        let hi_act_dao = ApfHiActinstDao::new(tran);
+        hi_act_dao.close_by_proc_inst(proc_inst_id, end_time, user_id.clone()).await?;
+
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        hi_task_dao.close_by_proc_inst(proc_inst_id, end_time, user_id.clone(), reason.clone()).await?;
+
+        let hi_procinst_dao = ApfHiProcinstDao::new(tran);
+        hi_procinst_dao.mark_deleted(proc_inst_id, end_time, user_id.clone(), reason.clone()).await?;
+
+        // delete runtime data
+        ApfRuIncidentDao::new(tran).delete_by_proc_inst_id(proc_inst_id).await?;
+        ApfRuJobDao::new(tran).delete_by_proc_inst_id(proc_inst_id).await?;
+        ApfRuIdentitylinkDao::new(tran).delete_by_proc_inst_id(proc_inst_id).await?;
+        ApfRuTaskDao::new(tran).delete_by_proc_inst_id(proc_inst_id).await?;
+        ApfRuVariableDao::new(tran).delete_by_proc_inst_id(proc_inst_id).await?;
+        exec_dao.delete_by_proc_inst_id(proc_inst_id).await?;
+
+        operator_ctx.emit(EngineEvent::ProcessDeleted {
+            proc_inst_id: proc_inst_id.to_owned(),
+            proc_def_id: proc_inst.proc_def_id.clone(),
+            reason,
+            user_id,
+        })?;
+
+        Ok(())
+    }
+}
+
+/// Refuses to act on the suspended process instance.
+pub fn check_not_suspended(proc_inst: &ApfRuExecution) -> Result<()> {
+    if proc_inst.is_suspended() {
+        Err(
+            AppError::new(
+                ErrorCode::Suspended, 
+                Some(&format!("process instance({}) is suspended", proc_inst.id)), 
+                concat!(file!(), ":", line!()), 
+                None
+            )
+        )?
+    }
+
+    Ok(())
 }
 
 
 #[cfg(test)]
 mod tests {
     use crate::common::db;
+    use crate::service::engine::{ListenerContext, register_listener, TaskService};
+    use crate::service::engine::query::{HistoricProcessInstanceQuery, ProcessInstanceQuery, TaskQuery};
     use crate::service::engine::tests::create_test_deploy;
     use super::*;
 
@@ -114,4 +292,160 @@ mod tests {
         tran.rollback().await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_suspend_process_instance() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_2.bpmn.xml", &tran).await;
+        let rt_service = RuntimeService::new();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        rt_service._update_suspension_state(&procinst.id, SuspensionState::TRUE, &tran).await.unwrap();
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).suspended().fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        // the task of suspended process instance can not be completed
+        let task_service = TaskService::new();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        let rst = task_service._complete(&tasks[0].id, &mut operator_ctx, &tran).await;
+        let err = rst.unwrap_err();
+        assert_eq!(err.downcast_ref::<AppError>().unwrap().code, ErrorCode::Suspended);
+
+        rt_service._update_suspension_state(&procinst.id, SuspensionState::FALSE, &tran).await.unwrap();
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).active().fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_process_instance() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_2.bpmn.xml", &tran).await;
+        let rt_service = RuntimeService::new();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, Some("admin".to_owned()), HashMap::new());
+        rt_service
+            ._delete_process_instance(&procinst.id, Some("canceled by applicant".to_owned()), &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert!(tasks.is_empty());
+        let rst = ApfRuExecutionDao::new(&tran).get_by_id(&procinst.id).await;
+        assert!(rst.is_err());
+
+        let hi_procinst = ApfHiProcinstDao::new(&tran).get_by_id(&procinst.id).await.unwrap();
+        assert!(hi_procinst.end_time.is_some());
+        assert_eq!(hi_procinst.end_user, Some("admin".to_owned()));
+        assert_eq!(hi_procinst.delete_reason, Some("canceled by applicant".to_owned()));
+
+        let hi_task = ApfHiTaskinstDao::new(&tran).get_by_id(&task.id).await.unwrap();
+        assert!(hi_task.end_time.is_some());
+        assert_eq!(hi_task.delete_reason, Some("canceled by applicant".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_listeners() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let deleted = Arc::new(std::sync::Mutex::new(vec![]));
+        let deleted_clone = deleted.clone();
+        register_listener("test_delete_listener", Arc::new(move |ctx: &mut ListenerContext| {
+            deleted_clone.lock().unwrap().push(ctx.task_id.clone().unwrap_or_default());
+            Ok(())
+        }));
+
+        let procdef = create_test_deploy("bpmn/process_delete_listener.bpmn.xml", &tran).await;
+        let rt_service = RuntimeService::new();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, Some("admin".to_owned()), HashMap::new());
+        rt_service
+            ._delete_process_instance(&procinst.id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+        assert!(deleted.lock().unwrap().contains(&task.id));
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_start_by_initiator() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_initiator.bpmn.xml", &tran).await;
+
+        let rt_service = RuntimeService::new();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+        assert_eq!(procinst.start_user, Some("user_1".to_owned()));
+
+        let hi_procinst = ApfHiProcinstDao::new(&tran).get_by_id(&procinst.id).await.unwrap();
+        assert_eq!(hi_procinst.start_user, Some("user_1".to_owned()));
+
+        // the initiator is copied into `applicant`, which the candidate users refer to
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_1".to_owned()))
+            .fetch_one()
+            .await
+            .unwrap();
+        assert_eq!(task.element_id, Some("apply_1".to_owned()));
+
+        let procinsts = ProcessInstanceQuery::new(&tran)
+            .started_by("user_1")
+            .process_definition_key(&procdef.key)
+            .fetch_all()
+            .await
+            .unwrap();
+        assert_eq!(procinsts.len(), 1);
+        assert_eq!(procinsts[0].id, procinst.id);
+
+        let count = ProcessInstanceQuery::new(&tran)
+            .count("t1.id")
+            .started_by("user_2")
+            .process_definition_key(&procdef.key)
+            .fetch_count()
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let hi_procinst = HistoricProcessInstanceQuery::new(&tran)
+            .started_by("user_1")
+            .unfinished()
+            .id(&procinst.id)
+            .fetch_one()
+            .await
+            .unwrap();
+        assert_eq!(hi_procinst.start_element_id, Some("startEvent_1".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+
 }
\ No newline at end of file
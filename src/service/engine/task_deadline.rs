@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use color_eyre::Result;
+
+use crate::error::{AppError, ErrorCode};
+use crate::model::WrappedValue;
+use crate::service::engine::{add_duration, BusinessCalendar, eval_expression};
+
+pub const DEFAULT_PRIORITY: i32 = 50;
+
+const SECOND: i64 = 1000;
+const MINUTE: i64 = 60 * SECOND;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+
+/// An ISO-8601 duration such as `P3D` or `P1DT12H`. The days and the time are kept apart, since
+/// the days may be counted in working days. Years and months are not supported, their length varies.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct IsoDuration {
+    pub days: i64,
+    pub millis: i64,
+}
+
+impl IsoDuration {
+    pub fn parse(s: &str) -> Result<Self> {
+        let invalid = || AppError::new(
+            ErrorCode::InvalidInput,
+            Some(&format!("invalid duration ({})", s)),
+            concat!(file!(), ":", line!()),
+            None
+        );
+
+        let body = s.trim().strip_prefix('P').ok_or_else(invalid)?;
+        let mut rst = Self::default();
+        let mut in_time = false;
+        let mut has_value = false;
+        let mut num = String::new();
+        for c in body.chars() {
+            match c {
+                '0'..='9' | '.' => num.push(c),
+                'T' if !in_time && num.is_empty() => in_time = true,
+                _ => {
+                    let n: f64 = num.parse().map_err(|_| invalid())?;
+                    match (in_time, c) {
+                        (false, 'W') => rst.days += (n * 7.0) as i64,
+                        (false, 'D') => rst.days += n as i64,
+                        (true, 'H') => rst.millis += (n * HOUR as f64) as i64,
+                        (true, 'M') => rst.millis += (n * MINUTE as f64) as i64,
+                        (true, 'S') => rst.millis += (n * SECOND as f64) as i64,
+                        _ => Err(invalid())?,
+                    }
+                    num.clear();
+                    has_value = true;
+                },
+            }
+        }
+
+        if !has_value || !num.is_empty() {
+            Err(invalid())?;
+        }
+
+        Ok(rst)
+    }
+
+    pub fn to_millis(&self) -> i64 {
+        self.days * DAY + self.millis
+    }
+}
+
+pub fn eval_priority(expr: &str, variables: &HashMap<String, WrappedValue>) -> Result<i32> {
+    let invalid = || AppError::new(
+        ErrorCode::InvalidInput,
+        Some(&format!("invalid priority ({})", expr)),
+        concat!(file!(), ":", line!()),
+        None
+    );
+
+    let rst = match eval_expression(expr, variables)? {
+        Some(WrappedValue::Int(v)) => v,
+        Some(WrappedValue::Double(v)) => v as i32,
+        Some(WrappedValue::Str(v)) => v.trim().parse::<i32>().map_err(|_| invalid())?,
+        _ => Err(invalid())?,
+    };
+
+    Ok(rst)
+}
+
+/// Evaluates a date attribute to the timestamp in milliseconds. The value can be a timestamp, an
+/// ISO-8601 date time, or an ISO-8601 duration which starts from `now` and is counted by the calendar.
+pub fn eval_date(
+    expr: &str,
+    variables: &HashMap<String, WrappedValue>,
+    now: i64,
+    calendar: Option<&Arc<dyn BusinessCalendar>>
+) -> Result<Option<i64>> {
+    let rst = match eval_expression(expr, variables)? {
+        None => None,
+        Some(WrappedValue::Int(v)) => Some(v as i64),
+        Some(WrappedValue::Double(v)) => Some(v as i64),
+        Some(WrappedValue::Str(v)) => parse_date(&v, now, calendar)?,
+        Some(WrappedValue::Bool(_)) => Err(
+            AppError::new(
+                ErrorCode::InvalidInput,
+                Some(&format!("invalid date ({})", expr)),
+                concat!(file!(), ":", line!()),
+                None
+            )
+        )?,
+    };
+
+    Ok(rst)
+}
+
+pub fn parse_date(s: &str, now: i64, calendar: Option<&Arc<dyn BusinessCalendar>>) -> Result<Option<i64>> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Ok(None);
+    }
+
+    if s.starts_with('P') {
+        return Ok(Some(add_duration(now, &IsoDuration::parse(s)?, calendar)));
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(Some(dt.timestamp_millis()));
+    }
+
+    let local = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok().and_then(|d| d.and_hms_opt(0, 0, 0)))
+        .and_then(|ndt| Local.from_local_datetime(&ndt).single());
+
+    match local {
+        Some(dt) => Ok(Some(dt.timestamp_millis())),
+        None => Err(
+            AppError::new(
+                ErrorCode::InvalidInput,
+                Some(&format!("invalid date ({})", s)),
+                concat!(file!(), ":", line!()),
+                None
+            )
+        )?,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(IsoDuration::parse("P3D").unwrap(), IsoDuration { days: 3, millis: 0 });
+        assert_eq!(IsoDuration::parse("P1W").unwrap().days, 7);
+        assert_eq!(IsoDuration::parse("P1DT1H30M").unwrap(), IsoDuration { days: 1, millis: 90 * MINUTE });
+        assert_eq!(IsoDuration::parse("PT0.5S").unwrap().to_millis(), 500);
+        assert!(IsoDuration::parse("P1M").is_err());
+        assert!(IsoDuration::parse("P").is_err());
+        assert!(IsoDuration::parse("3D").is_err());
+    }
+
+    #[test]
+    fn test_eval_date() {
+        let mut variables = HashMap::new();
+        variables.insert("urgent".to_owned(), WrappedValue::Bool(true));
+
+        let now = 1_000_000;
+        assert_eq!(eval_date("P1D", &variables, now, None).unwrap(), Some(now + DAY));
+        assert_eq!(eval_date("${urgent ? 'PT2H' : 'P3D'}", &variables, now, None).unwrap(), Some(now + 2 * HOUR));
+        assert_eq!(eval_date("2026-10-19T08:00:00Z", &variables, now, None).unwrap(), Some(1792396800000));
+        assert_eq!(eval_priority("${urgent ? 90 : 50}", &variables).unwrap(), 90);
+        assert!(eval_date("tomorrow", &variables, now, None).is_err());
+    }
+}
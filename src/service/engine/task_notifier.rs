@@ -0,0 +1,255 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use color_eyre::Result;
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use futures::{Stream, StreamExt};
+use log4rs_macros::error;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::{AsyncMessage, Client, NoTls, Transaction};
+
+use crate::common::global_cfg;
+use crate::dao::{ApfReProcdefDao, ApfRuIdentitylinkDao};
+use crate::error::{AppError, ErrorCode};
+use crate::model::ApfRuTask;
+
+pub const DEFAULT_TASK_NOTIFY_CHANNEL: &'static str = "apf_task_event";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TaskNotifyEvent {
+    Created,
+    Completed,
+    Reminded,
+    Escalated,
+}
+
+/// The payload sent by `pg_notify` when a task is created or completed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskNotification {
+    pub event: TaskNotifyEvent,
+    pub task_id: String,
+    pub proc_inst_id: String,
+    pub element_id: Option<String>,
+    pub company_id: String,
+    pub candidate_users: Vec<String>,
+    pub candidate_groups: Vec<String>,
+}
+
+#[cfg(test)]
+thread_local! {
+    // turns on the notifications for the test running in the current thread
+    static TASK_NOTIFY_IN_TEST: std::cell::Cell<bool> = std::cell::Cell::new(false);
+}
+
+pub fn task_notify_enabled() -> bool {
+    #[cfg(test)]
+    if TASK_NOTIFY_IN_TEST.with(|enabled| enabled.get()) {
+        return true;
+    }
+
+    global_cfg().engine
+        .as_ref()
+        .and_then(|engine| engine.task_notify)
+        .unwrap_or(false)
+}
+
+pub fn task_notify_channel() -> String {
+    global_cfg().engine
+        .as_ref()
+        .and_then(|engine| engine.task_notify_channel.clone())
+        .unwrap_or(DEFAULT_TASK_NOTIFY_CHANNEL.to_owned())
+}
+
+/// Sends the notification of the task if `engine.task_notify` is enabled. The notification is 
+/// delivered by postgres only when the transaction is committed.
+pub async fn notify_task(
+    event: TaskNotifyEvent, 
+    task: &ApfRuTask, 
+    candidate_users: Vec<String>, 
+    candidate_groups: Vec<String>, 
+    tran: &Transaction<'_>
+) -> Result<()> {
+    if !task_notify_enabled() {
+        return Ok(());
+    }
+
+    let procdef_dao = ApfReProcdefDao::new(tran);
+    let procdef = procdef_dao.get_by_id(&task.proc_def_id).await?;
+
+    let notification = TaskNotification {
+        event,
+        task_id: task.id.clone(),
+        proc_inst_id: task.proc_inst_id.clone(),
+        element_id: task.element_id.clone(),
+        company_id: procdef.company_id,
+        candidate_users,
+        candidate_groups,
+    };
+    let payload = serde_json::to_string(&notification)?;
+
+    let sql = r#"select pg_notify($1, $2)"#;
+    let stmt = tran.prepare(sql).await?;
+    tran.execute(&stmt, &[&task_notify_channel(), &payload]).await?;
+
+    Ok(())
+}
+
+/// Sends the completed notification, the candidates are read before the identity links are deleted.
+pub async fn notify_task_completed(task: &ApfRuTask, tran: &Transaction<'_>) -> Result<()> {
+    notify_task_candidates(TaskNotifyEvent::Completed, task, tran).await
+}
+
+/// Sends the notification to the current candidates of the task.
+pub async fn notify_task_candidates(event: TaskNotifyEvent, task: &ApfRuTask, tran: &Transaction<'_>) -> Result<()> {
+    if !task_notify_enabled() {
+        return Ok(());
+    }
+
+    let ru_ident_dao = ApfRuIdentitylinkDao::new(tran);
+    let ident_links = ru_ident_dao.find_by_task_id(&task.id).await?;
+    let candidate_users = ident_links.iter().filter_map(|link| link.user_id.clone()).collect();
+    let candidate_groups = ident_links.iter().filter_map(|link| link.group_id.clone()).collect();
+
+    notify_task(event, task, candidate_users, candidate_groups, tran).await
+}
+
+/// The stream of task notifications. It owns the dedicated connection which is listening on the
+/// channel, the connection is closed when the stream is dropped.
+pub struct TaskEventStream {
+    _client: Client,
+    receiver: UnboundedReceiver<TaskNotification>,
+}
+
+impl Stream for TaskEventStream {
+    type Item = TaskNotification;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+pub async fn subscribe_task_notifications() -> Result<TaskEventStream> {
+    let channel = task_notify_channel();
+    if !channel.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Err(AppError::new(
+            ErrorCode::InvalidInput, 
+            Some(&format!("task notify channel ({}) is invalid", channel)), 
+            concat!(file!(), ":", line!()), 
+            None
+        ))?
+    }
+
+    let db = &global_cfg().database;
+    let mut pg_config = tokio_postgres::Config::new();
+    if let Some(host) = &db.host {
+        pg_config.host(host);
+    }
+    if let Some(port) = db.port {
+        pg_config.port(port);
+    }
+    if let Some(user) = &db.user {
+        pg_config.user(user);
+    }
+    if let Some(password) = &db.password {
+        pg_config.password(password);
+    }
+    if let Some(dbname) = &db.dbname {
+        pg_config.dbname(dbname);
+    }
+
+    let (client, mut connection) = pg_config.connect(NoTls).await?;
+    let (sender, receiver) = mpsc::unbounded();
+
+    tokio::spawn(async move {
+        let mut messages = futures::stream::poll_fn(move |cx| connection.poll_message(cx));
+
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(AsyncMessage::Notification(n)) => {
+                    match serde_json::from_str::<TaskNotification>(n.payload()) {
+                        Ok(notification) => {
+                            if sender.unbounded_send(notification).is_err() {
+                                break;
+                            }
+                        },
+                        Err(e) => error!("task notification ({}) is invalid: {:?}", n.payload(), e),
+                    }
+                },
+                Ok(_) => {},
+                Err(e) => {
+                    error!("task notify connection is broken: {:?}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    client.batch_execute(&format!("LISTEN {}", channel)).await?;
+
+    Ok(TaskEventStream { _client: client, receiver })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use crate::common::db;
+    use crate::service::engine::{OperatorContext, ProcessEngine, TaskService};
+    use crate::service::engine::query::TaskQuery;
+    use crate::service::engine::tests::{create_test_deploy, delete_test_deploy};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribe_task_notifications() {
+        TASK_NOTIFY_IN_TEST.with(|enabled| enabled.set(true));
+        let mut stream = subscribe_task_notifications().await.unwrap();
+
+        // the notifications are sent only when the transaction is committed
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+        let procdef = create_test_deploy("bpmn/process_reject.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+        tran.commit().await.unwrap();
+
+        let tran = conn.transaction().await.unwrap();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        TaskService::new()._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        tran.commit().await.unwrap();
+
+        // the other tests may notify at the same time
+        let mut notifications = vec![];
+        while notifications.len() < 3 {
+            match tokio::time::timeout(Duration::from_secs(5), stream.next()).await {
+                Ok(Some(n)) if n.proc_inst_id == procinst.id => notifications.push(n),
+                Ok(Some(_)) => {},
+                _ => break,
+            }
+        }
+
+        let tran = conn.transaction().await.unwrap();
+        delete_test_deploy(&procdef.deployment_id, &tran).await;
+        tran.commit().await.unwrap();
+
+        assert_eq!(notifications.len(), 3);
+        assert_eq!(notifications[0].event, TaskNotifyEvent::Created);
+        assert_eq!(notifications[0].task_id, task.id);
+        assert_eq!(notifications[0].element_id, Some("apply_1".to_owned()));
+        assert_eq!(notifications[0].company_id, procdef.company_id);
+        assert_eq!(notifications[0].candidate_users, vec!["user_1".to_owned()]);
+
+        assert_eq!(notifications[1].event, TaskNotifyEvent::Completed);
+        assert_eq!(notifications[1].task_id, task.id);
+        assert_eq!(notifications[1].candidate_users, vec!["user_1".to_owned()]);
+
+        assert_eq!(notifications[2].event, TaskNotifyEvent::Created);
+        assert_eq!(notifications[2].element_id, Some("approval_1".to_owned()));
+        assert_eq!(notifications[2].candidate_users, vec!["user_2".to_owned()]);
+        assert!(notifications[2].candidate_groups.is_empty());
+    }
+}
@@ -8,10 +8,21 @@ use tokio_postgres::Transaction;
 
 use crate::get_now;
 use crate::common::db;
-use crate::dao::{ApfHiVarinstDao, ApfReProcdefDao, ApfRuExecutionDao, ApfRuTaskDao, ApfRuVariableDao};
-use crate::error::AppError;
-use crate::service::engine::{CompleteTaskCmd, Operator, OperatorContext, OperatorExecutor, ProcessEngine};
-use crate::model::{ApfRuVariable, ApfRuVariableDto, WrappedValue};
+use crate::dao::{
+    ApfGeBytearrayDao, ApfHiAttachmentDao, ApfHiCommentDao, ApfHiIdentitylinkDao, ApfHiOplogDao, ApfHiTaskinstDao,
+    ApfHiVarinstDao, ApfReProcdefDao, ApfRuExecutionDao, ApfRuIdentitylinkDao, ApfRuTaskDao, ApfRuVariableDao
+};
+use crate::error::{AppError, ErrorCode};
+use crate::service::engine::{
+    BaseOperator, BpmnProcess, check_not_suspended, check_sub_tasks_done, CompleteTaskCmd, dispatch_after_commit, EngineEvent, write_outbox,
+    ListenerEvent, ListenerType, notify_task, Operator, OperatorContext, OperatorExecutor, ProcessEngine, RejectTaskCmd,
+    TaskNotifyEvent, WithdrawTaskCmd
+};
+use crate::model::{
+    AddSignMode, ApfHiAttachment, ApfHiComment, ApfRuTask, ApfRuVariable, ApfRuVariableDto, AttachmentContent, CommentType,
+    DelegationState, IdentType, NewApfGeBytearray, NewApfHiAttachment, NewApfHiComment, NewApfHiOplog, NewApfRuIdentitylink,
+    NewApfRuTask, OpType, WrappedValue
+};
 
 #[derive(Debug)]
 pub struct TaskService {
@@ -35,8 +46,11 @@ impl TaskService {
 
         let mut operator_ctx = OperatorContext::new(group_id, user_id, variables);
         self._complete(task_id, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
         tran.commit().await?;
 
+        dispatch_after_commit(operator_ctx.take_events());
+
         Ok(())
     }
 
@@ -44,41 +58,24 @@ impl TaskService {
         let task_dao = ApfRuTaskDao::new(tran);
         let current_task = task_dao.get_by_id(task_id).await?;
 
-        let procdef_dao = ApfReProcdefDao::new(tran);
-        let re_def = procdef_dao.get_by_id(&current_task.proc_def_id).await?;
-        let repository_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_repository_service();
-        let bpmn_process = repository_service.load_bpmn_by_deployment(&re_def.deployment_id, tran).await?;
-        let bpmn_process = Arc::new(bpmn_process);
+        let execution_dao = ApfRuExecutionDao::new(tran);
+        let proc_inst = execution_dao.get_by_id(&current_task.proc_inst_id).await?;
+        check_not_suspended(&proc_inst)?;
+
+        // the sub-task does not move the process instance
+        if current_task.parent_task_id.is_some() {
+            return self.complete_sub_task(current_task, operator_ctx, tran).await;
+        }
+
+        let bpmn_process = self.load_bpmn_process(&current_task, tran).await?;
         let element = bpmn_process.element_map.get(&current_task.element_id_ex()?).ok_or(
             AppError::notfound_error(concat!(file!(), ":", line!())))?;
 
         operator_ctx.bpmn_process = Some(bpmn_process.clone());
 
-        // merge variables
-        let var_dao = ApfRuVariableDao::new(tran);
-        let hi_var_dao = ApfHiVarinstDao::new(tran);
-        let update_time = get_now();
-        for (key, value) in operator_ctx.variables.iter() {
-            let dto = ApfRuVariableDto {
-                var_type: value.get_type(),
-                name: key.to_owned(),
-                value: value.as_str(),
-                proc_inst_id: current_task.proc_inst_id.clone(),
-                execution_id: Some(current_task.execution_id.clone()),
-                task_id: Some(current_task.id.clone()),
-            };
-            let variable = var_dao.create_or_update(&dto).await?;
-            hi_var_dao.create_or_update_by_variable(&variable, update_time).await?;
-        }
-
-        let var_insts = var_dao.find_all_by_proc_inst(&current_task.proc_inst_id).await?;
-        let vars_map = ApfRuVariable::convert_variables_to_map(&var_insts);
-
-        operator_ctx.variables = vars_map;
+        self.merge_variables(&current_task, operator_ctx, tran).await?;
 
         // continue to handle operator
-        let execution_dao = ApfRuExecutionDao::new(tran);
-        let proc_inst = execution_dao.get_by_id(&current_task.proc_inst_id).await?;
         let current_execution = execution_dao.get_by_id(&current_task.execution_id).await?;
 
         let complete_task_cmd = CompleteTaskCmd::new(
@@ -93,51 +90,1824 @@ impl TaskService {
 
         Ok(())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::service::engine::query::TaskQuery;
-    use crate::service::engine::tests::create_test_deploy;
-    use super::*;
-
-    #[tokio::test]
-    async fn test_complete() {
-        log4rs_macros::prepare_log();
 
+    /// Rejects the task back to a user task which has been completed before, the target task is
+    /// created again with its original candidates.
+    pub async fn reject(
+        &self,
+        task_id: &str,
+        target_element_id: &str,
+        comment: Option<String>,
+        user_id: Option<String>,
+        group_id: Option<String>
+    ) -> Result<()> {
         let mut conn = db::get_connect().await.unwrap();
         let tran = conn.transaction().await.unwrap();
 
-        let procdef = create_test_deploy("bpmn/process_2.bpmn.xml", &tran).await;
-        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
-        let mut operator_ctx = OperatorContext::default();
-        let procinst = rt_service
-            ._start_process_instance_by_key(
-                &procdef.key,
-                &procdef.company_id,
-                Some("process_biz_key_2".to_owned()),
-                &mut operator_ctx,
-                &tran
+        let mut operator_ctx = OperatorContext::new(group_id, user_id, HashMap::new());
+        self._reject(task_id, target_element_id, comment, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub async fn _reject(
+        &self,
+        task_id: &str,
+        target_element_id: &str,
+        comment: Option<String>,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let task_dao = ApfRuTaskDao::new(tran);
+        let current_task = task_dao.get_by_id(task_id).await?;
+
+        let execution_dao = ApfRuExecutionDao::new(tran);
+        let proc_inst = execution_dao.get_by_id(&current_task.proc_inst_id).await?;
+        check_not_suspended(&proc_inst)?;
+
+        let bpmn_process = self.load_bpmn_process(&current_task, tran).await?;
+        let target_element = bpmn_process.element_map.get(target_element_id).ok_or(
+            AppError::new(
+                ErrorCode::NotFound,
+                Some(&format!("element ({}) is not found", target_element_id)),
+                concat!(file!(), ":", line!()),
+                None
             )
-            .await
-            .unwrap();
+        )?;
 
-        let task = TaskQuery::new(&tran)
-            .proc_inst_id(&procinst.id)
-            .candidate_user(Some("user_1".to_owned()))
-            .fetch_one()
-            .await.unwrap();
+        operator_ctx.bpmn_process = Some(bpmn_process.clone());
 
-        let mut variables = HashMap::new();
-        variables.insert("approval_pass".to_owned(), WrappedValue::Bool(false));
-        
-        let mut operator_ctx = OperatorContext::new(
-            None,
-            Some("user_1".to_owned()),
-            variables
+        let var_dao = ApfRuVariableDao::new(tran);
+        let var_insts = var_dao.find_all_by_proc_inst(&current_task.proc_inst_id).await?;
+        operator_ctx.variables = ApfRuVariable::convert_variables_to_map(&var_insts);
+
+        let current_execution = execution_dao.get_by_id(&current_task.execution_id).await?;
+
+        let reject_task_cmd = RejectTaskCmd::new(
+            target_element.clone(),
+            Rc::new(proc_inst),
+            Some(Rc::new(RefCell::new(current_execution))),
+            Some(Rc::new(current_task)),
+            comment
         );
-        let task_service = TaskService::new();
-        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        let mut operator_exec = OperatorExecutor::new();
+        operator_exec.execute(Operator::RejectTaskCmd(reject_task_cmd), operator_ctx, tran).await?;
+
+        Ok(())
+    }
+
+    /// Withdraws the completion of the task, the tasks created by the completion must not have been handled.
+    pub async fn withdraw(&self, hi_task_id: &str, user_id: Option<String>) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, user_id, HashMap::new());
+        self._withdraw(hi_task_id, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub async fn _withdraw(&self, hi_task_id: &str, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        let hi_task = hi_task_dao.get_by_id(hi_task_id).await?;
+
+        let execution_dao = ApfRuExecutionDao::new(tran);
+        let proc_inst = execution_dao.get_by_id(&hi_task.proc_inst_id).await?;
+        check_not_suspended(&proc_inst)?;
+        let proc_inst_id = proc_inst.id.clone();
+
+        let procdef_dao = ApfReProcdefDao::new(tran);
+        let re_def = procdef_dao.get_by_id(&hi_task.proc_def_id).await?;
+        let bpmn_process = self.load_bpmn_by_deployment(&re_def.deployment_id, tran).await?;
+        let element_id = hi_task.element_id.clone().unwrap_or_default();
+        let element = bpmn_process.element_map.get(&element_id).ok_or(
+            AppError::notfound_error(concat!(file!(), ":", line!())))?;
+
+        operator_ctx.bpmn_process = Some(bpmn_process.clone());
+
+        let var_dao = ApfRuVariableDao::new(tran);
+        let var_insts = var_dao.find_all_by_proc_inst(&hi_task.proc_inst_id).await?;
+        operator_ctx.variables = ApfRuVariable::convert_variables_to_map(&var_insts);
+
+        let end_user_id = hi_task.end_user_id.clone();
+        let withdraw_task_cmd = WithdrawTaskCmd::new(element.clone(), Rc::new(proc_inst), hi_task);
+
+        let mut operator_exec = OperatorExecutor::new();
+        operator_exec.execute(Operator::WithdrawTaskCmd(withdraw_task_cmd), operator_ctx, tran).await?;
+
+        // the task is reopened for the one who withdrew it, not for the other candidates
+        let task_dao = ApfRuTaskDao::new(tran);
+        let tasks = task_dao.find_by_proc_inst(&proc_inst_id).await?;
+        for task in tasks.iter().filter(|t| t.element_id.as_ref() == Some(&element_id) && t.parent_task_id.is_none()) {
+            if task.assignee != end_user_id {
+                self.assign(task, end_user_id.clone(), operator_ctx, tran).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Takes the task for the current user, the task disappears from the inboxes of the other candidates.
+    pub async fn claim(&self, task_id: &str, user_id: Option<String>, group_id: Option<String>) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(group_id, user_id, HashMap::new());
+        self._claim(task_id, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub async fn _claim(&self, task_id: &str, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let user_id = operator_ctx.user_id.clone().ok_or(
+            AppError::new(ErrorCode::InvalidInput, Some("user is required to claim a task"), concat!(file!(), ":", line!()), None)
+        )?;
+
+        let task = self.get_active_task(task_id, tran).await?;
+        if let Some(assignee) = &task.assignee {
+            if *assignee == user_id {
+                return Ok(());
+            }
+
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("task ({}) has been claimed by ({})", task.id, assignee)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        self.check_candidate(&task, operator_ctx, tran).await?;
+
+        // another candidate may have claimed it since it was read
+        let task_dao = ApfRuTaskDao::new(tran);
+        let r = task_dao.claim(&task.id, &user_id).await?;
+        if r != 1 {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("task ({}) has been claimed by another user", task.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        self.assigned(&task, Some(user_id), operator_ctx, tran).await
+    }
+
+    /// Gives the claimed task back to its candidates.
+    pub async fn unclaim(&self, task_id: &str, user_id: Option<String>) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, user_id, HashMap::new());
+        self._unclaim(task_id, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub async fn _unclaim(&self, task_id: &str, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let task = self.get_active_task(task_id, tran).await?;
+        if task.assignee.is_none() {
+            return Ok(());
+        }
+
+        self.check_not_delegated(&task)?;
+
+        self.check_assignee(&task, operator_ctx)?;
+        self.assign(&task, None, operator_ctx, tran).await
+    }
+
+    /// Hands the task over to another user, only the assignee can hand over a claimed task.
+    /// The system call, which has no user, is always allowed.
+    pub async fn set_assignee(&self, task_id: &str, assignee: Option<String>, user_id: Option<String>) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, user_id, HashMap::new());
+        self._set_assignee(task_id, assignee, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub async fn _set_assignee(
+        &self,
+        task_id: &str,
+        assignee: Option<String>,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let task = self.get_active_task(task_id, tran).await?;
+        self.check_not_delegated(&task)?;
+        self.check_task_handler(&task, operator_ctx, tran).await?;
+
+        self.assign(&task, assignee, operator_ctx, tran).await
+    }
+
+    /// Lets another user work on the task, the task is returned to the owner when the delegate resolves it.
+    pub async fn delegate(&self, task_id: &str, to_user: &str, user_id: Option<String>, group_id: Option<String>) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(group_id, user_id, HashMap::new());
+        self._delegate(task_id, to_user, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub async fn _delegate(&self, task_id: &str, to_user: &str, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let task = self.get_active_task(task_id, tran).await?;
+        self.check_not_delegated(&task)?;
+
+        // the one who delegates the task keeps the accountability
+        let owner = if task.assignee.is_some() {
+            self.check_assignee(&task, operator_ctx)?;
+            task.assignee.clone()
+        } else {
+            if operator_ctx.user_id.is_some() {
+                self.check_candidate(&task, operator_ctx, tran).await?;
+            }
+            operator_ctx.user_id.clone()
+        };
+
+        let delegation = Some(DelegationState::PENDING.to_owned());
+        let task_dao = ApfRuTaskDao::new(tran);
+        task_dao.update_delegation(&task.id, owner.clone(), Some(to_user.to_owned()), delegation.clone()).await?;
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        hi_task_dao.update_delegation(&task.id, owner.clone(), Some(to_user.to_owned()), delegation).await?;
+
+        let detail = serde_json::json!({
+            "owner": owner,
+            "assignee": to_user,
+        });
+        self.create_task_oplog(&task, OpType::DELEGATE_TASK, detail.to_string(), operator_ctx, tran).await?;
+
+        operator_ctx.emit(EngineEvent::TaskDelegated {
+            proc_inst_id: task.proc_inst_id.clone(),
+            task_id: task.id.clone(),
+            owner,
+            assignee: to_user.to_owned(),
+        })?;
+
+        self.fire_task_listeners(ListenerEvent::ASSIGNMENT, &task, operator_ctx, tran).await
+    }
+
+    /// Returns the delegated task to its owner with the variables, the task is not completed.
+    pub async fn resolve(
+        &self,
+        task_id: &str,
+        variables: HashMap<String, WrappedValue>,
+        user_id: Option<String>
+    ) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, user_id, variables);
+        self._resolve(task_id, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(())
+    }
+
+    pub async fn _resolve(&self, task_id: &str, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let task = self.get_active_task(task_id, tran).await?;
+        if !task.is_delegation_pending() {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("task ({}) is not delegated", task.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        self.check_assignee(&task, operator_ctx)?;
+        self.merge_variables(&task, operator_ctx, tran).await?;
+
+        let delegation = Some(DelegationState::RESOLVED.to_owned());
+        let task_dao = ApfRuTaskDao::new(tran);
+        task_dao.update_delegation(&task.id, task.owner.clone(), task.owner.clone(), delegation.clone()).await?;
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        hi_task_dao.update_delegation(&task.id, task.owner.clone(), task.owner.clone(), delegation).await?;
+
+        let detail = serde_json::json!({
+            "owner": task.owner,
+            "assignee": task.assignee,
+        });
+        self.create_task_oplog(&task, OpType::RESOLVE_TASK, detail.to_string(), operator_ctx, tran).await?;
+
+        operator_ctx.emit(EngineEvent::TaskResolved {
+            proc_inst_id: task.proc_inst_id.clone(),
+            task_id: task.id.clone(),
+            owner: task.owner.clone(),
+            user_id: operator_ctx.user_id.clone(),
+        })?;
+
+        self.fire_task_listeners(ListenerEvent::ASSIGNMENT, &task, operator_ctx, tran).await
+    }
+
+    /// Adds signers (加签) to the task, every signer gets a task on the same execution. The element
+    /// is left after the task and all the added tasks are completed.
+    pub async fn add_sign(
+        &self,
+        task_id: &str,
+        users: Vec<String>,
+        mode: AddSignMode,
+        user_id: Option<String>,
+        group_id: Option<String>
+    ) -> Result<Vec<ApfRuTask>> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(group_id, user_id, HashMap::new());
+        let rst = self._add_sign(task_id, users, mode, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(rst)
+    }
+
+    pub async fn _add_sign(
+        &self,
+        task_id: &str,
+        users: Vec<String>,
+        mode: AddSignMode,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<Vec<ApfRuTask>> {
+        if users.is_empty() {
+            Err(AppError::new(ErrorCode::InvalidInput, Some("no signer is given"), concat!(file!(), ":", line!()), None))?;
+        }
+
+        let task = self.get_active_task(task_id, tran).await?;
+        self.check_not_delegated(&task)?;
+        if task.sign_parent_id.is_some() || task.parent_task_id.is_some() {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("task ({}) is a sub-task or added by signing, no signer can be added to it", task.id)),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        self.check_task_handler(&task, operator_ctx, tran).await?;
+
+        let task_dao = ApfRuTaskDao::new(tran);
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        let ru_ident_dao = ApfRuIdentitylinkDao::new(tran);
+        let hi_ident_dao = ApfHiIdentitylinkDao::new(tran);
+        let mut rst = vec![];
+        for user in users.iter() {
+            let new_ru_task = NewApfRuTask {
+                rev: 1,
+                suspension_state: task.suspension_state,
+                create_time: Some(get_now()),
+                execution_id: task.execution_id.clone(),
+                proc_inst_id: task.proc_inst_id.clone(),
+                proc_def_id: task.proc_def_id.clone(),
+                element_id: task.element_id.clone(),
+                element_name: task.element_name.clone(),
+                element_type: task.element_type.clone(),
+                business_key: task.business_key.clone(),
+                description: task.description.clone(),
+                start_user_id: operator_ctx.user_id.clone(),
+                form_key: task.form_key.clone(),
+                assignee: Some(user.to_owned()),
+                owner: None,
+                delegation: None,
+                sign_parent_id: Some(task.id.clone()),
+                sign_mode: Some(mode.name()),
+                parent_task_id: None,
+                block_parent: false,
+                priority: task.priority,
+                due_date: task.due_date,
+                follow_up_date: task.follow_up_date,
+                prev_task_id: None,
+                next_sweep_time: task.next_sweep_time,
+            };
+            let sign_task = task_dao.create(&new_ru_task).await?;
+            hi_task_dao.create_from_task(&sign_task).await?;
+
+            let new_ru_ident = NewApfRuIdentitylink {
+                ident_type: IdentType::user,
+                group_id: None,
+                user_id: Some(user.to_owned()),
+                task_id: Some(sign_task.id.clone()),
+                proc_inst_id: Some(sign_task.proc_inst_id.clone()),
+                proc_def_id: Some(sign_task.proc_def_id.clone()),
+            };
+            let ru_ident = ru_ident_dao.create(&new_ru_ident).await?;
+            hi_ident_dao.create_from_ident_link(&ru_ident).await?;
+
+            operator_ctx.emit(EngineEvent::TaskCreated {
+                proc_inst_id: sign_task.proc_inst_id.clone(),
+                task_id: sign_task.id.clone(),
+                element_id: sign_task.element_id_ex()?,
+            })?;
+            operator_ctx.emit(EngineEvent::TaskAssigned {
+                proc_inst_id: sign_task.proc_inst_id.clone(),
+                task_id: sign_task.id.clone(),
+                assignee: sign_task.assignee.clone(),
+            })?;
+            notify_task(TaskNotifyEvent::Created, &sign_task, vec![user.to_owned()], vec![], tran).await?;
+
+            rst.push(sign_task);
+        }
+
+        let detail = serde_json::json!({
+            "mode": mode.name(),
+            "users": users,
+        });
+        self.create_task_oplog(&task, OpType::ADD_SIGN, detail.to_string(), operator_ctx, tran).await?;
+
+        Ok(rst)
+    }
+
+    /// Leaves an opinion on the task, the comments are kept after the task is completed.
+    pub async fn add_comment(&self, task_id: &str, message: &str, user_id: Option<String>) -> Result<ApfHiComment> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let rst = self._add_comment(task_id, message, user_id, &tran).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    pub async fn _add_comment(
+        &self,
+        task_id: &str,
+        message: &str,
+        user_id: Option<String>,
+        tran: &Transaction<'_>
+    ) -> Result<ApfHiComment> {
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        let hi_task = hi_task_dao.get_by_id(task_id).await?;
+
+        let comment_dao = ApfHiCommentDao::new(tran);
+        let rst = comment_dao.create(&NewApfHiComment {
+            comment_type: CommentType::COMMENT.to_owned(),
+            user_id,
+            task_id: Some(hi_task.id),
+            proc_inst_id: Some(hi_task.proc_inst_id),
+            message: Some(message.to_owned()),
+            create_time: get_now(),
+        }).await?;
+
+        Ok(rst)
+    }
+
+    pub async fn get_task_comments(&self, task_id: &str) -> Result<Vec<ApfHiComment>> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let comment_dao = ApfHiCommentDao::new(&tran);
+        let rst = comment_dao.find_by_task(task_id).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    /// The opinion trail of the process instance, ordered by time.
+    pub async fn get_process_instance_comments(&self, proc_inst_id: &str) -> Result<Vec<ApfHiComment>> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let comment_dao = ApfHiCommentDao::new(&tran);
+        let rst = comment_dao.find_by_proc_inst(proc_inst_id).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    /// Attaches a file to the task, the bytes are stored in `apf_ge_bytearray`.
+    pub async fn create_attachment(
+        &self,
+        task_id: &str,
+        name: &str,
+        attachment_type: Option<String>,
+        content: AttachmentContent,
+        user_id: Option<String>
+    ) -> Result<ApfHiAttachment> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let rst = self._create_attachment(task_id, name, attachment_type, content, user_id, &tran).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    pub async fn _create_attachment(
+        &self,
+        task_id: &str,
+        name: &str,
+        attachment_type: Option<String>,
+        content: AttachmentContent,
+        user_id: Option<String>,
+        tran: &Transaction<'_>
+    ) -> Result<ApfHiAttachment> {
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        let hi_task = hi_task_dao.get_by_id(task_id).await?;
+
+        let (url, content_id) = match content {
+            AttachmentContent::Url(url) => (Some(url), None),
+            AttachmentContent::Bytes(bytes) => {
+                let bytearray_dao = ApfGeBytearrayDao::new(tran);
+                let bytearray = bytearray_dao.create(&NewApfGeBytearray {
+                    name: Some(name.to_owned()),
+                    deployment_id: None,
+                    bytes: Some(bytes),
+                }).await?;
+                (None, Some(bytearray.id))
+            },
+        };
+
+        let attachment_dao = ApfHiAttachmentDao::new(tran);
+        let rst = attachment_dao.create(&NewApfHiAttachment {
+            name: Some(name.to_owned()),
+            description: None,
+            attachment_type,
+            user_id,
+            task_id: Some(hi_task.id),
+            proc_inst_id: Some(hi_task.proc_inst_id),
+            url,
+            content_id,
+            create_time: get_now(),
+        }).await?;
+
+        Ok(rst)
+    }
+
+    pub async fn get_task_attachments(&self, task_id: &str) -> Result<Vec<ApfHiAttachment>> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let attachment_dao = ApfHiAttachmentDao::new(&tran);
+        let rst = attachment_dao.find_by_task(task_id).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    /// The bytes of the attachment, the attachment which is stored by url has no content.
+    pub async fn get_attachment_content(&self, attachment_id: &str) -> Result<Option<Vec<u8>>> {
+        let mut conn = db::get_connect().await?;
+        let tran = conn.transaction().await?;
+
+        let rst = self._get_attachment_content(attachment_id, &tran).await?;
+        tran.commit().await?;
+
+        Ok(rst)
+    }
+
+    pub async fn _get_attachment_content(&self, attachment_id: &str, tran: &Transaction<'_>) -> Result<Option<Vec<u8>>> {
+        let attachment_dao = ApfHiAttachmentDao::new(tran);
+        let attachment = attachment_dao.get_by_id(attachment_id).await?;
+
+        let rst = match &attachment.content_id {
+            Some(content_id) => {
+                let bytearray_dao = ApfGeBytearrayDao::new(tran);
+                bytearray_dao.get_by_id(content_id).await?.bytes
+            },
+            None => None,
+        };
+
+        Ok(rst)
+    }
+
+    /// Creates an ad-hoc sub-task under the task. A blocking sub-task must be done before the parent
+    /// task can be completed.
+    pub async fn create_sub_task(
+        &self,
+        parent_task_id: &str,
+        name: &str,
+        assignee: Option<String>,
+        block_parent: bool,
+        user_id: Option<String>,
+        group_id: Option<String>
+    ) -> Result<ApfRuTask> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(group_id, user_id, HashMap::new());
+        let rst = self._create_sub_task(parent_task_id, name, assignee, block_parent, &mut operator_ctx, &tran).await?;
+        write_outbox(&operator_ctx.events, &tran).await?;
+        tran.commit().await?;
+
+        dispatch_after_commit(operator_ctx.take_events());
+
+        Ok(rst)
+    }
+
+    pub async fn _create_sub_task(
+        &self,
+        parent_task_id: &str,
+        name: &str,
+        assignee: Option<String>,
+        block_parent: bool,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<ApfRuTask> {
+        let parent = self.get_active_task(parent_task_id, tran).await?;
+        self.check_task_handler(&parent, operator_ctx, tran).await?;
+
+        let new_ru_task = NewApfRuTask {
+            rev: 1,
+            suspension_state: parent.suspension_state,
+            create_time: Some(get_now()),
+            execution_id: parent.execution_id.clone(),
+            proc_inst_id: parent.proc_inst_id.clone(),
+            proc_def_id: parent.proc_def_id.clone(),
+            element_id: parent.element_id.clone(),
+            element_name: Some(name.to_owned()),
+            element_type: None,
+            business_key: parent.business_key.clone(),
+            description: None,
+            start_user_id: operator_ctx.user_id.clone(),
+            form_key: None,
+            assignee: assignee.clone(),
+            owner: None,
+            delegation: None,
+            sign_parent_id: None,
+            sign_mode: None,
+            parent_task_id: Some(parent.id.clone()),
+            block_parent,
+            priority: parent.priority,
+            due_date: parent.due_date,
+            follow_up_date: parent.follow_up_date,
+            prev_task_id: None,
+            next_sweep_time: parent.next_sweep_time,
+        };
+        let task_dao = ApfRuTaskDao::new(tran);
+        let sub_task = task_dao.create(&new_ru_task).await?;
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        hi_task_dao.create_from_task(&sub_task).await?;
+
+        operator_ctx.emit(EngineEvent::TaskCreated {
+            proc_inst_id: sub_task.proc_inst_id.clone(),
+            task_id: sub_task.id.clone(),
+            element_id: sub_task.element_id_ex()?,
+        })?;
+        if assignee.is_some() {
+            operator_ctx.emit(EngineEvent::TaskAssigned {
+                proc_inst_id: sub_task.proc_inst_id.clone(),
+                task_id: sub_task.id.clone(),
+                assignee,
+            })?;
+        }
+        let (candidate_users, candidate_groups) = self.sub_task_candidates(&sub_task, &parent, tran).await?;
+        notify_task(TaskNotifyEvent::Created, &sub_task, candidate_users, candidate_groups, tran).await?;
+
+        Ok(sub_task)
+    }
+
+    /// The sub-task is in the inbox of its assignee, or of the ones who handle the parent task.
+    async fn sub_task_candidates(
+        &self,
+        sub_task: &ApfRuTask,
+        parent: &ApfRuTask,
+        tran: &Transaction<'_>
+    ) -> Result<(Vec<String>, Vec<String>)> {
+        if let Some(assignee) = sub_task.assignee.as_ref().or(parent.assignee.as_ref()) {
+            return Ok((vec![assignee.clone()], vec![]));
+        }
+
+        let ru_ident_dao = ApfRuIdentitylinkDao::new(tran);
+        let ident_links = ru_ident_dao.find_by_task_id(&parent.id).await?;
+        let candidate_users = ident_links.iter().filter_map(|link| link.user_id.clone()).collect();
+        let candidate_groups = ident_links.iter().filter_map(|link| link.group_id.clone()).collect();
+
+        Ok((candidate_users, candidate_groups))
+    }
+
+    pub async fn set_priority(&self, task_id: &str, priority: i32, user_id: Option<String>, group_id: Option<String>) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(group_id, user_id, HashMap::new());
+        self._set_priority(task_id, priority, &mut operator_ctx, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn _set_priority(&self, task_id: &str, priority: i32, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let task = self.get_active_task(task_id, tran).await?;
+        self.check_task_handler(&task, operator_ctx, tran).await?;
+
+        ApfRuTaskDao::new(tran).update_priority(&task.id, priority).await?;
+        ApfHiTaskinstDao::new(tran).update_priority(&task.id, priority).await?;
+
+        Ok(())
+    }
+
+    pub async fn set_due_date(&self, task_id: &str, due_date: Option<i64>, user_id: Option<String>, group_id: Option<String>) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(group_id, user_id, HashMap::new());
+        self._set_due_date(task_id, due_date, &mut operator_ctx, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn _set_due_date(&self, task_id: &str, due_date: Option<i64>, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let task = self.get_active_task(task_id, tran).await?;
+        self.check_task_handler(&task, operator_ctx, tran).await?;
+
+        self.update_dates(&task, due_date, task.follow_up_date, tran).await
+    }
+
+    pub async fn set_follow_up_date(
+        &self,
+        task_id: &str,
+        follow_up_date: Option<i64>,
+        user_id: Option<String>,
+        group_id: Option<String>
+    ) -> Result<()> {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(group_id, user_id, HashMap::new());
+        self._set_follow_up_date(task_id, follow_up_date, &mut operator_ctx, &tran).await?;
+        tran.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn _set_follow_up_date(
+        &self,
+        task_id: &str,
+        follow_up_date: Option<i64>,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let task = self.get_active_task(task_id, tran).await?;
+        self.check_task_handler(&task, operator_ctx, tran).await?;
+
+        self.update_dates(&task, task.due_date, follow_up_date, tran).await
+    }
+
+    /// The sub-task is done by its assignee, or by the one who handles the parent task when it's not assigned.
+    async fn complete_sub_task(&self, task: ApfRuTask, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let task_dao = ApfRuTaskDao::new(tran);
+        let parent_task_id = task.parent_task_id
+            .as_ref()
+            .ok_or(AppError::notfound_error(concat!(file!(), ":", line!())))?;
+        let parent = task_dao.get_by_id(parent_task_id).await?;
+        if task.assignee.is_some() {
+            self.check_assignee(&task, operator_ctx)?;
+        } else {
+            self.check_task_handler(&parent, operator_ctx, tran).await?;
+        }
+        check_sub_tasks_done(&task, tran).await?;
+        let (candidate_users, candidate_groups) = self.sub_task_candidates(&task, &parent, tran).await?;
+
+        self.merge_variables(&task, operator_ctx, tran).await?;
+
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        hi_task_dao.mark_end(&task.id, operator_ctx.user_id.clone()).await?;
+        let ru_ident_dao = ApfRuIdentitylinkDao::new(tran);
+        ru_ident_dao.delete_by_task_id(&task.id).await?;
+        task_dao.delete(&task.id).await?;
+        notify_task(TaskNotifyEvent::Completed, &task, candidate_users, candidate_groups, tran).await?;
+
+        operator_ctx.emit(EngineEvent::TaskCompleted {
+            proc_inst_id: task.proc_inst_id.clone(),
+            task_id: task.id.clone(),
+            element_id: task.element_id_ex()?,
+            user_id: operator_ctx.user_id.clone(),
+        })?;
+
+        Ok(())
+    }
+
+    /// Saves the variables of the context on the task, and reloads all the variables of the process instance.
+    async fn merge_variables(&self, task: &ApfRuTask, operator_ctx: &mut OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let var_dao = ApfRuVariableDao::new(tran);
+        let hi_var_dao = ApfHiVarinstDao::new(tran);
+        let update_time = get_now();
+        let mut events = vec![];
+        for (key, value) in operator_ctx.variables.iter() {
+            let dto = ApfRuVariableDto {
+                var_type: value.get_type(),
+                name: key.to_owned(),
+                value: value.as_str(),
+                proc_inst_id: task.proc_inst_id.clone(),
+                execution_id: Some(task.execution_id.clone()),
+                task_id: Some(task.id.clone()),
+            };
+            let variable = var_dao.create_or_update(&dto).await?;
+            hi_var_dao.create_or_update_by_variable(&variable, update_time).await?;
+
+            events.push(EngineEvent::VariableUpdated {
+                proc_inst_id: task.proc_inst_id.clone(),
+                name: key.to_owned(),
+                value: value.clone(),
+            });
+        }
+
+        for event in events {
+            operator_ctx.emit(event)?;
+        }
+
+        let var_insts = var_dao.find_all_by_proc_inst(&task.proc_inst_id).await?;
+        let vars_map = ApfRuVariable::convert_variables_to_map(&var_insts);
+
+        operator_ctx.variables = vars_map;
+
+        Ok(())
+    }
+
+    async fn get_active_task(&self, task_id: &str, tran: &Transaction<'_>) -> Result<ApfRuTask> {
+        let task_dao = ApfRuTaskDao::new(tran);
+        let task = task_dao.get_by_id(task_id).await?;
+
+        let execution_dao = ApfRuExecutionDao::new(tran);
+        let proc_inst = execution_dao.get_by_id(&task.proc_inst_id).await?;
+        check_not_suspended(&proc_inst)?;
+
+        Ok(task)
+    }
+
+    /// The current user must be one of the candidates, the task without candidates is open to everyone.
+    async fn check_candidate(&self, task: &ApfRuTask, operator_ctx: &OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        let ident_dao = ApfRuIdentitylinkDao::new(tran);
+        let links = ident_dao.find_by_task_id(&task.id).await?;
+        if links.is_empty() {
+            return Ok(());
+        }
+
+        let groups: Vec<&str> = operator_ctx.group_id
+            .as_ref()
+            .map_or(vec![], |g| g.split(',').map(|g| g.trim()).collect());
+        let is_candidate = links.iter().any(|link| {
+            (link.user_id.is_some() && link.user_id == operator_ctx.user_id)
+                || link.group_id.as_ref().map_or(false, |g| groups.contains(&g.as_str()))
+        });
+
+        if !is_candidate {
+            Err(
+                AppError::new(
+                    ErrorCode::UnAuthorized,
+                    Some(
+                        &format!(
+                            "Current user ({}) is not a candidate of the task ({})",
+                            operator_ctx.user_id.clone().unwrap_or("?".to_owned()),
+                            task.id
+                        )
+                    ),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn check_assignee(&self, task: &ApfRuTask, operator_ctx: &OperatorContext) -> Result<()> {
+        if operator_ctx.user_id.is_some() && operator_ctx.user_id != task.assignee {
+            Err(
+                AppError::new(
+                    ErrorCode::UnAuthorized,
+                    Some(
+                        &format!(
+                            "Current user ({}) is not the assignee of the task ({})",
+                            operator_ctx.user_id.clone().unwrap_or("?".to_owned()),
+                            task.id
+                        )
+                    ),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// The assignee of the task, or one of its candidates when it's not assigned.
+    async fn check_task_handler(&self, task: &ApfRuTask, operator_ctx: &OperatorContext, tran: &Transaction<'_>) -> Result<()> {
+        if task.assignee.is_some() {
+            self.check_assignee(task, operator_ctx)
+        } else if operator_ctx.user_id.is_some() {
+            self.check_candidate(task, operator_ctx, tran).await
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_not_delegated(&self, task: &ApfRuTask) -> Result<()> {
+        if task.is_delegation_pending() {
+            Err(
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    Some(&format!("task ({}) is delegated to ({})", task.id, task.assignee.clone().unwrap_or_default())),
+                    concat!(file!(), ":", line!()),
+                    None
+                )
+            )?;
+        }
+
+        Ok(())
+    }
+
+    async fn create_task_oplog(
+        &self,
+        task: &ApfRuTask,
+        op_type: &str,
+        detail: String,
+        operator_ctx: &OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let oplog_dao = ApfHiOplogDao::new(tran);
+        oplog_dao.create(&NewApfHiOplog {
+            op_type: op_type.to_owned(),
+            proc_def_id: Some(task.proc_def_id.clone()),
+            proc_inst_id: Some(task.proc_inst_id.clone()),
+            task_id: Some(task.id.clone()),
+            element_id: task.element_id.clone(),
+            user_id: operator_ctx.user_id.clone(),
+            detail: Some(detail),
+            create_time: get_now(),
+        }).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn assign(
+        &self,
+        task: &ApfRuTask,
+        assignee: Option<String>,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let task_dao = ApfRuTaskDao::new(tran);
+        task_dao.update_assignee(&task.id, assignee.clone()).await?;
+
+        self.assigned(task, assignee, operator_ctx, tran).await
+    }
+
+    /// Records the new assignee in history, and tells the subscribers and the listeners.
+    async fn assigned(
+        &self,
+        task: &ApfRuTask,
+        assignee: Option<String>,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let hi_task_dao = ApfHiTaskinstDao::new(tran);
+        hi_task_dao.update_assignee(&task.id, assignee.clone()).await?;
+
+        operator_ctx.emit(EngineEvent::TaskAssigned {
+            proc_inst_id: task.proc_inst_id.clone(),
+            task_id: task.id.clone(),
+            assignee,
+        })?;
+
+        self.fire_task_listeners(ListenerEvent::ASSIGNMENT, task, operator_ctx, tran).await
+    }
+
+    /// Fires the task listeners of the task with the variables of the process instance.
+    async fn fire_task_listeners(
+        &self,
+        event: &str,
+        task: &ApfRuTask,
+        operator_ctx: &mut OperatorContext,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        let bpmn_process = match &operator_ctx.bpmn_process {
+            Some(bpmn_process) => bpmn_process.clone(),
+            None => self.load_bpmn_process(task, tran).await?,
+        };
+        let element_id = task.element_id.clone().unwrap_or_default();
+        if bpmn_process.get_listeners(&element_id, ListenerType::Task, event).is_empty() {
+            return Ok(());
+        }
+        operator_ctx.bpmn_process = Some(bpmn_process);
+
+        // the listeners see the variables of the process instance
+        let var_dao = ApfRuVariableDao::new(tran);
+        let var_insts = var_dao.find_all_by_proc_inst(&task.proc_inst_id).await?;
+        for (key, value) in ApfRuVariable::convert_variables_to_map(&var_insts) {
+            operator_ctx.variables.entry(key).or_insert(value);
+        }
+
+        let execution_dao = ApfRuExecutionDao::new(tran);
+        let proc_inst = execution_dao.get_by_id(&task.proc_inst_id).await?;
+        BaseOperator::fire_listeners_of_task(Rc::new(proc_inst), event, task, operator_ctx, tran).await
+    }
+
+    async fn update_dates(
+        &self,
+        task: &ApfRuTask,
+        due_date: Option<i64>,
+        follow_up_date: Option<i64>,
+        tran: &Transaction<'_>
+    ) -> Result<()> {
+        ApfRuTaskDao::new(tran).update_dates(&task.id, due_date, follow_up_date).await?;
+        ApfHiTaskinstDao::new(tran).update_dates(&task.id, due_date, follow_up_date).await?;
+
+        Ok(())
+    }
+
+    async fn load_bpmn_process(&self, task: &ApfRuTask, tran: &Transaction<'_>) -> Result<Arc<BpmnProcess>> {
+        let procdef_dao = ApfReProcdefDao::new(tran);
+        let re_def = procdef_dao.get_by_id(&task.proc_def_id).await?;
+
+        self.load_bpmn_by_deployment(&re_def.deployment_id, tran).await
+    }
+
+    async fn load_bpmn_by_deployment(&self, deployment_id: &str, tran: &Transaction<'_>) -> Result<Arc<BpmnProcess>> {
+        let repository_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_repository_service();
+        let bpmn_process = repository_service.load_bpmn_by_deployment(deployment_id, tran).await?;
+
+        Ok(Arc::new(bpmn_process))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::DeleteReason;
+    use crate::service::engine::DEFAULT_PRIORITY;
+    use crate::service::engine::query::TaskQuery;
+    use std::time::Duration;
+    use crate::service::engine::tests::{create_test_deploy, delete_test_deploy};
+    use super::*;
+
+    #[tokio::test]
+    async fn test_complete() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_2.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(
+                &procdef.key,
+                &procdef.company_id,
+                Some("process_biz_key_2".to_owned()),
+                &mut operator_ctx,
+                &tran
+            )
+            .await
+            .unwrap();
+
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_1".to_owned()))
+            .fetch_one()
+            .await.unwrap();
+
+        let mut variables = HashMap::new();
+        variables.insert("approval_pass".to_owned(), WrappedValue::Bool(false));
+        
+        let mut operator_ctx = OperatorContext::new(
+            None,
+            Some("user_1".to_owned()),
+            variables
+        );
+        let task_service = TaskService::new();
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_claim() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_2.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_1".to_owned()))
+            .fetch_one()
+            .await.unwrap();
+
+        // only the candidates can claim
+        let mut operator_ctx = OperatorContext::new(None, Some("user_3".to_owned()), HashMap::new());
+        assert!(task_service._claim(&task.id, &mut operator_ctx, &tran).await.is_err());
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._claim(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        // the claimed task is not offered to the other candidates
+        let rst = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_2".to_owned()))
+            .count("t1.id")
+            .fetch_count()
+            .await.unwrap();
+        assert_eq!(rst, 0);
+        let rst = TaskQuery::new(&tran).assignee("user_1").proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(rst.len(), 1);
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        assert!(task_service._claim(&task.id, &mut operator_ctx, &tran).await.is_err());
+        assert!(task_service._complete(&task.id, &mut operator_ctx, &tran).await.is_err());
+
+        // hand over to user_2 and give it back to the candidates
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._set_assignee(&task.id, Some("user_2".to_owned()), &mut operator_ctx, &tran).await.unwrap();
+        let hi_task = ApfHiTaskinstDao::new(&tran).get_by_id(&task.id).await.unwrap();
+        assert_eq!(hi_task.assignee, Some("user_2".to_owned()));
+
+        assert!(task_service._unclaim(&task.id, &mut operator_ctx, &tran).await.is_err());
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        task_service._unclaim(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_1".to_owned()))
+            .fetch_one()
+            .await.unwrap();
+        assert_eq!(task.assignee, None);
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_claim() {
+        log4rs_macros::prepare_log();
+
+        // the candidates claim in their own transactions, which only see the committed task
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+        let procdef = create_test_deploy("bpmn/process_2.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+        tran.commit().await.unwrap();
+
+        let task_service = TaskService::new();
+        let mut conn_1 = db::get_connect().await.unwrap();
+        let tran_1 = conn_1.transaction().await.unwrap();
+        let mut conn_2 = db::get_connect().await.unwrap();
+        let tran_2 = conn_2.transaction().await.unwrap();
+
+        let task = TaskQuery::new(&tran_1).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut operator_ctx_1 = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._claim(&task.id, &mut operator_ctx_1, &tran_1).await.unwrap();
+
+        // user_2 still reads the task unassigned, and waits on the row until user_1 commits
+        let mut operator_ctx_2 = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        let (rst, _) = tokio::join!(
+            task_service._claim(&task.id, &mut operator_ctx_2, &tran_2),
+            async {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                tran_1.commit().await.unwrap();
+            }
+        );
+        tran_2.rollback().await.unwrap();
+        let events_2 = operator_ctx_2.take_events();
+
+        let tran = conn.transaction().await.unwrap();
+        let assignee = ApfRuTaskDao::new(&tran).get_by_id(&task.id).await.unwrap().assignee;
+        delete_test_deploy(&procdef.deployment_id, &tran).await;
+        tran.commit().await.unwrap();
+
+        assert!(rst.is_err());
+        assert!(events_2.is_empty());
+        assert_eq!(assignee, Some("user_1".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_delegate_and_resolve() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_2.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_1".to_owned()))
+            .fetch_one()
+            .await.unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._claim(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        task_service._delegate(&task.id, "user_3", &mut operator_ctx, &tran).await.unwrap();
+
+        // the delegate can not complete the task
+        let task = TaskQuery::new(&tran).assignee("user_3").fetch_one().await.unwrap();
+        assert_eq!(task.owner, Some("user_1".to_owned()));
+        let mut operator_ctx = OperatorContext::new(None, Some("user_3".to_owned()), HashMap::new());
+        assert!(task_service._complete(&task.id, &mut operator_ctx, &tran).await.is_err());
+
+        let mut variables = HashMap::new();
+        variables.insert("approval_pass".to_owned(), WrappedValue::Bool(true));
+        let mut operator_ctx = OperatorContext::new(None, Some("user_3".to_owned()), variables);
+        task_service._resolve(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        let hi_task = ApfHiTaskinstDao::new(&tran).get_by_id(&task.id).await.unwrap();
+        assert_eq!(hi_task.assignee, Some("user_1".to_owned()));
+        assert_eq!(hi_task.delegation, Some(DelegationState::RESOLVED.to_owned()));
+        assert_eq!(hi_task.end_time, None);
+        let oplogs = ApfHiOplogDao::new(&tran).find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(oplogs.len(), 2);
+
+        // the owner completes the task
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_assignment_listeners() {
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_assignment.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut variables = HashMap::new();
+        variables.insert("assign_count".to_owned(), WrappedValue::Int(0));
+        let mut operator_ctx = OperatorContext::new(None, None, variables);
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let assign_count = || async {
+            let var_insts = ApfRuVariableDao::new(&tran).find_all_by_proc_inst(&procinst.id).await.unwrap();
+            ApfRuVariable::convert_variables_to_map(&var_insts).get("assign_count").cloned()
+        };
+
+        // the assignee of the bpmn is an assignment
+        assert_eq!(assign_count().await, Some(WrappedValue::Int(1)));
+
+        let task_service = TaskService::new();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        assert_eq!(assign_count().await, Some(WrappedValue::Int(1)));
+
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        task_service._claim(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        assert_eq!(assign_count().await, Some(WrappedValue::Int(2)));
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        task_service._unclaim(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        assert_eq!(assign_count().await, Some(WrappedValue::Int(3)));
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        task_service._delegate(&task.id, "user_3", &mut operator_ctx, &tran).await.unwrap();
+        assert_eq!(assign_count().await, Some(WrappedValue::Int(4)));
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_3".to_owned()), HashMap::new());
+        task_service._resolve(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        assert_eq!(assign_count().await, Some(WrappedValue::Int(5)));
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_add_sign() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_reject.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        // the before signer handles the task first
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut user_2_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        let signs = task_service
+            ._add_sign(&task.id, vec!["user_5".to_owned()], AddSignMode::Before, &mut user_2_ctx, &tran)
+            .await
+            .unwrap();
+        assert!(task_service._complete(&task.id, &mut user_2_ctx, &tran).await.is_err());
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_5".to_owned()), HashMap::new());
+        task_service._complete(&signs[0].id, &mut operator_ctx, &tran).await.unwrap();
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].id, task.id);
+
+        // the after signer handles the task after the original one
+        let signs = task_service
+            ._add_sign(&task.id, vec!["user_6".to_owned()], AddSignMode::After, &mut user_2_ctx, &tran)
+            .await
+            .unwrap();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_6".to_owned()), HashMap::new());
+        assert!(task_service._complete(&signs[0].id, &mut operator_ctx, &tran).await.is_err());
+
+        task_service._complete(&task.id, &mut user_2_ctx, &tran).await.unwrap();
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].element_id, Some("approval_1".to_owned()));
+
+        task_service._complete(&signs[0].id, &mut operator_ctx, &tran).await.unwrap();
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 2);
+
+        // the parallel signer works with the accountant
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_3".to_owned()))
+            .fetch_one()
+            .await.unwrap();
+        let mut user_3_ctx = OperatorContext::new(None, Some("user_3".to_owned()), HashMap::new());
+        let signs = task_service
+            ._add_sign(&task.id, vec!["user_7".to_owned()], AddSignMode::Parallel, &mut user_3_ctx, &tran)
+            .await
+            .unwrap();
+        let hi_task = ApfHiTaskinstDao::new(&tran).get_by_id(&signs[0].id).await.unwrap();
+        assert_eq!(hi_task.sign_parent_id, Some(task.id.clone()));
+        assert_eq!(hi_task.sign_mode, Some("parallel".to_owned()));
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_7".to_owned()), HashMap::new());
+        task_service._complete(&signs[0].id, &mut operator_ctx, &tran).await.unwrap();
+        task_service._complete(&task.id, &mut user_3_ctx, &tran).await.unwrap();
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].element_id, Some("lawyer_approval_1".to_owned()));
+
+        let oplogs = ApfHiOplogDao::new(&tran).find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(oplogs.len(), 3);
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_comment_and_attachment() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_reject.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let user_1 = Some("user_1".to_owned());
+        task_service._add_comment(&task.id, "同意，请注意预算", user_1.clone(), &tran).await.unwrap();
+        let attachment = task_service
+            ._create_attachment(&task.id, "budget.txt", None, AttachmentContent::Bytes(b"1000".to_vec()), user_1.clone(), &tran)
+            .await
+            .unwrap();
+        task_service
+            ._create_attachment(&task.id, "invoice", None, AttachmentContent::Url("https://example.com/1".to_owned()), user_1, &tran)
+            .await
+            .unwrap();
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        // the comments and attachments are kept after the task is completed
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        task_service._add_comment(&task.id, "不同意", Some("user_2".to_owned()), &tran).await.unwrap();
+
+        let comments = ApfHiCommentDao::new(&tran).find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].message, Some("同意，请注意预算".to_owned()));
+
+        let content = task_service._get_attachment_content(&attachment.id, &tran).await.unwrap();
+        assert_eq!(content, Some(b"1000".to_vec()));
+        let attachments = ApfHiAttachmentDao::new(&tran).find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(attachments.len(), 2);
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sub_task() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_reject.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut user_1_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        let blocking = task_service
+            ._create_sub_task(&task.id, "prepare the invoice", Some("user_5".to_owned()), true, &mut user_1_ctx, &tran)
+            .await
+            .unwrap();
+        task_service
+            ._create_sub_task(&task.id, "check the budget", None, false, &mut user_1_ctx, &tran)
+            .await
+            .unwrap();
+
+        let sub_tasks = TaskQuery::new(&tran).parent_task_id(&task.id).fetch_all().await.unwrap();
+        assert_eq!(sub_tasks.len(), 2);
+
+        // the blocking sub-task must be done first
+        assert!(task_service._complete(&task.id, &mut user_1_ctx, &tran).await.is_err());
+        assert!(task_service._complete(&blocking.id, &mut user_1_ctx, &tran).await.is_err());
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_5".to_owned()), HashMap::new());
+        task_service._complete(&blocking.id, &mut operator_ctx, &tran).await.unwrap();
+        let hi_task = ApfHiTaskinstDao::new(&tran).get_by_id(&blocking.id).await.unwrap();
+        assert_eq!(hi_task.parent_task_id, Some(task.id.clone()));
+        assert!(hi_task.end_time.is_some());
+
+        task_service._complete(&task.id, &mut user_1_ctx, &tran).await.unwrap();
+        // the sub-task which is not blocking is closed with the parent
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].element_id, Some("approval_1".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_priority_and_due_date() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_due_date.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        operator_ctx.variables.insert("urgent".to_owned(), WrappedValue::Bool(true));
+        let before = get_now();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        assert_eq!(task.priority, 80);
+        let due_date = task.due_date.unwrap();
+        assert!(due_date >= before + 3 * 24 * 3600 * 1000);
+        assert!(task.follow_up_date.unwrap() < due_date);
+
+        let overdue = TaskQuery::new(&tran).proc_inst_id(&procinst.id).overdue().fetch_all().await.unwrap();
+        assert!(overdue.is_empty());
+
+        // the candidate can not change the task which is assigned
+        let task_service = TaskService::new();
+        let mut user_2_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        assert!(task_service._set_priority(&task.id, 10, &mut user_2_ctx, &tran).await.is_err());
+
+        let mut user_1_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._set_priority(&task.id, 10, &mut user_1_ctx, &tran).await.unwrap();
+        task_service._set_due_date(&task.id, Some(before - 1000), &mut user_1_ctx, &tran).await.unwrap();
+
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).overdue().fetch_one().await.unwrap();
+        assert_eq!(task.priority, 10);
+        assert!(task.follow_up_date.is_some());
+        let hi_task = ApfHiTaskinstDao::new(&tran).get_by_id(&task.id).await.unwrap();
+        assert_eq!(hi_task.due_date, Some(before - 1000));
+
+        // the due date of the next task is evaluated with the variables
+        task_service._complete(&task.id, &mut user_1_ctx, &tran).await.unwrap();
+        let tasks = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .order_by_priority_desc()
+            .fetch_all()
+            .await
+            .unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].priority, DEFAULT_PRIORITY);
+        assert!(tasks[0].due_date.unwrap() < before + 3 * 3600 * 1000);
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reject() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_reject.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        for user in ["user_1", "user_2", "user_4"] {
+            let task = TaskQuery::new(&tran)
+                .proc_inst_id(&procinst.id)
+                .candidate_user(Some(user.to_owned()))
+                .fetch_one()
+                .await.unwrap();
+            let mut operator_ctx = OperatorContext::new(None, Some(user.to_owned()), HashMap::new());
+            task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        }
+
+        // the lawyer has completed, the accountant rejects back to the department approval
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_3".to_owned()))
+            .fetch_one()
+            .await.unwrap();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_3".to_owned()), HashMap::new());
+        task_service
+            ._reject(&task.id, "approval_1", Some("missing invoice".to_owned()), &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let hi_task = ApfHiTaskinstDao::new(&tran).get_by_id(&task.id).await.unwrap();
+        assert_eq!(hi_task.delete_reason, Some(DeleteReason::REJECTED.to_owned()));
+        let comments = ApfHiCommentDao::new(&tran).find_by_task(&task.id).await.unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].comment_type, CommentType::REJECT);
+        assert_eq!(comments[0].message, Some("missing invoice".to_owned()));
+        assert_eq!(comments[0].user_id, Some("user_3".to_owned()));
+
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].element_id, Some("approval_1".to_owned()));
+        let execs = ApfRuExecutionDao::new(&tran).find_children_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(execs.len(), 1);
+
+        // the target must be completed before the current task
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        let rst = task_service
+            ._reject(&tasks[0].id, "accountant_approval_1", None, &mut operator_ctx, &tran)
+            .await;
+        assert!(rst.is_err());
+
+        task_service._reject(&tasks[0].id, "apply_1", None, &mut operator_ctx, &tran).await.unwrap();
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_1".to_owned()))
+            .fetch_one()
+            .await.unwrap();
+        assert_eq!(task.element_id, Some("apply_1".to_owned()));
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_default_flow() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_default_flow.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let task_service = TaskService::new();
+
+        // the condition of the task outflow, the condition of the gateway outflow, both default flows
+        let cases = [
+            (20000, Some("director_approval_1")),
+            (5000, Some("manager_approval_1")),
+            (100, None),
+        ];
+        for (amount, next_element_id) in cases {
+            let mut operator_ctx = OperatorContext::default();
+            let procinst = rt_service
+                ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+                .await
+                .unwrap();
+
+            let task = TaskQuery::new(&tran)
+                .proc_inst_id(&procinst.id)
+                .candidate_user(Some("user_1".to_owned()))
+                .fetch_one()
+                .await.unwrap();
+            assert_eq!(task.element_id, Some("apply_1".to_owned()));
+
+            let mut variables = HashMap::new();
+            variables.insert("amount".to_owned(), WrappedValue::Int(amount));
+            let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), variables);
+            task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+            let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+            match next_element_id {
+                Some(element_id) => {
+                    assert_eq!(tasks.len(), 1);
+                    assert_eq!(tasks[0].element_id, Some(element_id.to_owned()));
+                },
+                None => {
+                    assert!(tasks.is_empty());
+                    assert!(ApfRuExecutionDao::new(&tran).get_by_id(&procinst.id).await.is_err());
+                },
+            }
+        }
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_withdraw() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_reject.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+        let next_task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_one().await.unwrap();
+
+        // only the one who completed the task can withdraw it
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        assert!(task_service._withdraw(&task.id, &mut operator_ctx, &tran).await.is_err());
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._withdraw(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        // the completion stays in the history, and the withdrawal is recorded besides it
+        let hi_task_dao = ApfHiTaskinstDao::new(&tran);
+        let hi_task = hi_task_dao.get_by_id(&task.id).await.unwrap();
+        assert!(hi_task.end_time.is_some());
+        assert_eq!(hi_task.delete_reason, None);
+        let hi_next_task = hi_task_dao.get_by_id(&next_task.id).await.unwrap();
+        assert_eq!(hi_next_task.delete_reason, Some(DeleteReason::WITHDRAWN.to_owned()));
+        let oplogs = ApfHiOplogDao::new(&tran).find_by_proc_inst(&procinst.id).await.unwrap();
+        assert_eq!(oplogs.iter().filter(|log| log.op_type == OpType::WITHDRAW_TASK).count(), 1);
+
+        // the same completion can't be withdrawn twice
+        assert!(task_service._withdraw(&task.id, &mut operator_ctx, &tran).await.is_err());
+
+        // the task is reopened for the same user
+        let task = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .assignee("user_1")
+            .fetch_one()
+            .await.unwrap();
+        assert_eq!(task.element_id, Some("apply_1".to_owned()));
+        assert_eq!(task.assignee, Some("user_1".to_owned()));
+        let rst = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_1".to_owned()))
+            .count("t1.id")
+            .fetch_count()
+            .await.unwrap();
+        assert_eq!(rst, 0);
+
+        // the completion can not be withdrawn after one of the next tasks is completed
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+
+        let mut completed = vec![task.id];
+        for user in ["user_2", "user_4"] {
+            let task = TaskQuery::new(&tran)
+                .proc_inst_id(&procinst.id)
+                .candidate_user(Some(user.to_owned()))
+                .fetch_one()
+                .await.unwrap();
+            let mut operator_ctx = OperatorContext::new(None, Some(user.to_owned()), HashMap::new());
+            task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+            completed.push(task.id);
+        }
+
+        let mut operator_ctx = OperatorContext::new(None, Some("user_2".to_owned()), HashMap::new());
+        assert!(task_service._withdraw(&completed[1], &mut operator_ctx, &tran).await.is_err());
+
+        tran.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_parallel() {
+        log4rs_macros::prepare_log();
+
+        let mut conn = db::get_connect().await.unwrap();
+        let tran = conn.transaction().await.unwrap();
+
+        let procdef = create_test_deploy("bpmn/process_withdraw_parallel.bpmn.xml", &tran).await;
+        let rt_service = ProcessEngine::new(ProcessEngine::DEFAULT_ENGINE).get_runtime_service();
+        let mut operator_ctx = OperatorContext::default();
+        let procinst = rt_service
+            ._start_process_instance_by_key(&procdef.key, &procdef.company_id, None, &mut operator_ctx, &tran)
+            .await
+            .unwrap();
+
+        let task_service = TaskService::new();
+        let mut completed = vec![];
+        for user in ["user_1", "user_3"] {
+            let task = TaskQuery::new(&tran)
+                .proc_inst_id(&procinst.id)
+                .candidate_user(Some(user.to_owned()))
+                .fetch_one()
+                .await.unwrap();
+            let mut operator_ctx = OperatorContext::new(None, Some(user.to_owned()), HashMap::new());
+            task_service._complete(&task.id, &mut operator_ctx, &tran).await.unwrap();
+            completed.push(task.id);
+        }
+        let approval_b = TaskQuery::new(&tran)
+            .proc_inst_id(&procinst.id)
+            .candidate_user(Some("user_4".to_owned()))
+            .fetch_one()
+            .await.unwrap();
+
+        // the other branch has moved on since, which doesn't matter
+        let mut operator_ctx = OperatorContext::new(None, Some("user_1".to_owned()), HashMap::new());
+        task_service._withdraw(&completed[0], &mut operator_ctx, &tran).await.unwrap();
+
+        let tasks = TaskQuery::new(&tran).proc_inst_id(&procinst.id).fetch_all().await.unwrap();
+        let mut element_ids: Vec<String> = tasks.iter().map(|t| t.element_id.clone().unwrap()).collect();
+        element_ids.sort();
+        assert_eq!(element_ids, vec!["apply_a".to_owned(), "approval_b".to_owned()]);
+        assert!(tasks.iter().any(|t| t.id == approval_b.id));
+
+        // and the completion of the other branch is still withdrawable
+        let mut operator_ctx = OperatorContext::new(None, Some("user_3".to_owned()), HashMap::new());
+        task_service._withdraw(&completed[1], &mut operator_ctx, &tran).await.unwrap();
+        let task = TaskQuery::new(&tran).proc_inst_id(&procinst.id).assignee("user_3").fetch_one().await.unwrap();
+        assert_eq!(task.element_id, Some("apply_b".to_owned()));
 
         tran.rollback().await.unwrap();
     }